@@ -0,0 +1,8 @@
+-- Per-tenant AI provider selection. NULL columns fall back to the server's env.
+-- llm_api_key_env holds the NAME of the env var with the key, never the key itself.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS llm_provider TEXT;
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS llm_model TEXT;
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS llm_base_url TEXT;
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS llm_api_key_env TEXT;
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS llm_deployment TEXT;
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS llm_api_version TEXT;
@@ -0,0 +1,3 @@
+-- What to do when the AI reviewer returns no schema-valid answer:
+-- 'fail_closed' (hold for human review), 'fail_open' (warn and continue), or 'retry[:n]'.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS ai_failure_policy TEXT NOT NULL DEFAULT 'fail_closed';
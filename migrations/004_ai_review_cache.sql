@@ -0,0 +1,12 @@
+-- AI verdicts keyed by hash(normalized chunk, model, prompt version, rule-pack version).
+CREATE TABLE IF NOT EXISTS ai_review_cache (
+    cache_key TEXT PRIMARY KEY,
+    model TEXT NOT NULL,
+    prompt_version TEXT NOT NULL,
+    rule_pack_version TEXT NOT NULL,
+    result JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_ai_review_cache_expires_at ON ai_review_cache (expires_at);
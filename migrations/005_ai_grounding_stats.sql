@@ -0,0 +1,20 @@
+-- Per-review counts of AI citations checked against the diff.
+CREATE TABLE IF NOT EXISTS ai_grounding_stats (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID REFERENCES tenants(id),
+    model TEXT NOT NULL,
+    cited INT NOT NULL,
+    grounded INT NOT NULL,
+    relocated INT NOT NULL,
+    downgraded INT NOT NULL,
+    dropped INT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Grounding rate per model: share of AI findings anchored to a changed line.
+CREATE OR REPLACE VIEW ai_grounding_rate AS
+SELECT model,
+       SUM(cited) AS cited,
+       SUM(grounded + relocated)::FLOAT / NULLIF(SUM(cited), 0) AS grounding_rate
+FROM ai_grounding_stats
+GROUP BY model;
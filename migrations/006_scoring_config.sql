@@ -0,0 +1,3 @@
+-- Per-tenant overrides for the risk scoring engine (see `scoring::ScoringConfig`).
+-- NULL uses the built-in weights; any omitted key falls back to its default.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS scoring_config JSONB;
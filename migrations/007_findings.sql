@@ -0,0 +1,39 @@
+-- One row per distinct finding per repository, keyed by its fingerprint.
+CREATE TABLE IF NOT EXISTS findings (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID REFERENCES tenants(id),
+    repo_name TEXT NOT NULL,
+    fingerprint TEXT NOT NULL,
+    rule_id TEXT NOT NULL,
+    category TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    file TEXT,
+    line INT,
+    state TEXT NOT NULL DEFAULT 'open',
+    suppressed_reason TEXT,
+    last_pr INT NOT NULL,
+    first_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_seen TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    fixed_at TIMESTAMPTZ,
+    UNIQUE (tenant_id, repo_name, fingerprint)
+);
+
+CREATE INDEX IF NOT EXISTS idx_findings_repo_pr ON findings(tenant_id, repo_name, last_pr);
+
+-- Every state change, for remediation evidence.
+CREATE TABLE IF NOT EXISTS finding_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    finding_id UUID NOT NULL REFERENCES findings(id) ON DELETE CASCADE,
+    from_state TEXT,
+    to_state TEXT NOT NULL,
+    pr_number INT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- How long each PHI finding was open. Open findings count up to now.
+CREATE OR REPLACE VIEW phi_finding_exposure AS
+SELECT tenant_id, repo_name, fingerprint, rule_id, severity, state, file,
+       first_seen, fixed_at,
+       COALESCE(fixed_at, NOW()) - first_seen AS open_for
+FROM findings
+WHERE rule_id LIKE 'GHG-PHI-%';
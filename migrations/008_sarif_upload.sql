@@ -0,0 +1,2 @@
+-- Upload each scan as SARIF to GitHub code scanning (needs security_events: write).
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS upload_sarif BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,17 @@
+-- Report formats per destination (see `export::ReportSettings`), e.g.
+-- {"ci": ["junit", "gitlab"], "compliance": ["csv"]}. NULL keeps the JSON archive only.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS report_formats JSONB;
+
+-- Rendered reports, fetched by CI jobs and compliance exports.
+CREATE TABLE IF NOT EXISTS scan_reports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID REFERENCES tenants(id),
+    repo_name TEXT NOT NULL,
+    pr_number INT NOT NULL,
+    destination TEXT NOT NULL,
+    format TEXT NOT NULL,
+    content TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_scan_reports_pr ON scan_reports(tenant_id, repo_name, pr_number);
@@ -0,0 +1,4 @@
+-- Markdown template for PR reviews (see `markdown::Template`). Placeholders:
+-- {{header}} {{notes}} {{status}} {{risk_score}} {{summary}} {{score_breakdown}}
+-- {{controls}} {{findings}}. NULL uses the built-in layout.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS review_template TEXT;
@@ -0,0 +1,7 @@
+-- ChatOps commands are chained into audit_logs next to scans.
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS kind TEXT NOT NULL DEFAULT 'scan';
+CREATE INDEX IF NOT EXISTS idx_audit_logs_pr ON audit_logs(tenant_id, repo_name, pr_number, created_at);
+
+-- Waivers from `/ghosthealth waive`: who granted them and until when.
+ALTER TABLE findings ADD COLUMN IF NOT EXISTS suppressed_until TIMESTAMPTZ;
+ALTER TABLE findings ADD COLUMN IF NOT EXISTS suppressed_by TEXT;
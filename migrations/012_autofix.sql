@@ -0,0 +1,2 @@
+-- Open a PR with verified fixes (see `autofix`) when a scan finds a violation.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS autofix BOOLEAN NOT NULL DEFAULT FALSE;
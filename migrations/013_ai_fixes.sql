@@ -0,0 +1,17 @@
+-- Ask the AI reviewer for fixes to findings no deterministic fixer handles.
+ALTER TABLE tenants ADD COLUMN IF NOT EXISTS ai_fixes BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Every AI-proposed fix and whether it passed verification (see `ai_fix`).
+CREATE TABLE IF NOT EXISTS ai_fix_attempts (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    tenant_id UUID REFERENCES tenants(id),
+    repo_name TEXT NOT NULL,
+    pr_number INT NOT NULL,
+    model TEXT NOT NULL,
+    rule_id TEXT NOT NULL,
+    fingerprint TEXT,
+    location TEXT,
+    accepted BOOLEAN NOT NULL,
+    reason TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
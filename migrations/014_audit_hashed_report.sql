@@ -0,0 +1,3 @@
+-- The exact report text each entry was hashed over. `report` is JSONB, which
+-- reorders keys and drops whitespace, so it cannot be re-hashed byte for byte.
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS hashed_report TEXT;
@@ -0,0 +1,30 @@
+-- Each entry has exactly one successor: two appends that read the same tip
+-- would fork the chain, so the second one must fail instead.
+--
+-- Appends used to race, so existing chains may already have forked: several
+-- rows sharing a `previous_hash`. Rows are never rewritten (that would break
+-- their hashes) or deleted; all but one per `previous_hash` are marked
+-- `forked` and left out of the index. The one kept is the branch the chain
+-- continued from, or the earliest if none did.
+ALTER TABLE audit_logs ADD COLUMN IF NOT EXISTS forked BOOLEAN NOT NULL DEFAULT FALSE;
+
+WITH ranked AS (
+    SELECT a.id,
+           ROW_NUMBER() OVER (
+               PARTITION BY a.previous_hash
+               ORDER BY EXISTS (
+                            SELECT 1 FROM audit_logs n WHERE n.previous_hash = a.current_hash
+                        ) DESC,
+                        a.created_at,
+                        a.id
+           ) AS position
+    FROM audit_logs a
+    WHERE a.previous_hash IS NOT NULL
+)
+UPDATE audit_logs
+SET forked = TRUE
+FROM ranked
+WHERE audit_logs.id = ranked.id AND ranked.position > 1;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_audit_logs_previous_hash
+    ON audit_logs(previous_hash) WHERE NOT forked;
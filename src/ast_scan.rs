@@ -1,58 +1,745 @@
-use syn::{visit::Visit, File, ItemFn, Attribute};
+use crate::models::{Analyzer, Finding, Location, Severity};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 use std::fs;
+use std::path::{Path, PathBuf};
+use syn::spanned::Spanned;
+use syn::{
+    visit::Visit, Attribute, Expr, ExprCall, ExprUnary, ExprUnsafe, FnArg, ImplItemFn, ItemFn,
+    ItemForeignMod, ItemImpl, ItemStatic, ItemTrait, Local, Pat, Signature, Type, UnOp,
+};
 use walkdir::WalkDir;
-use crate::models::Issue;
 
-pub fn scan_rust_project(path: &str) -> Vec<Issue> {
+pub fn scan_rust_project(path: &str) -> Vec<Finding> {
+    let inventory = unsafe_inventory(path);
     let mut issues = vec![];
 
-    for entry in WalkDir::new(path).into_iter().filter_map(Result::ok) {
-        if entry.path().extension().map(|e| e == "rs").unwrap_or(false) {
-            let content = fs::read_to_string(entry.path()).unwrap_or_default();
-            if let Ok(parsed) = syn::parse_file(&content) {
-                let mut visitor = RustVisitor::default();
-                visitor.visit_file(&parsed);
-                issues.extend(visitor.issues);
-            }
+    for entry in rust_files(Path::new(path)) {
+        let content = fs::read_to_string(&entry).unwrap_or_default();
+        issues.extend(scan_source(&entry.display().to_string(), &content));
+    }
+
+    for (name, counts) in &inventory.crates {
+        if !counts.forbids_unsafe && counts.total() == 0 {
+            issues.push(Finding::new(
+                Analyzer::Ast,
+                "UNSAFE_NOT_FORBIDDEN",
+                Severity::Low,
+                format!(
+                    "Crate {} has no unsafe code but does not declare #![forbid(unsafe_code)]",
+                    name
+                ),
+            ));
         }
     }
 
     issues
 }
 
-#[derive(Default)]
+/// Scans a single Rust source file. Files that fail to parse produce no issues.
+pub fn scan_source(file: &str, content: &str) -> Vec<Finding> {
+    match syn::parse_file(content) {
+        Ok(parsed) => {
+            let mut visitor = RustVisitor::new(file);
+            visitor.visit_file(&parsed);
+            visitor.issues
+        }
+        Err(_) => vec![],
+    }
+}
+
+/// Whether the source declares `#![forbid(unsafe_code)]` at the top level.
+pub fn forbids_unsafe_code(content: &str) -> bool {
+    syn::parse_file(content)
+        .map(|parsed| parsed.attrs.iter().any(is_forbid_unsafe))
+        .unwrap_or(false)
+}
+
+// ─────────────────────────────────────────────
+// Per-crate unsafe inventory
+// ─────────────────────────────────────────────
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct UnsafeCounts {
+    pub forbids_unsafe: bool,
+    pub unsafe_fns: usize,
+    pub unsafe_blocks: usize,
+    pub unsafe_impls: usize,
+    pub unsafe_traits: usize,
+    pub extern_blocks: usize,
+    pub no_mangle: usize,
+    pub raw_derefs: usize,
+    pub transmutes: usize,
+}
+
+impl UnsafeCounts {
+    pub fn total(&self) -> usize {
+        self.unsafe_fns
+            + self.unsafe_blocks
+            + self.unsafe_impls
+            + self.unsafe_traits
+            + self.extern_blocks
+            + self.no_mangle
+            + self.raw_derefs
+            + self.transmutes
+    }
+
+    fn add_issues(&mut self, issues: &[Finding]) {
+        for issue in issues {
+            match issue.category.as_str() {
+                "UNSAFE_FUNCTION" => self.unsafe_fns += 1,
+                "UNSAFE_BLOCK" => self.unsafe_blocks += 1,
+                "UNSAFE_IMPL" => self.unsafe_impls += 1,
+                "UNSAFE_TRAIT" => self.unsafe_traits += 1,
+                "EXTERN_BLOCK" => self.extern_blocks += 1,
+                "NO_MANGLE" => self.no_mangle += 1,
+                "RAW_POINTER_DEREF" => self.raw_derefs += 1,
+                "TRANSMUTE" => self.transmutes += 1,
+                _ => {}
+            }
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct UnsafeInventory {
+    pub crates: BTreeMap<String, UnsafeCounts>,
+}
+
+/// Change in a single crate's unsafe usage between two inventories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InventoryChange {
+    pub crate_name: String,
+    pub before: usize,
+    pub after: usize,
+    pub forbids_unsafe: bool,
+}
+
+impl InventoryChange {
+    pub fn delta(&self) -> i64 {
+        self.after as i64 - self.before as i64
+    }
+}
+
+impl fmt::Display for InventoryChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} → {} ({:+}){}",
+            self.crate_name,
+            self.before,
+            self.after,
+            self.delta(),
+            if self.forbids_unsafe {
+                ", forbid(unsafe_code)"
+            } else {
+                ""
+            }
+        )
+    }
+}
+
+impl UnsafeInventory {
+    /// Compares a base inventory against the PR head, one entry per crate seen in either.
+    pub fn compare(base: &UnsafeInventory, head: &UnsafeInventory) -> Vec<InventoryChange> {
+        let mut names: Vec<&String> = base.crates.keys().chain(head.crates.keys()).collect();
+        names.sort();
+        names.dedup();
+
+        names
+            .into_iter()
+            .map(|name| {
+                let after = head.crates.get(name).copied().unwrap_or_default();
+                InventoryChange {
+                    crate_name: name.clone(),
+                    before: base.crates.get(name).map(|c| c.total()).unwrap_or(0),
+                    after: after.total(),
+                    forbids_unsafe: after.forbids_unsafe,
+                }
+            })
+            .collect()
+    }
+
+    /// Findings for crates whose unsafe surface grew in the PR.
+    pub fn regressions(base: &UnsafeInventory, head: &UnsafeInventory) -> Vec<Finding> {
+        Self::compare(base, head)
+            .into_iter()
+            .filter(|change| change.delta() > 0)
+            .map(|change| {
+                Finding::new(
+                    Analyzer::Ast,
+                    "UNSAFE_INCREASE",
+                    Severity::Medium,
+                    format!("Unsafe usage increased in {}", change),
+                )
+            })
+            .collect()
+    }
+}
+
+pub fn unsafe_inventory(path: &str) -> UnsafeInventory {
+    let root = Path::new(path);
+    let mut inventory = UnsafeInventory::default();
+
+    for file in rust_files(root) {
+        let Some(manifest_dir) = crate_root(&file, root) else {
+            continue;
+        };
+        let content = fs::read_to_string(&file).unwrap_or_default();
+        let counts = inventory
+            .crates
+            .entry(crate_name(&manifest_dir))
+            .or_insert_with(|| UnsafeCounts {
+                forbids_unsafe: manifest_forbids_unsafe(&manifest_dir),
+                ..Default::default()
+            });
+
+        counts.add_issues(&scan_source(&file.display().to_string(), &content));
+
+        let is_crate_root = ["src/lib.rs", "src/main.rs"]
+            .iter()
+            .any(|root_file| manifest_dir.join(root_file) == file);
+        if is_crate_root && forbids_unsafe_code(&content) {
+            counts.forbids_unsafe = true;
+        }
+    }
+
+    inventory
+}
+
+/// Inventory of just `files` (path, content), e.g. the changed files of a PR
+/// at one side of the diff. Files are attributed to the directory above
+/// their `src/`, `tests/`, `benches/` or `examples/` directory, since the
+/// manifests are not at hand; comparing two such inventories gives the same
+/// per-crate delta as comparing whole trees.
+pub fn inventory_of<'a>(files: impl IntoIterator<Item = (&'a str, &'a str)>) -> UnsafeInventory {
+    let mut inventory = UnsafeInventory::default();
+    for (path, content) in files {
+        let dir = crate_dir(path);
+        let counts = inventory
+            .crates
+            .entry(if dir.is_empty() {
+                ".".to_string()
+            } else {
+                dir.clone()
+            })
+            .or_default();
+        counts.add_issues(&scan_source(path, content));
+
+        let root = |file: &str| {
+            if dir.is_empty() {
+                file.to_string()
+            } else {
+                format!("{}/{}", dir, file)
+            }
+        };
+        if (path == root("src/lib.rs") || path == root("src/main.rs"))
+            && forbids_unsafe_code(content)
+        {
+            counts.forbids_unsafe = true;
+        }
+    }
+    inventory
+}
+
+/// The crate directory of a repository-relative `path`; empty for the root.
+fn crate_dir(path: &str) -> String {
+    let parts: Vec<&str> = path.split('/').collect();
+    let parent = parts.len().saturating_sub(1);
+    let end = parts[..parent]
+        .iter()
+        .rposition(|p| matches!(*p, "src" | "tests" | "benches" | "examples"))
+        .unwrap_or(parent);
+    parts[..end].join("/")
+}
+
+fn rust_files(root: &Path) -> Vec<PathBuf> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|e| {
+            let name = e.file_name().to_string_lossy();
+            e.depth() == 0 || !(name == "target" || name.starts_with('.'))
+        })
+        .filter_map(Result::ok)
+        .filter(|e| e.path().extension().map(|e| e == "rs").unwrap_or(false))
+        .map(|e| e.into_path())
+        .collect()
+}
+
+/// Nearest ancestor of `file` (within `root`) that holds a Cargo.toml.
+fn crate_root(file: &Path, root: &Path) -> Option<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .take_while(|dir| dir.starts_with(root))
+        .find(|dir| dir.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+fn crate_name(manifest_dir: &Path) -> String {
+    let manifest = fs::read_to_string(manifest_dir.join("Cargo.toml")).unwrap_or_default();
+    let mut in_package = false;
+
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_package = line == "[package]";
+        } else if in_package {
+            if let Some(value) = line.strip_prefix("name") {
+                if let Some(value) = value.trim_start().strip_prefix('=') {
+                    return value.trim().trim_matches('"').to_string();
+                }
+            }
+        }
+    }
+
+    manifest_dir
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string())
+}
+
+/// `[lints.rust] unsafe_code = "forbid"` in the crate's manifest.
+fn manifest_forbids_unsafe(manifest_dir: &Path) -> bool {
+    let manifest = fs::read_to_string(manifest_dir.join("Cargo.toml")).unwrap_or_default();
+    let mut in_lints = false;
+
+    manifest.lines().map(str::trim).any(|line| {
+        if line.starts_with('[') {
+            in_lints = line == "[lints.rust]";
+            return false;
+        }
+        in_lints && line.replace(' ', "") == "unsafe_code=\"forbid\""
+    })
+}
+
+// ─────────────────────────────────────────────
+// Visitor
+// ─────────────────────────────────────────────
 struct RustVisitor {
-    issues: Vec<Issue>,
+    file: String,
+    issues: Vec<Finding>,
+    /// Depth of enclosing `unsafe` blocks and `unsafe fn` bodies.
+    unsafe_depth: usize,
+    /// Bindings in the current function declared as `*const T` / `*mut T`.
+    raw_pointers: HashSet<String>,
 }
 
-impl<'ast> Visit<'ast> for RustVisitor {
+impl RustVisitor {
+    fn new(file: &str) -> Self {
+        Self {
+            file: file.to_string(),
+            issues: vec![],
+            unsafe_depth: 0,
+            raw_pointers: HashSet::new(),
+        }
+    }
 
-    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+    fn push(
+        &mut self,
+        category: &str,
+        severity: Severity,
+        message: String,
+        span: proc_macro2::Span,
+    ) {
+        let start = span.start();
+        let end = span.end();
+        let location = Location {
+            file: self.file.clone(),
+            start_line: start.line as u32,
+            end_line: Some(end.line as u32),
+            start_column: Some(start.column as u32 + 1),
+            end_column: Some(end.column as u32 + 1),
+        };
+        self.issues.push(
+            Finding::new(
+                Analyzer::Ast,
+                category,
+                severity,
+                format!("{} at {}:{}", message, self.file, start.line),
+            )
+            .at(location),
+        );
+    }
+
+    fn check_no_mangle(&mut self, attrs: &[Attribute], item: &str, span: proc_macro2::Span) {
+        if attrs.iter().any(is_no_mangle) {
+            self.push(
+                "NO_MANGLE",
+                Severity::Medium,
+                format!("#[no_mangle] symbol exported: {}", item),
+                span,
+            );
+        }
+    }
+
+    /// Visits a function body with only its own raw-pointer parameters in
+    /// scope.
+    fn in_fn(&mut self, sig: &Signature, visit: impl FnOnce(&mut Self)) {
+        let params = sig.inputs.iter().filter_map(|arg| match arg {
+            FnArg::Typed(typed) if matches!(*typed.ty, Type::Ptr(_)) => binding(&typed.pat),
+            _ => None,
+        });
+        let outer = std::mem::replace(&mut self.raw_pointers, params.collect());
+        let is_unsafe = sig.unsafety.is_some();
+        self.unsafe_depth += usize::from(is_unsafe);
+        visit(self);
+        self.unsafe_depth -= usize::from(is_unsafe);
+        self.raw_pointers = outer;
+    }
+
+    /// Whether `expr` is known to be a raw pointer: a cast to one, a binding
+    /// declared as one, or pointer arithmetic on either.
+    fn is_raw_pointer(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::Cast(cast) => matches!(*cast.ty, Type::Ptr(_)),
+            Expr::Paren(inner) => self.is_raw_pointer(&inner.expr),
+            Expr::Path(path) => path
+                .path
+                .get_ident()
+                .is_some_and(|ident| self.raw_pointers.contains(&ident.to_string())),
+            Expr::MethodCall(call) => match call.method.to_string().as_str() {
+                "as_ptr" | "as_mut_ptr" => true,
+                "add" | "sub" | "offset" | "wrapping_add" | "wrapping_sub" | "wrapping_offset"
+                | "byte_add" | "byte_sub" | "cast" | "cast_mut" | "cast_const" => {
+                    self.is_raw_pointer(&call.receiver)
+                }
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
 
-        // Detect unsafe blocks
+impl<'ast> Visit<'ast> for RustVisitor {
+    fn visit_item_fn(&mut self, node: &'ast ItemFn) {
+        // Detect unsafe functions
         if node.sig.unsafety.is_some() {
-            self.issues.push(Issue {
-                category: "UNSAFE_FUNCTION".into(),
-                severity: "MEDIUM".into(),
-                message: format!("Unsafe function: {}", node.sig.ident),
-            });
+            self.push(
+                "UNSAFE_FUNCTION",
+                Severity::Medium,
+                format!("Unsafe function: {}", node.sig.ident),
+                node.sig.ident.span(),
+            );
+        }
+
+        // Detect FFI exports
+        if node.sig.abi.is_some() {
+            self.push(
+                "FFI_FUNCTION",
+                Severity::Low,
+                format!("extern ABI function: {}", node.sig.ident),
+                node.sig.ident.span(),
+            );
         }
+        self.check_no_mangle(
+            &node.attrs,
+            &node.sig.ident.to_string(),
+            node.sig.ident.span(),
+        );
 
         // Detect #[Sensitive] attribute usage
         for attr in &node.attrs {
             if is_sensitive(attr) {
-                self.issues.push(Issue {
-                    category: "SENSITIVE_FUNCTION".into(),
-                    severity: "HIGH".into(),
-                    message: format!("Sensitive function exposed: {}", node.sig.ident),
-                });
+                self.push(
+                    "SENSITIVE_FUNCTION",
+                    Severity::High,
+                    format!("Sensitive function exposed: {}", node.sig.ident),
+                    node.sig.ident.span(),
+                );
             }
         }
 
-        syn::visit::visit_item_fn(self, node);
+        self.in_fn(&node.sig, |v| syn::visit::visit_item_fn(v, node));
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast ImplItemFn) {
+        if node.sig.unsafety.is_some() {
+            self.push(
+                "UNSAFE_FUNCTION",
+                Severity::Medium,
+                format!("Unsafe method: {}", node.sig.ident),
+                node.sig.ident.span(),
+            );
+        }
+        self.check_no_mangle(
+            &node.attrs,
+            &node.sig.ident.to_string(),
+            node.sig.ident.span(),
+        );
+
+        self.in_fn(&node.sig, |v| syn::visit::visit_impl_item_fn(v, node));
+    }
+
+    fn visit_local(&mut self, node: &'ast Local) {
+        // Visit the initializer first: `let p = p as *const u8;` reads the outer `p`.
+        syn::visit::visit_local(self, node);
+        let (pat, declared) = match &node.pat {
+            Pat::Type(typed) => (&*typed.pat, Some(matches!(*typed.ty, Type::Ptr(_)))),
+            pat => (pat, None),
+        };
+        let Some(name) = binding(pat) else {
+            return;
+        };
+        let is_raw = declared.unwrap_or_else(|| {
+            node.init
+                .as_ref()
+                .is_some_and(|init| self.is_raw_pointer(&init.expr))
+        });
+        if is_raw {
+            self.raw_pointers.insert(name);
+        } else {
+            self.raw_pointers.remove(&name);
+        }
+    }
+
+    fn visit_item_static(&mut self, node: &'ast ItemStatic) {
+        self.check_no_mangle(&node.attrs, &node.ident.to_string(), node.ident.span());
+        syn::visit::visit_item_static(self, node);
+    }
+
+    fn visit_expr_unsafe(&mut self, node: &'ast ExprUnsafe) {
+        self.push(
+            "UNSAFE_BLOCK",
+            Severity::Medium,
+            "Unsafe block".to_string(),
+            node.unsafe_token.span,
+        );
+
+        self.unsafe_depth += 1;
+        syn::visit::visit_expr_unsafe(self, node);
+        self.unsafe_depth -= 1;
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast ItemImpl) {
+        if let Some(token) = node.unsafety {
+            let trait_name = node
+                .trait_
+                .as_ref()
+                .and_then(|(_, path, _)| path.segments.last())
+                .map(|s| s.ident.to_string())
+                .unwrap_or_default();
+            self.push(
+                "UNSAFE_IMPL",
+                Severity::High,
+                format!("unsafe impl {}", trait_name),
+                token.span,
+            );
+        }
+        syn::visit::visit_item_impl(self, node);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast ItemTrait) {
+        if let Some(token) = node.unsafety {
+            self.push(
+                "UNSAFE_TRAIT",
+                Severity::Medium,
+                format!("unsafe trait {}", node.ident),
+                token.span,
+            );
+        }
+        syn::visit::visit_item_trait(self, node);
+    }
+
+    fn visit_item_foreign_mod(&mut self, node: &'ast ItemForeignMod) {
+        let abi = node
+            .abi
+            .name
+            .as_ref()
+            .map(|n| n.value())
+            .unwrap_or_else(|| "C".to_string());
+        self.push(
+            "EXTERN_BLOCK",
+            Severity::Medium,
+            format!("extern \"{}\" block with {} item(s)", abi, node.items.len()),
+            node.abi.extern_token.span,
+        );
+        syn::visit::visit_item_foreign_mod(self, node);
+    }
+
+    fn visit_expr_unary(&mut self, node: &'ast ExprUnary) {
+        if matches!(node.op, UnOp::Deref(_)) && self.unsafe_depth > 0 {
+            if self.is_raw_pointer(&node.expr) {
+                self.push(
+                    "RAW_POINTER_DEREF",
+                    Severity::High,
+                    "Raw pointer dereference".to_string(),
+                    node.span(),
+                );
+            } else {
+                // Could be a reference, a Box or an untracked pointer.
+                self.push(
+                    "UNSAFE_DEREF",
+                    Severity::Info,
+                    "Dereference inside unsafe code".to_string(),
+                    node.span(),
+                );
+            }
+        }
+        syn::visit::visit_expr_unary(self, node);
+    }
+
+    fn visit_expr_call(&mut self, node: &'ast ExprCall) {
+        if let Expr::Path(func) = node.func.as_ref() {
+            if let Some(last) = func.path.segments.last() {
+                if last.ident == "transmute" || last.ident == "transmute_copy" {
+                    self.push(
+                        "TRANSMUTE",
+                        Severity::High,
+                        format!("mem::{} call", last.ident),
+                        last.ident.span(),
+                    );
+                }
+            }
+        }
+        syn::visit::visit_expr_call(self, node);
+    }
+}
+
+/// The name a simple `x` / `mut x` pattern binds.
+fn binding(pat: &Pat) -> Option<String> {
+    match pat {
+        Pat::Ident(ident) if ident.subpat.is_none() => Some(ident.ident.to_string()),
+        _ => None,
     }
 }
 
 fn is_sensitive(attr: &Attribute) -> bool {
     attr.path().is_ident("Sensitive")
 }
+
+/// Matches both `#[no_mangle]` and the 2024-edition `#[unsafe(no_mangle)]`.
+fn is_no_mangle(attr: &Attribute) -> bool {
+    if attr.path().is_ident("no_mangle") {
+        return true;
+    }
+    attr.path().is_ident("unsafe")
+        && attr
+            .parse_args::<syn::Path>()
+            .map(|p| p.is_ident("no_mangle"))
+            .unwrap_or(false)
+}
+
+fn is_forbid_unsafe(attr: &Attribute) -> bool {
+    matches!(attr.style, syn::AttrStyle::Inner(_))
+        && attr.path().is_ident("forbid")
+        && attr
+            .parse_args_with(
+                syn::punctuated::Punctuated::<syn::Path, syn::Token![,]>::parse_terminated,
+            )
+            .map(|lints| lints.iter().any(|p| p.is_ident("unsafe_code")))
+            .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories(src: &str) -> Vec<String> {
+        scan_source("lib.rs", src)
+            .into_iter()
+            .map(|i| i.category)
+            .collect()
+    }
+
+    #[test]
+    fn finds_unsafe_blocks_nested_in_expressions() {
+        let src = r#"
+            fn read(p: *const u8) -> u8 {
+                let v = if true { unsafe { *p } } else { 0 };
+                v
+            }
+        "#;
+        let cats = categories(src);
+        assert!(cats.contains(&"UNSAFE_BLOCK".to_string()));
+        assert!(cats.contains(&"RAW_POINTER_DEREF".to_string()));
+    }
+
+    #[test]
+    fn reports_raw_derefs_only_for_known_pointers() {
+        let src = r#"
+            fn f(r: &u8, b: Box<u8>, addr: usize, s: &[u8]) -> u8 {
+                let q: *mut u8 = std::ptr::null_mut();
+                let c = addr as *const u8;
+                unsafe { *r + *b + *q + *c + *(addr as *const u8) + *s.as_ptr().add(1) }
+            }
+        "#;
+        let cats = categories(src);
+        let count = |c: &str| cats.iter().filter(|x| *x == c).count();
+        assert_eq!(count("RAW_POINTER_DEREF"), 4);
+        assert_eq!(count("UNSAFE_DEREF"), 2);
+    }
+
+    #[test]
+    fn ignores_unsafe_in_strings_and_comments() {
+        let src = r#"
+            // unsafe { nothing here }
+            fn f() -> &'static str { "unsafe { }" }
+        "#;
+        assert!(categories(src).is_empty());
+    }
+
+    #[test]
+    fn finds_impls_traits_ffi_and_transmute() {
+        let src = r#"
+            unsafe trait Zeroable {}
+            unsafe impl Send for Handle {}
+            extern "C" { fn abs(x: i32) -> i32; }
+            #[no_mangle]
+            pub extern "C" fn exported() {}
+            fn cast(x: u32) -> f32 { unsafe { std::mem::transmute(x) } }
+        "#;
+        let cats = categories(src);
+        for expected in [
+            "UNSAFE_TRAIT",
+            "UNSAFE_IMPL",
+            "EXTERN_BLOCK",
+            "NO_MANGLE",
+            "FFI_FUNCTION",
+            "TRANSMUTE",
+        ] {
+            assert!(cats.contains(&expected.to_string()), "missing {expected}");
+        }
+    }
+
+    #[test]
+    fn detects_forbid_unsafe_code() {
+        assert!(forbids_unsafe_code("#![forbid(unsafe_code)]\nfn main() {}"));
+        assert!(!forbids_unsafe_code("#![deny(unsafe_code)]\nfn main() {}"));
+    }
+
+    #[test]
+    fn compare_reports_per_crate_delta() {
+        let mut base = UnsafeInventory::default();
+        base.crates.insert(
+            "core".into(),
+            UnsafeCounts {
+                unsafe_blocks: 1,
+                ..Default::default()
+            },
+        );
+        let mut head = base.clone();
+        if let Some(counts) = head.crates.get_mut("core") {
+            counts.transmutes = 2;
+        }
+
+        let regressions = UnsafeInventory::regressions(&base, &head);
+        assert_eq!(regressions.len(), 1);
+        assert!(regressions[0].message.contains("core: 1 → 3 (+2)"));
+    }
+
+    #[test]
+    fn inventories_changed_files_per_crate_directory() {
+        let base = inventory_of([("crates/io/src/ffi/mod.rs", "fn f() {}")]);
+        let head = inventory_of([
+            ("crates/io/src/ffi/mod.rs", "fn f() { unsafe {} }"),
+            ("src/lib.rs", "#![forbid(unsafe_code)]"),
+        ]);
+        assert_eq!(crate_dir("build.rs"), "");
+        let changes = UnsafeInventory::compare(&base, &head);
+        assert_eq!(
+            changes.iter().map(|c| c.to_string()).collect::<Vec<_>>(),
+            [
+                ".: 0 → 0 (+0), forbid(unsafe_code)",
+                "crates/io: 0 → 1 (+1)"
+            ]
+        );
+    }
+}
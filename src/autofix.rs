@@ -0,0 +1,582 @@
+//! Patches for findings whose fix is mechanical. Each rule with a fixer gets
+//! its patch checked before it is proposed: the file must still parse, and a
+//! re-scan must no longer report the finding or report anything new.
+
+use crate::diff;
+use crate::models::{Finding, Fix, Location};
+use crate::{ast_scan, scanner};
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+use syn::spanned::Spanned;
+
+/// What PHI values are replaced with.
+pub const REDACTED: &str = "[REDACTED]";
+
+static LOG_CALL_RE: OnceLock<Option<Regex>> = OnceLock::new();
+static SECRET_LET_RE: OnceLock<Option<Regex>> = OnceLock::new();
+static PLACEHOLDER_RE: OnceLock<Option<Regex>> = OnceLock::new();
+
+pub trait Fixer: Send + Sync {
+    /// Rule whose findings this fixer patches.
+    fn rule_id(&self) -> &'static str;
+
+    /// What the patch does, for the commit message and PR body.
+    fn description(&self) -> &'static str;
+
+    /// `source` with the finding at 1-based `line` fixed, or `None` when the
+    /// code there is not a shape this fixer understands.
+    fn fix(&self, source: &str, line: u32) -> Option<String>;
+}
+
+static FIXERS: &[&dyn Fixer] = &[&PhiLogging, &HardcodedSecret, &RedactPhiFields];
+
+pub fn fixer_for(rule_id: &str) -> Option<&'static dyn Fixer> {
+    FIXERS.iter().copied().find(|f| f.rule_id() == rule_id)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub path: String,
+    pub original: String,
+    pub patched: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FixPlan {
+    /// One per changed file, all verified.
+    pub patches: Vec<Patch>,
+    /// Findings the patches fix.
+    pub fixed: Vec<Finding>,
+    /// Findings left for a human, with the reason.
+    pub skipped: Vec<(Finding, String)>,
+}
+
+impl FixPlan {
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
+    }
+}
+
+/// Applies every fixer that matches `findings` to `files`, or the finding's
+/// own `fix` where no fixer does. Within a file, findings are fixed bottom-up
+/// so earlier line numbers stay valid. A file whose patch fails verification
+/// is left out entirely.
+pub fn plan(files: &[SourceFile], findings: &[Finding]) -> FixPlan {
+    let mut plan = FixPlan::default();
+    let mut by_file: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+    for finding in findings {
+        let fixer = fixer_for(&finding.rule_id);
+        let Some(location) =
+            (finding.location.as_ref()).filter(|_| fixer.is_some() || finding.fix.is_some())
+        else {
+            plan.skipped.push((
+                finding.clone(),
+                "no automatic fix for this rule".to_string(),
+            ));
+            continue;
+        };
+        if !files.iter().any(|f| f.path == location.file) {
+            plan.skipped
+                .push((finding.clone(), "source file not available".to_string()));
+            continue;
+        }
+        by_file
+            .entry(location.file.as_str())
+            .or_default()
+            .push(finding);
+    }
+
+    for file in files {
+        let Some(mut targets) = by_file.remove(file.path.as_str()) else {
+            continue;
+        };
+        targets.sort_by_key(|f| std::cmp::Reverse(line_of(f)));
+
+        let mut content = file.content.clone();
+        let mut fixed = vec![];
+        for finding in targets {
+            let patched = match (fixer_for(&finding.rule_id), &finding.fix, &finding.location) {
+                (Some(fixer), _, _) => fixer.fix(&content, line_of(finding)),
+                (None, Some(fix), Some(location)) => apply_fix(&content, location, fix),
+                _ => None,
+            };
+            match patched {
+                Some(patched) if patched != content => {
+                    content = patched;
+                    fixed.push(finding);
+                }
+                _ => plan.skipped.push((
+                    finding.clone(),
+                    "code is not in a shape the fixer handles".to_string(),
+                )),
+            }
+        }
+        if fixed.is_empty() {
+            continue;
+        }
+
+        match verify(&file.path, &file.content, &content, &fixed) {
+            Ok(()) => {
+                plan.patches.push(Patch {
+                    path: file.path.clone(),
+                    original: file.content.clone(),
+                    patched: content,
+                });
+                plan.fixed.extend(fixed.into_iter().cloned());
+            }
+            Err(reason) => plan
+                .skipped
+                .extend(fixed.into_iter().map(|f| (f.clone(), reason.clone()))),
+        }
+    }
+    plan
+}
+
+/// The patched file must parse, the fixed findings must be gone from a
+/// re-scan, and no rule may report more than before.
+pub fn verify(path: &str, original: &str, patched: &str, fixed: &[&Finding]) -> Result<(), String> {
+    if path.ends_with(".rs") {
+        syn::parse_file(patched).map_err(|e| format!("patched file does not parse: {}", e))?;
+    }
+    let before = rescan(path, original);
+    let after = rescan(path, patched);
+
+    for finding in fixed {
+        if after
+            .iter()
+            .any(|a| a.rule_id == finding.rule_id && line_of(a) == line_of(finding))
+        {
+            return Err(format!(
+                "re-scan still reports {} at line {}",
+                finding.rule_id,
+                line_of(finding)
+            ));
+        }
+    }
+    let count = |findings: &[Finding]| {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for f in findings {
+            *counts.entry(f.rule_id.clone()).or_default() += 1;
+        }
+        counts
+    };
+    let before = count(&before);
+    for (rule, n) in count(&after) {
+        if n > before.get(&rule).copied().unwrap_or(0) {
+            return Err(format!("patch introduces a new {} finding", rule));
+        }
+    }
+    Ok(())
+}
+
+fn rescan(path: &str, content: &str) -> Vec<Finding> {
+    let mut findings = scanner::deterministic_scan(&diff::added_file(path, content));
+    if path.ends_with(".rs") {
+        findings.extend(ast_scan::scan_source(path, content));
+    }
+    findings
+}
+
+fn line_of(finding: &Finding) -> u32 {
+    finding.location.as_ref().map_or(0, |l| l.start_line)
+}
+
+// ─────────────────────────────────────────────
+// Fixers
+// ─────────────────────────────────────────────
+/// `println!("{}", ssn)` → `tracing::info!("{}", "[REDACTED]")`. Single-line
+/// calls whose first argument is the format string.
+pub struct PhiLogging;
+
+impl Fixer for PhiLogging {
+    fn rule_id(&self) -> &'static str {
+        "GHG-PHI-001"
+    }
+
+    fn description(&self) -> &'static str {
+        "Logs through `tracing` with PHI arguments redacted"
+    }
+
+    fn fix(&self, source: &str, line: u32) -> Option<String> {
+        let text = line_at(source, line)?;
+        let re = LOG_CALL_RE
+            .get_or_init(|| {
+                Regex::new(
+                    r"^(\s*)((?:tracing::|log::)?(?:println|print|eprintln|eprint|info|debug|warn|error|trace))!\((.*)\)(;?)\s*$",
+                )
+                .ok()
+            })
+            .as_ref()?;
+        let caps = re.captures(text)?;
+        let args = split_args(&caps[3])?;
+        let (format, values) = args.split_first()?;
+        if !format.starts_with('"') {
+            return None;
+        }
+
+        let mut call = vec![redact_placeholders(format)];
+        call.extend(values.iter().map(|v| redact_arg(v)));
+        let macro_path = match &caps[2] {
+            "println" | "print" => "tracing::info",
+            "eprintln" | "eprint" => "tracing::warn",
+            other => other,
+        };
+        let rewritten = format!(
+            "{}{}!({}){}",
+            &caps[1],
+            macro_path,
+            call.join(", "),
+            &caps[4]
+        );
+        (rewritten != text).then(|| replace_line(source, line, &rewritten))
+    }
+}
+
+/// `{ssn}` and `{ssn:?}` in a format string become the redaction marker.
+fn redact_placeholders(format: &str) -> String {
+    let Some(re) = PLACEHOLDER_RE
+        .get_or_init(|| Regex::new(r"\{([A-Za-z_]\w*)(?::[^}]*)?\}").ok())
+        .as_ref()
+    else {
+        return format.to_string();
+    };
+    let mut out = String::new();
+    let mut last = 0;
+    for caps in re.captures_iter(format) {
+        let Some(m) = caps.get(0) else { continue };
+        let escaped = format[..m.start()].ends_with('{');
+        if escaped || !scanner::mentions_phi(&caps[1]) {
+            continue;
+        }
+        out.push_str(&format[last..m.start()]);
+        out.push_str(REDACTED);
+        last = m.end();
+    }
+    out.push_str(&format[last..]);
+    out
+}
+
+fn redact_arg(arg: &str) -> String {
+    if !scanner::mentions_phi(arg) {
+        return arg.to_string();
+    }
+    match arg.split_once('=') {
+        // Named format argument: `ssn = patient.ssn`.
+        Some((name, value))
+            if !value.starts_with('=')
+                && name.trim().chars().all(|c| c.is_alphanumeric() || c == '_') =>
+        {
+            format!("{} = \"{}\"", name.trim(), REDACTED)
+        }
+        _ => format!("\"{}\"", REDACTED),
+    }
+}
+
+/// Splits macro arguments on top-level commas. `None` if brackets or
+/// strings are unbalanced.
+fn split_args(args: &str) -> Option<Vec<String>> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in args.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(args[start..i].trim().to_string());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        if depth < 0 {
+            return None;
+        }
+    }
+    if in_string || depth != 0 {
+        return None;
+    }
+    let last = args[start..].trim();
+    if !last.is_empty() {
+        parts.push(last.to_string());
+    }
+    Some(parts)
+}
+
+/// `let api_key = "sk-…";` → `let api_key = std::env::var("API_KEY")…;`.
+/// The literal is gone from the code but not from history: rotate it.
+pub struct HardcodedSecret;
+
+impl Fixer for HardcodedSecret {
+    fn rule_id(&self) -> &'static str {
+        "GHG-SEC-001"
+    }
+
+    fn description(&self) -> &'static str {
+        "Reads the secret from an environment variable (rotate the old value)"
+    }
+
+    fn fix(&self, source: &str, line: u32) -> Option<String> {
+        let text = line_at(source, line)?;
+        let re = SECRET_LET_RE
+            .get_or_init(|| {
+                Regex::new(
+                    r#"^(\s*let\s+(?:mut\s+)?)([A-Za-z_]\w*)\s*(?::\s*(?:&\s*(?:'static\s+)?str|String)\s*)?=\s*"(?:[^"\\]|\\.)*"(?:\.to_string\(\)|\.to_owned\(\)|\.into\(\))?\s*;\s*$"#,
+                )
+                .ok()
+            })
+            .as_ref()?;
+        let caps = re.captures(text)?;
+        let var = caps[2].to_ascii_uppercase();
+        let rewritten = format!(
+            "{}{} = std::env::var(\"{}\").expect(\"{} must be set\");",
+            &caps[1], &caps[2], var, var
+        );
+        Some(replace_line(source, line, &rewritten))
+    }
+}
+
+/// Wraps the PHI fields of the struct at the finding in `Redacted<T>`, whose
+/// `Debug` prints a placeholder. The type is added to the file if it has none.
+pub struct RedactPhiFields;
+
+const REDACTED_TYPE: &str = "
+/// Holds PHI; `Debug` prints a placeholder instead of the value.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Redacted<T>(pub T);
+
+impl<T> std::fmt::Debug for Redacted<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(\"[REDACTED]\")
+    }
+}
+";
+
+impl Fixer for RedactPhiFields {
+    fn rule_id(&self) -> &'static str {
+        "GHG-PHI-003"
+    }
+
+    fn description(&self) -> &'static str {
+        "Wraps PHI fields in `Redacted<T>` so `Debug` cannot print them"
+    }
+
+    fn fix(&self, source: &str, line: u32) -> Option<String> {
+        let file = syn::parse_file(source).ok()?;
+        let item = file.items.iter().find_map(|item| match item {
+            syn::Item::Struct(s)
+                if s.span().start().line <= line as usize
+                    && line as usize <= s.span().end().line =>
+            {
+                Some(s)
+            }
+            _ => None,
+        })?;
+        let syn::Fields::Named(fields) = &item.fields else {
+            return None;
+        };
+
+        let mut edits = vec![];
+        for field in &fields.named {
+            let name = field.ident.as_ref()?.to_string();
+            if !scanner::mentions_phi(&name) {
+                continue;
+            }
+            let span = field.ty.span();
+            let start = offset(source, span.start())?;
+            let end = offset(source, span.end())?;
+            let ty = source.get(start..end)?;
+            if !ty.starts_with("Redacted<") {
+                edits.push((start, end, format!("Redacted<{}>", ty)));
+            }
+        }
+        if edits.is_empty() {
+            return None;
+        }
+
+        let mut out = source.to_string();
+        for (start, end, text) in edits.iter().rev() {
+            out.replace_range(*start..*end, text);
+        }
+        if !names_redacted(&file) {
+            if !out.ends_with('\n') {
+                out.push('\n');
+            }
+            out.push_str(REDACTED_TYPE);
+        }
+        Some(out)
+    }
+}
+
+/// Whether the file defines or imports something called `Redacted`.
+fn names_redacted(file: &syn::File) -> bool {
+    fn in_use(tree: &syn::UseTree) -> bool {
+        match tree {
+            syn::UseTree::Path(p) => in_use(&p.tree),
+            syn::UseTree::Name(n) => n.ident == "Redacted",
+            syn::UseTree::Rename(r) => r.rename == "Redacted",
+            syn::UseTree::Glob(_) => false,
+            syn::UseTree::Group(g) => g.items.iter().any(in_use),
+        }
+    }
+    file.items.iter().any(|item| match item {
+        syn::Item::Struct(s) => s.ident == "Redacted",
+        syn::Item::Use(u) => in_use(&u.tree),
+        _ => false,
+    })
+}
+
+// ─────────────────────────────────────────────
+// Line helpers
+// ─────────────────────────────────────────────
+/// Lines `start..=end` of a location, as `Fix::replacement` covers them.
+pub fn span(location: &Location) -> (u32, u32) {
+    let start = location.start_line;
+    (start, location.end_line.unwrap_or(start).max(start))
+}
+
+/// `source` with the lines of `location` replaced by `fix`, keeping the line
+/// ending of the last one. `None` if the lines are not in the file.
+pub fn apply_fix(source: &str, location: &Location, fix: &Fix) -> Option<String> {
+    let (start, end) = span(location);
+    let lines: Vec<&str> = source.split_inclusive('\n').collect();
+    if start == 0 || end as usize > lines.len() {
+        return None;
+    }
+    let last = lines[end as usize - 1];
+    let ending = &last[last.trim_end_matches(['\r', '\n']).len()..];
+    let mut out: String = lines[..start as usize - 1].concat();
+    out.push_str(&fix.replacement);
+    out.push_str(ending);
+    out.push_str(&lines[end as usize..].concat());
+    Some(out)
+}
+
+fn line_at(source: &str, line: u32) -> Option<&str> {
+    let text = source.lines().nth((line as usize).checked_sub(1)?)?;
+    Some(text.strip_suffix('\r').unwrap_or(text))
+}
+
+/// Replaces line `line`, keeping its line ending.
+fn replace_line(source: &str, line: u32, text: &str) -> String {
+    source
+        .split_inclusive('\n')
+        .enumerate()
+        .map(|(i, original)| {
+            if i + 1 != line as usize {
+                return original.to_string();
+            }
+            let ending = &original[original.trim_end_matches(['\r', '\n']).len()..];
+            format!("{}{}", text, ending)
+        })
+        .collect()
+}
+
+/// Byte offset of a span position (1-based line, column in characters).
+fn offset(source: &str, at: proc_macro2::LineColumn) -> Option<usize> {
+    let line_start: usize = source
+        .split_inclusive('\n')
+        .take(at.line.checked_sub(1)?)
+        .map(str::len)
+        .sum();
+    let line = source.get(line_start..)?;
+    let column = line
+        .char_indices()
+        .nth(at.column)
+        .map_or(line.len(), |(i, _)| i);
+    Some(line_start + column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location, Severity};
+
+    fn finding(rule: &str, path: &str, line: u32) -> Finding {
+        let mut f = Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "x")
+            .at(Location::line(path, line));
+        f.rule_id = rule.to_string();
+        f
+    }
+
+    #[test]
+    fn redacts_phi_in_log_calls() {
+        let source = "fn a() {\n    println!(\"Checking record for {}\", name);\n    info!(\"{ssn:?} {} {{name}}\", count);\n}\n";
+        let fixed = PhiLogging.fix(source, 2).unwrap_or_default();
+        assert!(fixed.contains("    tracing::info!(\"Checking record for {}\", \"[REDACTED]\");\n"));
+        let fixed = PhiLogging.fix(source, 3).unwrap_or_default();
+        assert!(fixed.contains("info!(\"[REDACTED] {} {{name}}\", count);"));
+        assert_eq!(PhiLogging.fix("let x = 1;\n", 1), None);
+    }
+
+    #[test]
+    fn moves_hardcoded_secrets_to_the_environment() {
+        let source = "fn a() {\n    let api_key: &str = \"sk-live-123\";\n}\n";
+        let fixed = HardcodedSecret.fix(source, 2).unwrap_or_default();
+        assert!(fixed.contains(
+            "    let api_key = std::env::var(\"API_KEY\").expect(\"API_KEY must be set\");\n"
+        ));
+    }
+
+    #[test]
+    fn wraps_phi_fields_and_adds_the_type_once() {
+        let source = "#[derive(Debug)]\npub struct Patient {\n    pub id: u64,\n    pub ssn: String,\n    pub diagnosis: Option<String>,\n}\n";
+        let fixed = RedactPhiFields.fix(source, 4).unwrap_or_default();
+        assert!(fixed.contains("pub ssn: Redacted<String>,"));
+        assert!(fixed.contains("pub diagnosis: Redacted<Option<String>>,"));
+        assert!(fixed.contains("pub id: u64,"));
+        assert_eq!(fixed.matches("pub struct Redacted<T>").count(), 1);
+        assert!(syn::parse_file(&fixed).is_ok());
+        assert_eq!(RedactPhiFields.fix(&fixed, 4), None);
+    }
+
+    #[test]
+    fn plans_verified_patches_across_files() {
+        let files = vec![
+            SourceFile {
+                path: "src/log.rs".into(),
+                content: "fn a() {\n    println!(\"{}\", ssn);\n    let token = \"abc\";\n}\n"
+                    .into(),
+            },
+            SourceFile {
+                path: "src/b.rs".into(),
+                content: "fn b() {\n    println!(\"Patient {}\", id);\n}\n".into(),
+            },
+        ];
+        let findings = vec![
+            finding("GHG-PHI-001", "src/log.rs", 2),
+            finding("GHG-SEC-001", "src/log.rs", 3),
+            // The format string itself names PHI, so the re-scan still flags it.
+            finding("GHG-PHI-001", "src/b.rs", 2),
+            finding("GHG-SEC-004", "src/log.rs", 1),
+        ];
+
+        let plan = plan(&files, &findings);
+        assert_eq!(plan.patches.len(), 1);
+        assert_eq!(plan.fixed.len(), 2);
+        assert_eq!(plan.skipped.len(), 2);
+        assert!(plan.patches[0].patched.contains("std::env::var(\"TOKEN\")"));
+        assert!(plan
+            .skipped
+            .iter()
+            .any(|(f, why)| f.rule_id == "GHG-PHI-001" && why.contains("still reports")));
+    }
+}
@@ -0,0 +1,408 @@
+//! Runs GhostHealth Guard locally, without the server or a database.
+//!
+//! `ghosthealth scan [PATH]` scans a working tree, `scan --diff FILE|-` a
+//! unified diff and `scan --git BASE..HEAD` a git range, with the
+//! deterministic, AST and Semgrep analyzers. `--ai` adds the AI reviewer,
+//! configured from the same `LLM_*` variables as the server. The exit status
+//! is 1 if a finding reaches `--fail-on` (default `high`), 2 on error.
+//!
+//! `ghosthealth hook pre-commit` scans the staged changes and `hook
+//! pre-receive` every pushed commit, deterministic checks only;
+//! `ghosthealth install-hooks` writes the scripts that call them.
+//!
+//! `ghosthealth ci` runs in a GitHub Actions job: it scans the PR or push
+//! from the event at `GITHUB_EVENT_PATH` in the local checkout, writes SARIF
+//! and the job summary, and can post the review with `GITHUB_TOKEN`. It exits
+//! 1 on a violation, when the PR is held for review because the AI review
+//! failed under `--failure-policy fail_closed` (the default), or when a
+//! finding reaches `--fail-on` (default `high`). There is no database here,
+//! so the posted review carries a standalone `sha256:` digest of the report
+//! rather than an audit-chain hash.
+//!
+//! `ghosthealth [--format FORMAT] [--output FILE] [DIFF]` still scans a diff
+//! from `DIFF` or stdin with the AI reviewer, and always exits 0 on success.
+
+use anyhow::Context;
+use ghosthealth_guard::audit::{self, FailurePolicy, ReviewOptions};
+use ghosthealth_guard::ci::ActionsContext;
+use ghosthealth_guard::export::{self, Format};
+use ghosthealth_guard::local::{self, Hook, RefUpdate, Workspace};
+use ghosthealth_guard::markdown::{self, Template};
+use ghosthealth_guard::models::{AuditResult, Severity, Status};
+use ghosthealth_guard::{diff, github, llm};
+use std::io::{Read, Write};
+use std::path::Path;
+
+const USAGE: &str = "usage: ghosthealth scan [PATH | --diff FILE|- | --git BASE..HEAD]
+                        [--format human|json|sarif|junit|gitlab|checkstyle|csv] [--output FILE]
+                        [--fail-on critical|high|medium|low|info|none] [--ai] [--no-semgrep]
+       ghosthealth hook pre-commit|pre-receive [--fail-on SEVERITY|none]
+       ghosthealth install-hooks [pre-commit] [pre-receive] [--force]
+       ghosthealth ci [--sarif FILE] [--post-review] [--no-ai] [--no-semgrep]
+                      [--fail-on SEVERITY|none] [--failure-policy fail_closed|fail_open|retry[:N]]
+       ghosthealth [--format json|sarif|junit|gitlab|checkstyle|csv] [--output FILE] [DIFF]";
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let outcome = match args.first().map(String::as_str) {
+        Some("scan") => scan(&args[1..]).await,
+        Some("hook") => hook(&args[1..]).await,
+        Some("install-hooks") => install_hooks(&args[1..]),
+        Some("ci") => ci(&args[1..]).await,
+        _ => legacy(&args).await.map(|()| 0),
+    };
+    match outcome {
+        Ok(code) => std::process::exit(code),
+        Err(e) => {
+            eprintln!("ghosthealth: {:#}", e);
+            std::process::exit(2);
+        }
+    }
+}
+
+enum Input {
+    Tree(String),
+    Diff(String),
+    Git(String),
+}
+
+async fn scan(args: &[String]) -> anyhow::Result<i32> {
+    let mut input = None;
+    let mut format: Option<Format> = None;
+    let mut output = None;
+    let mut fail_on = Some(Severity::High);
+    let mut ai = false;
+    let mut semgrep = true;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().cloned().context(USAGE);
+        match arg.as_str() {
+            "--diff" | "-d" => input = Some(Input::Diff(value()?)),
+            "--git" | "-g" => input = Some(Input::Git(value()?)),
+            "--format" | "-f" => {
+                format = match value()?.as_str() {
+                    "human" => None,
+                    other => Some(other.parse()?),
+                }
+            }
+            "--output" | "-o" => output = Some(value()?),
+            "--fail-on" => {
+                fail_on = match value()?.as_str() {
+                    "none" => None,
+                    other => Some(other.parse()?),
+                }
+            }
+            "--ai" => ai = true,
+            "--no-semgrep" => semgrep = false,
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(0);
+            }
+            _ if arg.starts_with('-') => anyhow::bail!("{}\nunknown option {}", USAGE, arg),
+            _ => input = Some(Input::Tree(arg.clone())),
+        }
+    }
+
+    let cwd = Path::new(".");
+    let workspace = match input.unwrap_or_else(|| Input::Tree(".".into())) {
+        Input::Tree(path) => Workspace::tree(Path::new(&path))?,
+        Input::Diff(file) => Workspace::from_diff(read_input(Some(&file))?, cwd),
+        Input::Git(range) => Workspace::from_git(&range, cwd)?,
+    };
+
+    let reviewer = if ai {
+        let reviewer = llm::LlmConfig::from_env()?
+            .build()
+            .context("Failed to configure AI reviewer")?;
+        Some(with_cassette(reviewer)?)
+    } else {
+        None
+    };
+    let result = check(
+        &workspace,
+        reviewer.as_deref(),
+        semgrep,
+        FailurePolicy::default(),
+    )
+    .await?;
+
+    let report = match format {
+        Some(format) => export::render(&result, format)?,
+        None => local::human(&result),
+    };
+    write_output(output.as_deref(), &report)?;
+    Ok(if local::fails(&result, fail_on) { 1 } else { 0 })
+}
+
+async fn check(
+    workspace: &Workspace,
+    reviewer: Option<&dyn llm::LlmReviewer>,
+    semgrep: bool,
+    failure_policy: FailurePolicy,
+) -> anyhow::Result<AuditResult> {
+    let options = ReviewOptions {
+        failure_policy,
+        ..Default::default()
+    };
+    github::process_diff_with(
+        reviewer,
+        &options,
+        &workspace.diff,
+        workspace.analyze(semgrep),
+    )
+    .await
+}
+
+/// Runs as a git hook: deterministic checks only, so it stays fast. Reports
+/// go to stderr, which git shows to the committer or pusher.
+async fn hook(args: &[String]) -> anyhow::Result<i32> {
+    let mut kind = None;
+    let mut fail_on = Some(Severity::High);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--fail-on" => {
+                fail_on = match args.next().context(USAGE)?.as_str() {
+                    "none" => None,
+                    other => Some(other.parse()?),
+                }
+            }
+            _ if arg.starts_with('-') => anyhow::bail!("{}\nunknown option {}", USAGE, arg),
+            _ => kind = Some(arg.parse::<Hook>()?),
+        }
+    }
+    let root = Path::new(".");
+
+    match kind.context(USAGE)? {
+        Hook::PreCommit => {
+            let result = check(
+                &Workspace::staged(root)?,
+                None,
+                false,
+                FailurePolicy::default(),
+            )
+            .await?;
+            if result.issues.is_empty() {
+                return Ok(0);
+            }
+            eprint!("{}", local::human(&result));
+            if !local::fails(&result, fail_on) {
+                return Ok(0);
+            }
+            eprintln!(
+                "\nGhostHealth Guard blocked this commit. Fix the findings above, or bypass with `git commit --no-verify`."
+            );
+            Ok(1)
+        }
+        Hook::PreReceive => {
+            let updates = RefUpdate::parse_all(&read_input(None)?);
+            let mut rejected = 0;
+            for commit in local::pushed_commits(root, &updates)? {
+                let result = check(
+                    &Workspace::commit(root, &commit)?,
+                    None,
+                    false,
+                    FailurePolicy::default(),
+                )
+                .await?;
+                if local::fails(&result, fail_on) {
+                    eprintln!("commit {}:", &commit[..12.min(commit.len())]);
+                    eprint!("{}", local::human(&result));
+                    rejected += 1;
+                }
+            }
+            if rejected == 0 {
+                return Ok(0);
+            }
+            eprintln!(
+                "\nGhostHealth Guard rejected this push: {} commit(s) have blocking findings. Rewrite them so PHI and secrets never enter history.",
+                rejected
+            );
+            Ok(1)
+        }
+    }
+}
+
+fn install_hooks(args: &[String]) -> anyhow::Result<i32> {
+    let mut hooks = vec![];
+    let mut force = false;
+    for arg in args {
+        match arg.as_str() {
+            "--force" => force = true,
+            _ if arg.starts_with('-') => anyhow::bail!("{}\nunknown option {}", USAGE, arg),
+            _ => hooks.push(arg.parse::<Hook>()?),
+        }
+    }
+    if hooks.is_empty() {
+        hooks.push(Hook::PreCommit);
+    }
+
+    let exe = std::env::current_exe().context("Failed to locate the ghosthealth binary")?;
+    for hook in hooks {
+        let path = local::install_hook(Path::new("."), hook, &exe.to_string_lossy(), force)?;
+        println!("Installed {} hook at {}", hook, path.display());
+    }
+    Ok(0)
+}
+
+/// The PR or push of a GitHub Actions run, without the GitHub App. The AI
+/// review runs when a provider is configured through the `LLM_*` variables.
+async fn ci(args: &[String]) -> anyhow::Result<i32> {
+    let mut sarif = "ghosthealth.sarif".to_string();
+    let mut post_review = false;
+    let mut ai = true;
+    let mut semgrep = true;
+    let mut fail_on = Some(Severity::High);
+    let mut failure_policy = FailurePolicy::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sarif" => sarif = args.next().context(USAGE)?.clone(),
+            "--post-review" => post_review = true,
+            "--no-ai" => ai = false,
+            "--no-semgrep" => semgrep = false,
+            "--fail-on" => {
+                fail_on = match args.next().context(USAGE)?.as_str() {
+                    "none" => None,
+                    other => Some(other.parse()?),
+                }
+            }
+            "--failure-policy" => failure_policy = args.next().context(USAGE)?.parse()?,
+            _ => anyhow::bail!("{}\nunknown option {}", USAGE, arg),
+        }
+    }
+
+    let context = ActionsContext::from_env()?;
+    let range = context
+        .range()
+        .with_context(|| format!("No commit range in the {} event", context.event_name))?;
+    let workspace = Workspace::from_git(&range, Path::new(".")).with_context(|| {
+        format!(
+            "Failed to diff {}; check out with `fetch-depth: 0` so both commits are present",
+            range
+        )
+    })?;
+
+    let config = llm::LlmConfig::from_env()?;
+    let configured = config.api_key.is_some() || config.provider == llm::LlmProvider::Local;
+    let reviewer = if ai && configured {
+        let reviewer = config.build().context("Failed to configure AI reviewer")?;
+        Some(with_cassette(reviewer)?)
+    } else {
+        None
+    };
+    let result = check(&workspace, reviewer.as_deref(), semgrep, failure_policy).await?;
+
+    write_output(Some(&sarif), &export::render(&result, Format::Sarif)?)?;
+    let template = Template::default();
+    let summary = markdown::render(&result, &template, context.source_links().as_ref());
+    context.write_step_summary(&summary)?;
+    print!("{}", local::human(&result));
+
+    if post_review {
+        match context.pull_request() {
+            Some(pr) => {
+                let digest = audit::standalone_digest(&result)?;
+                github::post_review(
+                    &context.client()?,
+                    &pr,
+                    &diff::parse(&workspace.diff),
+                    &result,
+                    &template,
+                    &digest,
+                )
+                .await
+                .context("Failed to post the review")?;
+            }
+            None => eprintln!(
+                "ghosthealth: not a pull_request event ({}); review not posted",
+                context.event_name
+            ),
+        }
+    }
+    let held = matches!(result.status, Status::Violation | Status::NeedsReview);
+    Ok(if held || local::fails(&result, fail_on) {
+        1
+    } else {
+        0
+    })
+}
+
+async fn legacy(args: &[String]) -> anyhow::Result<()> {
+    let mut format = Format::default();
+    let mut output = None;
+    let mut input = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" | "-f" => format = args.next().context(USAGE)?.parse()?,
+            "--output" | "-o" => output = Some(args.next().context(USAGE)?.clone()),
+            "--help" | "-h" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if arg.starts_with('-') && arg != "-" => {
+                anyhow::bail!("{}\nunknown option {}", USAGE, arg)
+            }
+            _ => input = Some(arg.clone()),
+        }
+    }
+
+    let diff = read_input(input.as_deref())?;
+    let reviewer = llm::LlmConfig::from_env()?
+        .build()
+        .context("Failed to configure AI reviewer")?;
+    let reviewer = with_cassette(reviewer)?;
+    let result = github::process_diff(reviewer.as_ref(), &ReviewOptions::default(), &diff).await?;
+
+    write_output(output.as_deref(), &export::render(&result, format)?)
+}
+
+/// Wraps the reviewer in the `LLM_CASSETTE` record/replay cassette in
+/// `dev-tools` builds; other builds use the reviewer as configured.
+#[cfg(feature = "dev-tools")]
+fn with_cassette(reviewer: Box<dyn llm::LlmReviewer>) -> anyhow::Result<Box<dyn llm::LlmReviewer>> {
+    ghosthealth_guard::cassette::CassetteReviewer::from_env(reviewer)
+}
+
+#[cfg(not(feature = "dev-tools"))]
+fn with_cassette(reviewer: Box<dyn llm::LlmReviewer>) -> anyhow::Result<Box<dyn llm::LlmReviewer>> {
+    Ok(reviewer)
+}
+
+/// Reads `path`, or stdin for `None` and `-`.
+fn read_input(path: Option<&str>) -> anyhow::Result<String> {
+    match path {
+        None | Some("-") => {
+            let mut diff = String::new();
+            std::io::stdin().read_to_string(&mut diff)?;
+            Ok(diff)
+        }
+        Some(path) => {
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))
+        }
+    }
+}
+
+fn write_output(path: Option<&str>, report: &str) -> anyhow::Result<()> {
+    match path {
+        Some(path) => {
+            std::fs::write(path, report).with_context(|| format!("Failed to write {}", path))
+        }
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(report.as_bytes())?;
+            if !report.ends_with('\n') {
+                stdout.write_all(b"\n")?;
+            }
+            Ok(())
+        }
+    }
+}
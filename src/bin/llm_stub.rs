@@ -0,0 +1,25 @@
+//! Local OpenAI-compatible stub for offline review runs.
+//!
+//! `LLM_STUB_SCRIPT` points at a JSON array of replies (see `StubReply`);
+//! without it every request gets a CLEAN verdict. Then run the app with
+//! `LLM_PROVIDER=local LLM_BASE_URL=http://127.0.0.1:8089`.
+
+use anyhow::Context;
+use ghosthealth_guard::llm_stub::{StubReply, StubServer};
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .init();
+
+    let replies = match std::env::var("LLM_STUB_SCRIPT") {
+        Ok(path) => StubReply::load_script(path.as_ref())?,
+        Err(_) => vec![],
+    };
+    let addr = std::env::var("LLM_STUB_ADDR").unwrap_or_else(|_| "127.0.0.1:8089".to_string());
+
+    let server = StubServer::start(&addr, replies).await?;
+    tracing::info!("LLM stub listening on {}", server.url());
+    server.wait().await.context("LLM stub exited")
+}
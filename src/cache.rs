@@ -0,0 +1,330 @@
+use crate::diff;
+use crate::hash::generate_hash;
+use crate::models::AuditResult;
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Identifies a cached AI verdict. Changing the prompt or the rule pack changes
+/// every key, so stale verdicts are never served.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheKey {
+    pub key: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub rule_pack_version: String,
+}
+
+impl CacheKey {
+    /// `scope` is whatever else decides the verdict besides the chunk and
+    /// model: the tenant, its failure policy and the provider endpoint (see
+    /// `audit::ReviewOptions::cache_scope`). Verdicts never cross scopes.
+    pub fn new(
+        chunk: &str,
+        scope: &str,
+        model: &str,
+        prompt_version: &str,
+        rule_pack_version: &str,
+    ) -> Self {
+        let key = generate_hash(&format!(
+            "{}\0{}\0{}\0{}\0{}",
+            normalize_hunk(chunk),
+            scope,
+            model,
+            prompt_version,
+            rule_pack_version
+        ));
+        Self {
+            key,
+            model: model.to_string(),
+            prompt_version: prompt_version.to_string(),
+            rule_pack_version: rule_pack_version.to_string(),
+        }
+    }
+}
+
+/// Drops what changes between otherwise identical hunks: blob hashes on `index`
+/// lines, hunk line numbers and trailing whitespace. A cherry-pick of the same
+/// change onto a release branch therefore hashes the same.
+pub fn normalize_hunk(chunk: &str) -> String {
+    chunk
+        .lines()
+        .filter(|line| !line.starts_with("index ") && !line.starts_with("diff --git "))
+        .map(|line| match line.strip_prefix("@@ ") {
+            Some(rest) => match rest.split_once(" @@") {
+                Some((_, section)) => format!("@@{}", section.trim_end()),
+                None => line.trim_end().to_string(),
+            },
+            None => line.trim_end().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A cached verdict. The key ignores hunk line numbers, so each finding's
+/// lines are kept relative to a hunk of the chunk it was reviewed in and
+/// rebased onto the same hunk of whichever chunk hits the entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedVerdict {
+    pub result: AuditResult,
+    /// One per finding in `result.issues`; `None` for findings without a
+    /// location in the chunk, which are served as they were.
+    anchors: Vec<Option<Anchor>>,
+}
+
+/// A finding's position: lines from the new-side start of the `hunk`-th
+/// hunk of its file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+struct Anchor {
+    hunk: usize,
+    start: i64,
+    end: Option<i64>,
+}
+
+impl CachedVerdict {
+    pub fn new(chunk: &str, result: AuditResult) -> Self {
+        let starts = hunk_starts(chunk);
+        let anchors = result
+            .issues
+            .iter()
+            .map(|issue| {
+                let location = issue.location.as_ref()?;
+                let file = starts.get(location.file.as_str())?;
+                // The last hunk starting at or before the line, else the first.
+                let hunk = file
+                    .iter()
+                    .rposition(|&start| start <= location.start_line)
+                    .unwrap_or(0);
+                let origin = i64::from(*file.get(hunk)?);
+                Some(Anchor {
+                    hunk,
+                    start: i64::from(location.start_line) - origin,
+                    end: location.end_line.map(|end| i64::from(end) - origin),
+                })
+            })
+            .collect();
+        Self { result, anchors }
+    }
+
+    /// The verdict with its lines rebased onto the hunks of `chunk`.
+    pub fn rebase(mut self, chunk: &str) -> AuditResult {
+        let starts = hunk_starts(chunk);
+        for (issue, anchor) in self.result.issues.iter_mut().zip(&self.anchors) {
+            let (Some(location), Some(anchor)) = (issue.location.as_mut(), anchor) else {
+                continue;
+            };
+            let Some(origin) = starts
+                .get(location.file.as_str())
+                .and_then(|file| file.get(anchor.hunk))
+            else {
+                continue;
+            };
+            let origin = i64::from(*origin);
+            location.start_line = u32::try_from(origin + anchor.start).unwrap_or(0);
+            location.end_line = anchor
+                .end
+                .map(|end| u32::try_from(origin + end).unwrap_or(0));
+        }
+        self.result
+    }
+}
+
+/// New-side start line of each hunk in `chunk`, per file, in order.
+fn hunk_starts(chunk: &str) -> HashMap<String, Vec<u32>> {
+    diff::parse(chunk)
+        .into_iter()
+        .map(|file| (file.path, file.hunks.iter().map(|h| h.new_start).collect()))
+        .collect()
+}
+
+#[async_trait]
+pub trait ReviewCache: Send + Sync {
+    async fn get(&self, key: &CacheKey) -> anyhow::Result<Option<CachedVerdict>>;
+
+    async fn put(
+        &self,
+        key: &CacheKey,
+        verdict: &CachedVerdict,
+        ttl: Duration,
+    ) -> anyhow::Result<()>;
+}
+
+// ─────────────────────────────────────────────
+// Postgres
+// ─────────────────────────────────────────────
+pub struct PgReviewCache {
+    db: PgPool,
+}
+
+impl PgReviewCache {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Deletes expired rows and rows written under another prompt or rule-pack version.
+    pub async fn purge_stale(
+        &self,
+        prompt_version: &str,
+        rule_pack_version: &str,
+    ) -> anyhow::Result<u64> {
+        let deleted = sqlx::query(
+            "DELETE FROM ai_review_cache
+             WHERE expires_at <= NOW() OR prompt_version <> $1 OR rule_pack_version <> $2",
+        )
+        .bind(prompt_version)
+        .bind(rule_pack_version)
+        .execute(&self.db)
+        .await
+        .context("Failed to purge AI review cache")?
+        .rows_affected();
+
+        Ok(deleted)
+    }
+}
+
+#[async_trait]
+impl ReviewCache for PgReviewCache {
+    async fn get(&self, key: &CacheKey) -> anyhow::Result<Option<CachedVerdict>> {
+        let row: Option<serde_json::Value> = sqlx::query_scalar(
+            "SELECT result FROM ai_review_cache WHERE cache_key = $1 AND expires_at > NOW()",
+        )
+        .bind(&key.key)
+        .fetch_optional(&self.db)
+        .await
+        .context("Failed to read AI review cache")?;
+
+        // Entries from before verdicts carried anchors are misses, not errors.
+        Ok(row.and_then(|value| serde_json::from_value(value).ok()))
+    }
+
+    async fn put(
+        &self,
+        key: &CacheKey,
+        verdict: &CachedVerdict,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let expires_at: DateTime<Utc> =
+            Utc::now() + chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::days(7));
+
+        sqlx::query(
+            r#"
+            INSERT INTO ai_review_cache
+            (cache_key, model, prompt_version, rule_pack_version, result, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (cache_key)
+            DO UPDATE SET result = EXCLUDED.result, expires_at = EXCLUDED.expires_at, created_at = NOW()
+            "#,
+        )
+        .bind(&key.key)
+        .bind(&key.model)
+        .bind(&key.prompt_version)
+        .bind(&key.rule_pack_version)
+        .bind(serde_json::to_value(verdict)?)
+        .bind(expires_at)
+        .execute(&self.db)
+        .await
+        .context("Failed to write AI review cache")?;
+
+        Ok(())
+    }
+}
+
+// ─────────────────────────────────────────────
+// In-memory (tests and offline runs)
+// ─────────────────────────────────────────────
+#[derive(Default)]
+pub struct MemoryReviewCache {
+    entries: Mutex<HashMap<String, (CachedVerdict, DateTime<Utc>)>>,
+}
+
+#[async_trait]
+impl ReviewCache for MemoryReviewCache {
+    async fn get(&self, key: &CacheKey) -> anyhow::Result<Option<CachedVerdict>> {
+        let entries = self
+            .entries
+            .lock()
+            .map_err(|_| anyhow::anyhow!("cache lock poisoned"))?;
+        Ok(entries
+            .get(&key.key)
+            .filter(|(_, expires_at)| *expires_at > Utc::now())
+            .map(|(verdict, _)| verdict.clone()))
+    }
+
+    async fn put(
+        &self,
+        key: &CacheKey,
+        verdict: &CachedVerdict,
+        ttl: Duration,
+    ) -> anyhow::Result<()> {
+        let expires_at =
+            Utc::now() + chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::days(7));
+        self.entries
+            .lock()
+            .map_err(|_| anyhow::anyhow!("cache lock poisoned"))?
+            .insert(key.key.clone(), (verdict.clone(), expires_at));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_shifts_and_blob_hashes_do_not_change_the_key() {
+        let a =
+            "diff --git a/x.rs b/x.rs\nindex 111..222\n@@ -10,2 +10,3 @@ fn f()\n+let x = 1;  \n";
+        let b = "diff --git a/x.rs b/x.rs\nindex 333..444\n@@ -52,2 +57,3 @@ fn f()\n+let x = 1;\n";
+        assert_eq!(
+            CacheKey::new(a, "t", "m", "p1", "r1"),
+            CacheKey::new(b, "t", "m", "p1", "r1")
+        );
+    }
+
+    #[test]
+    fn cached_lines_follow_their_hunk() {
+        use crate::models::{Analyzer, Finding, Location, Severity};
+        let at = |line| {
+            Finding::new(Analyzer::Ai, "PHI_LOGGING", Severity::High, "x")
+                .at(Location::line("x.rs", line))
+        };
+        let cached = "+++ b/x.rs\n@@ -1,1 +1,2 @@\n a\n+b\n@@ -20,1 +21,2 @@\n c\n+d\n";
+        let shifted = "+++ b/x.rs\n@@ -5,1 +9,2 @@\n a\n+b\n@@ -90,1 +100,2 @@\n c\n+d\n";
+        let verdict = CachedVerdict::new(
+            cached,
+            AuditResult {
+                issues: vec![
+                    at(2),
+                    at(22),
+                    Finding::new(Analyzer::Ai, "X", Severity::Low, "y"),
+                ],
+                ..Default::default()
+            },
+        );
+        let lines: Vec<_> = verdict
+            .rebase(shifted)
+            .issues
+            .iter()
+            .map(|f| f.location.as_ref().map(|l| l.start_line))
+            .collect();
+        assert_eq!(lines, vec![Some(10), Some(101), None]);
+    }
+
+    #[test]
+    fn scope_prompt_and_model_changes_invalidate() {
+        let chunk = "+let x = 1;";
+        let base = CacheKey::new(chunk, "t", "m", "p1", "r1").key;
+        assert_ne!(
+            base,
+            CacheKey::new(chunk, "other-tenant", "m", "p1", "r1").key
+        );
+        assert_ne!(base, CacheKey::new(chunk, "t", "m", "p2", "r1").key);
+        assert_ne!(base, CacheKey::new(chunk, "t", "other", "p1", "r1").key);
+        assert_ne!(base, CacheKey::new(chunk, "t", "m", "p1", "r2").key);
+    }
+}
@@ -0,0 +1,234 @@
+//! Record-and-replay for the AI reviewer, so the review pipeline can be tested
+//! offline against real model answers.
+
+use crate::hash::generate_hash;
+use crate::llm::{LlmRequest, LlmResponse, LlmReviewer};
+use crate::redact;
+use anyhow::Context;
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+
+static NONCE_RE: OnceLock<Option<Regex>> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CassetteMode {
+    /// Call the real reviewer and append every exchange to the cassette.
+    Record,
+    /// Serve exchanges from the cassette; never touch the network.
+    Replay,
+}
+
+impl FromStr for CassetteMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "record" => Ok(Self::Record),
+            "replay" => Ok(Self::Replay),
+            other => anyhow::bail!("Unknown cassette mode: {}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RecordedResponse {
+    Text { content: String },
+    Blocked { reason: String },
+    Error { message: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    pub fingerprint: String,
+    pub model: String,
+    pub response: RecordedResponse,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read cassette {}", path.display()))?;
+        serde_json::from_str(&raw).with_context(|| format!("Invalid cassette {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write cassette {}", path.display()))
+    }
+}
+
+/// Identifies a request independently of the per-request fence nonce.
+pub fn request_fingerprint(request: &LlmRequest) -> String {
+    let prompt = match NONCE_RE
+        .get_or_init(|| Regex::new(r"<<<(END-)?([A-Z]+)-[0-9a-f]{32}>>>").ok())
+        .as_ref()
+    {
+        Some(re) => re
+            .replace_all(&request.prompt, "<<<${1}${2}>>>")
+            .into_owned(),
+        None => request.prompt.clone(),
+    };
+    generate_hash(&format!(
+        "{}\0{}\0{}",
+        request.system, prompt, request.max_output_tokens
+    ))
+}
+
+pub struct CassetteReviewer {
+    inner: Option<Box<dyn LlmReviewer>>,
+    path: PathBuf,
+    state: Mutex<(Cassette, Vec<bool>)>,
+}
+
+impl CassetteReviewer {
+    /// Wraps `inner`, writing each exchange to `path` as it happens.
+    pub fn record(inner: Box<dyn LlmReviewer>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            inner: Some(inner),
+            path: path.into(),
+            state: Mutex::new((Cassette::default(), vec![])),
+        }
+    }
+
+    pub fn replay(path: impl Into<PathBuf>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let cassette = Cassette::load(&path)?;
+        let used = vec![false; cassette.interactions.len()];
+        Ok(Self {
+            inner: None,
+            path,
+            state: Mutex::new((cassette, used)),
+        })
+    }
+
+    /// Applies `LLM_CASSETTE` (a file path) and `LLM_CASSETTE_MODE` (`record` or
+    /// `replay`, default `replay`) to a freshly built reviewer.
+    pub fn from_env(reviewer: Box<dyn LlmReviewer>) -> anyhow::Result<Box<dyn LlmReviewer>> {
+        let Ok(path) = std::env::var("LLM_CASSETTE") else {
+            return Ok(reviewer);
+        };
+        let mode = std::env::var("LLM_CASSETTE_MODE")
+            .map(|m| m.parse())
+            .unwrap_or(Ok(CassetteMode::Replay))?;
+
+        tracing::info!("AI reviewer cassette {} in {:?} mode", path, mode);
+        Ok(match mode {
+            CassetteMode::Record => Box::new(Self::record(reviewer, path)),
+            CassetteMode::Replay => Box::new(Self::replay(path)?),
+        })
+    }
+
+    fn lock(&self) -> anyhow::Result<std::sync::MutexGuard<'_, (Cassette, Vec<bool>)>> {
+        self.state
+            .lock()
+            .map_err(|_| anyhow::anyhow!("cassette lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl LlmReviewer for CassetteReviewer {
+    fn model(&self) -> String {
+        match &self.inner {
+            Some(inner) => inner.model(),
+            None => self
+                .lock()
+                .ok()
+                .and_then(|s| s.0.interactions.first().map(|i| i.model.clone()))
+                .unwrap_or_else(|| "cassette".to_string()),
+        }
+    }
+
+    fn endpoint(&self) -> Option<String> {
+        self.inner.as_ref().and_then(|inner| inner.endpoint())
+    }
+
+    fn context_tokens(&self) -> usize {
+        self.inner
+            .as_ref()
+            .map(|inner| inner.context_tokens())
+            .unwrap_or(8_192)
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> anyhow::Result<LlmResponse> {
+        let fingerprint = request_fingerprint(request);
+
+        let Some(inner) = &self.inner else {
+            let mut state = self.lock()?;
+            let (cassette, used) = &mut *state;
+            let index = cassette
+                .interactions
+                .iter()
+                .enumerate()
+                .position(|(i, x)| !used[i] && x.fingerprint == fingerprint)
+                .with_context(|| {
+                    format!(
+                        "No recorded interaction for request {} in cassette {}",
+                        fingerprint,
+                        self.path.display()
+                    )
+                })?;
+            used[index] = true;
+            return match &cassette.interactions[index].response {
+                RecordedResponse::Text { content } => Ok(LlmResponse::Text(content.clone())),
+                RecordedResponse::Blocked { reason } => Ok(LlmResponse::Blocked(reason.clone())),
+                RecordedResponse::Error { message } => Err(anyhow::anyhow!("{}", message)),
+            };
+        };
+
+        let outcome = inner.complete(request).await;
+        // Answers can quote the diff; fixtures are committed, so scrub them too.
+        let recorded = match &outcome {
+            Ok(LlmResponse::Text(text)) => RecordedResponse::Text {
+                content: redact::redact(text).text,
+            },
+            Ok(LlmResponse::Blocked(reason)) => RecordedResponse::Blocked {
+                reason: redact::redact(reason).text,
+            },
+            Err(e) => RecordedResponse::Error {
+                message: redact::redact(&format!("{:#}", e)).text,
+            },
+        };
+
+        let mut state = self.lock()?;
+        state.0.interactions.push(Interaction {
+            fingerprint,
+            model: inner.model(),
+            response: recorded,
+        });
+        state.1.push(true);
+        state.0.save(&self.path)?;
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fingerprint_ignores_fence_nonce() {
+        let request = |nonce: &str| LlmRequest {
+            system: "s".into(),
+            prompt: format!("<<<DIFF-{n}>>>\n+x\n<<<END-DIFF-{n}>>>", n = nonce),
+            max_output_tokens: 10,
+        };
+        assert_eq!(
+            request_fingerprint(&request(&"a".repeat(32))),
+            request_fingerprint(&request(&"b".repeat(32)))
+        );
+    }
+}
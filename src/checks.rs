@@ -0,0 +1,179 @@
+//! Check run output for the GitHub Checks API, so the Guard can be made a
+//! required status check in branch protection.
+
+use crate::models::{AuditResult, Finding, Severity, Status};
+use crate::sarif;
+use serde_json::{json, Value};
+
+/// Name of the check run; this is what branch protection rules refer to.
+pub const CHECK_NAME: &str = sarif::TOOL_NAME;
+
+/// GitHub accepts at most 50 annotations per create or update call.
+pub const MAX_ANNOTATIONS_PER_CALL: usize = 50;
+
+/// Limit on `output.summary` and `output.text`, in characters.
+pub const OUTPUT_LIMIT: usize = 65_535;
+
+/// A violation fails the check; findings that need a human leave it neutral.
+pub fn conclusion(status: Status) -> &'static str {
+    match status {
+        Status::Violation => "failure",
+        Status::NeedsReview => "neutral",
+        Status::Clean | Status::Blocked => "success",
+    }
+}
+
+pub fn annotation_level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "failure",
+        Severity::Medium => "warning",
+        Severity::Low | Severity::Info => "notice",
+    }
+}
+
+/// One-line title shown next to the check in the PR.
+pub fn title(result: &AuditResult) -> String {
+    match result.issues.len() {
+        0 => "No PHI/HIPAA findings".to_string(),
+        1 => format!("1 finding, risk score {}", result.risk_score),
+        n => format!("{} findings, risk score {}", n, result.risk_score),
+    }
+}
+
+/// Annotations for located findings. Findings without a file are only in the
+/// summary, since an annotation needs a path.
+pub fn annotations(result: &AuditResult) -> Vec<Value> {
+    result.issues.iter().filter_map(annotation).collect()
+}
+
+fn annotation(issue: &Finding) -> Option<Value> {
+    let location = issue.location.as_ref()?;
+    let start = location.start_line.max(1);
+    let end = location.end_line.unwrap_or(start).max(start);
+
+    let mut value = json!({
+        "path": location.file,
+        "start_line": start,
+        "end_line": end,
+        "annotation_level": annotation_level(issue.severity),
+        "title": format!("{} {}", issue.rule_id, issue.category),
+        "message": issue.message,
+    });
+    // Columns are only allowed on single-line annotations.
+    if start == end {
+        if let Some(column) = location.start_column {
+            value["start_column"] = json!(column);
+        }
+        if let Some(column) = location.end_column {
+            value["end_column"] = json!(column);
+        }
+    }
+    if let Some(remediation) = &issue.remediation {
+        value["raw_details"] = json!(remediation);
+    }
+    Some(value)
+}
+
+/// PATCH bodies that finish a check run: every batch but the last only adds
+/// annotations, and the last one also completes the run. GitHub appends the
+/// annotations of each update.
+pub fn completion_updates(result: &AuditResult, summary: &str) -> Vec<Value> {
+    let annotations = annotations(result);
+    let output = |batch: &[Value]| {
+        json!({
+            "title": title(result),
+            "summary": truncate(summary, OUTPUT_LIMIT),
+            "annotations": batch,
+        })
+    };
+
+    let mut batches: Vec<&[Value]> = annotations.chunks(MAX_ANNOTATIONS_PER_CALL).collect();
+    let last = batches.pop().unwrap_or_default();
+    let mut updates: Vec<Value> = batches
+        .into_iter()
+        .map(|batch| json!({ "output": output(batch) }))
+        .collect();
+    updates.push(json!({
+        "status": "completed",
+        "conclusion": conclusion(result.status),
+        "output": output(last),
+    }));
+    updates
+}
+
+/// PATCH body for a scan that could not finish. It fails closed, and the
+/// author can re-run it from the Checks tab.
+pub fn failure_update(error: &str) -> Value {
+    json!({
+        "status": "completed",
+        "conclusion": "failure",
+        "output": {
+            "title": "Scan did not complete",
+            "summary": truncate(
+                &format!("The scan failed before a verdict was reached. Re-run the check to try again.\n\n```\n{}\n```", error),
+                OUTPUT_LIMIT,
+            ),
+        },
+    })
+}
+
+fn truncate(text: &str, limit: usize) -> String {
+    match text.char_indices().nth(limit) {
+        Some((cut, _)) => text[..cut].to_string(),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location};
+
+    fn located(n: u32) -> Finding {
+        Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "PHI logged")
+            .at(Location::line("src/a.rs", n))
+    }
+
+    #[test]
+    fn batches_annotations_and_completes_last() {
+        let mut issues: Vec<Finding> = (1..=120).map(located).collect();
+        issues.push(Finding::new(
+            Analyzer::Ai,
+            "SAFETY_FILTER",
+            Severity::Info,
+            "blocked",
+        ));
+        let result = AuditResult {
+            status: Status::Violation,
+            issues,
+            ..Default::default()
+        };
+
+        let updates = completion_updates(&result, "summary");
+        assert_eq!(updates.len(), 3);
+        assert_eq!(
+            updates[0]["output"]["annotations"].as_array().map(Vec::len),
+            Some(50)
+        );
+        assert!(updates[0].get("status").is_none());
+        assert_eq!(
+            updates[2]["output"]["annotations"].as_array().map(Vec::len),
+            Some(20)
+        );
+        assert_eq!(updates[2]["status"], "completed");
+        assert_eq!(updates[2]["conclusion"], "failure");
+        assert_eq!(
+            updates[0]["output"]["annotations"][0]["annotation_level"],
+            "failure"
+        );
+    }
+
+    #[test]
+    fn clean_result_completes_in_one_call() {
+        let updates = completion_updates(&AuditResult::default(), "all clear");
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0]["conclusion"], "success");
+        assert_eq!(updates[0]["output"]["title"], "No PHI/HIPAA findings");
+        assert_eq!(conclusion(Status::NeedsReview), "neutral");
+    }
+}
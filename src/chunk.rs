@@ -0,0 +1,243 @@
+use crate::diff::{self, FileDiff, Hunk};
+use crate::models::Finding;
+use std::collections::HashSet;
+
+/// A slice of a diff small enough for a single AI review request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunk {
+    /// Stable label such as `src/a.rs#2` (hunk 2) or `src/a.rs#2.1` (part of hunk 2).
+    pub id: String,
+    pub path: String,
+    pub text: String,
+}
+
+/// Rough token count: ~4 bytes per token for code, which over-counts slightly
+/// for English prose and keeps us under the real limit.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// Splits a diff into chunks of at most `budget` tokens. Whole files are kept
+/// together when they fit, then consecutive hunks, and oversized hunks are split
+/// into windows overlapping by `context_lines` so each part keeps its surroundings.
+pub fn chunk_diff(diff: &str, budget: usize, context_lines: usize) -> Vec<Chunk> {
+    let budget = budget.max(64);
+    let mut chunks = vec![];
+
+    for file in diff::parse(diff) {
+        let rendered = file.render();
+        if estimate_tokens(&rendered) <= budget {
+            chunks.push(Chunk {
+                id: label(&file.path, None),
+                path: file.path.clone(),
+                text: rendered,
+            });
+            continue;
+        }
+
+        let header = file.header.join("\n");
+        let room = budget.saturating_sub(estimate_tokens(&header) + 1).max(32);
+        chunk_file(&file, &header, room, context_lines, &mut chunks);
+    }
+
+    chunks
+}
+
+fn chunk_file(
+    file: &FileDiff,
+    header: &str,
+    room: usize,
+    context_lines: usize,
+    chunks: &mut Vec<Chunk>,
+) {
+    let with_header = |body: &str| {
+        if header.is_empty() {
+            body.to_string()
+        } else {
+            format!("{}\n{}", header, body)
+        }
+    };
+
+    let mut packed: Vec<String> = vec![];
+    let mut packed_ids: Vec<usize> = vec![];
+    let flush = |packed: &mut Vec<String>, ids: &mut Vec<usize>, chunks: &mut Vec<Chunk>| {
+        if packed.is_empty() {
+            return;
+        }
+        let first = ids.first().copied().unwrap_or(1);
+        let last = ids.last().copied().unwrap_or(first);
+        let id = if first == last {
+            format!("{}", first)
+        } else {
+            format!("{}-{}", first, last)
+        };
+        chunks.push(Chunk {
+            id: label(&file.path, Some(&id)),
+            path: file.path.clone(),
+            text: with_header(&packed.join("\n")),
+        });
+        packed.clear();
+        ids.clear();
+    };
+
+    for (i, hunk) in file.hunks.iter().enumerate() {
+        let n = i + 1;
+        let rendered = hunk.render();
+        let tokens = estimate_tokens(&rendered);
+
+        if tokens > room {
+            flush(&mut packed, &mut packed_ids, chunks);
+            for (part, window) in split_hunk(hunk, room, context_lines)
+                .into_iter()
+                .enumerate()
+            {
+                chunks.push(Chunk {
+                    id: label(&file.path, Some(&format!("{}.{}", n, part + 1))),
+                    path: file.path.clone(),
+                    text: with_header(&window.render()),
+                });
+            }
+            continue;
+        }
+
+        let used: usize = packed.iter().map(|p| estimate_tokens(p) + 1).sum();
+        if used + tokens > room {
+            flush(&mut packed, &mut packed_ids, chunks);
+        }
+        packed.push(rendered);
+        packed_ids.push(n);
+    }
+    flush(&mut packed, &mut packed_ids, chunks);
+}
+
+/// Windows over one hunk's lines, each re-headed with correct line numbers.
+fn split_hunk(hunk: &Hunk, room: usize, context_lines: usize) -> Vec<Hunk> {
+    let mut windows = vec![];
+    let mut start = 0;
+
+    while start < hunk.lines.len() {
+        let mut end = start;
+        let mut used = estimate_tokens(&hunk.header()) + 1;
+        while end < hunk.lines.len() {
+            let cost = estimate_tokens(&hunk.lines[end].render()) + 1;
+            if used + cost > room && end > start {
+                break;
+            }
+            used += cost;
+            end += 1;
+        }
+
+        let lines = hunk.lines[start..end].to_vec();
+        let first = &lines[0];
+        windows.push(Hunk {
+            old_start: first.old_line.unwrap_or_else(|| next_old(hunk, start)),
+            new_start: first.new_line.unwrap_or_else(|| next_new(hunk, start)),
+            section: hunk.section.clone(),
+            lines,
+        });
+
+        if end >= hunk.lines.len() {
+            break;
+        }
+        // Step back for overlap, but always make progress.
+        start = end.saturating_sub(context_lines).max(start + 1);
+    }
+
+    windows
+}
+
+fn next_old(hunk: &Hunk, from: usize) -> u32 {
+    hunk.lines[from..]
+        .iter()
+        .find_map(|l| l.old_line)
+        .unwrap_or(hunk.old_start)
+}
+
+fn next_new(hunk: &Hunk, from: usize) -> u32 {
+    hunk.lines[from..]
+        .iter()
+        .find_map(|l| l.new_line)
+        .unwrap_or(hunk.new_start)
+}
+
+fn label(path: &str, part: Option<&str>) -> String {
+    let path = if path.is_empty() { "diff" } else { path };
+    match part {
+        Some(part) => format!("{}#{}", path, part),
+        None => path.to_string(),
+    }
+}
+
+/// Merges findings from several chunks, dropping duplicates at the same file
+/// and line that differ only in case or whitespace (overlapping windows often
+/// report the same thing twice). The same message elsewhere is kept.
+pub fn dedupe_issues(issues: Vec<Finding>) -> Vec<Finding> {
+    let mut seen = HashSet::new();
+    issues
+        .into_iter()
+        .filter(|issue| {
+            let message = issue
+                .message
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .to_lowercase();
+            let location = issue
+                .location
+                .as_ref()
+                .map(|l| (l.file.clone(), l.start_line));
+            seen.insert((issue.category.clone(), issue.severity, location, message))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location, Severity};
+
+    fn big_diff(lines: usize) -> String {
+        let mut diff = format!(
+            "diff --git a/src/big.rs b/src/big.rs\n--- a/src/big.rs\n+++ b/src/big.rs\n@@ -1,0 +1,{} @@\n",
+            lines
+        );
+        for i in 0..lines {
+            diff.push_str(&format!("+let value_{} = compute_something({});\n", i, i));
+        }
+        diff
+    }
+
+    #[test]
+    fn small_files_stay_whole() {
+        let chunks = chunk_diff(&big_diff(3), 1_000, 3);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].id, "src/big.rs");
+    }
+
+    #[test]
+    fn oversized_hunks_split_within_budget_with_overlap() {
+        let chunks = chunk_diff(&big_diff(200), 300, 3);
+        assert!(chunks.len() > 1);
+        assert!(chunks.iter().all(|c| estimate_tokens(&c.text) <= 300));
+        assert!(chunks.iter().all(|c| c.text.starts_with("diff --git")));
+        assert_eq!(chunks[1].id, "src/big.rs#1.2");
+
+        // The last lines of one window reappear at the top of the next.
+        let tail = chunks[0].text.lines().last().unwrap_or_default();
+        assert!(chunks[1].text.contains(tail));
+    }
+
+    #[test]
+    fn dedupes_across_chunks() {
+        let issue = |m: &str, file: &str| {
+            Finding::new(Analyzer::Ai, "PHI_LOGGING", Severity::High, m).at(Location::line(file, 3))
+        };
+        let merged = dedupe_issues(vec![
+            issue("SSN  logged", "src/a.rs"),
+            issue("ssn logged", "src/a.rs"),
+            issue("x", "src/a.rs"),
+            issue("ssn logged", "src/b.rs"),
+        ]);
+        assert_eq!(merged.len(), 3);
+    }
+}
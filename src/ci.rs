@@ -0,0 +1,162 @@
+//! Running inside a GitHub Actions job instead of behind the GitHub App: the
+//! event comes from `GITHUB_EVENT_PATH`, the diff from the local checkout and
+//! the credentials from the workflow's `GITHUB_TOKEN`.
+
+use crate::github::PullRequest;
+use crate::markdown::SourceLinks;
+use anyhow::Context;
+use octocrab::Octocrab;
+use secrecy::SecretString;
+use std::path::PathBuf;
+
+/// What a workflow run tells its steps about itself.
+#[derive(Debug, Clone)]
+pub struct ActionsContext {
+    /// `GITHUB_EVENT_NAME`, e.g. `pull_request` or `push`.
+    pub event_name: String,
+    /// The webhook payload at `GITHUB_EVENT_PATH`.
+    pub event: serde_json::Value,
+    /// `GITHUB_REPOSITORY`, `owner/repo`.
+    pub repository: String,
+    /// `GITHUB_SHA`: the merge commit for PRs, the pushed commit for pushes.
+    pub sha: String,
+    pub token: Option<SecretString>,
+    /// `GITHUB_API_URL`, for GitHub Enterprise Server.
+    pub api_url: Option<String>,
+    /// `GITHUB_STEP_SUMMARY`, the file job-summary Markdown is appended to.
+    pub step_summary: Option<PathBuf>,
+}
+
+impl ActionsContext {
+    pub fn from_env() -> anyhow::Result<Self> {
+        let var = |name: &str| std::env::var(name).ok().filter(|v| !v.is_empty());
+        let event_path = var("GITHUB_EVENT_PATH")
+            .context("GITHUB_EVENT_PATH not set; `ghosthealth ci` runs inside GitHub Actions")?;
+        let event = std::fs::read_to_string(&event_path)
+            .with_context(|| format!("Failed to read {}", event_path))?;
+        Ok(Self {
+            event_name: var("GITHUB_EVENT_NAME").unwrap_or_default(),
+            event: serde_json::from_str(&event).context("Invalid event payload")?,
+            repository: var("GITHUB_REPOSITORY").unwrap_or_default(),
+            sha: var("GITHUB_SHA").context("GITHUB_SHA not set")?,
+            token: var("GITHUB_TOKEN").map(SecretString::new),
+            api_url: var("GITHUB_API_URL"),
+            step_summary: var("GITHUB_STEP_SUMMARY").map(PathBuf::from),
+        })
+    }
+
+    /// The PR for `pull_request` and `pull_request_target` events.
+    pub fn pull_request(&self) -> Option<PullRequest> {
+        if !self.event_name.starts_with("pull_request") {
+            return None;
+        }
+        PullRequest::from_payload(&self.event)
+    }
+
+    /// Commit range to scan: the PR's base to its head; for other events,
+    /// `before..GITHUB_SHA`, or just the last commit when there is no
+    /// `before`. `None` for a PR event without a base commit.
+    pub fn range(&self) -> Option<String> {
+        let sha = |v: &serde_json::Value| {
+            v.as_str()
+                .filter(|s| !s.is_empty() && s.bytes().any(|b| b != b'0'))
+                .map(str::to_string)
+        };
+        if self.event_name.starts_with("pull_request") {
+            let pr = &self.event["pull_request"];
+            let head = sha(&pr["head"]["sha"]).unwrap_or_else(|| self.sha.clone());
+            return Some(format!("{}...{}", sha(&pr["base"]["sha"])?, head));
+        }
+        let before = sha(&self.event["before"]).unwrap_or_else(|| format!("{}~1", self.sha));
+        Some(format!("{}..{}", before, self.sha))
+    }
+
+    /// Links to changed lines at the scanned commit.
+    pub fn source_links(&self) -> Option<SourceLinks> {
+        if let Some(pr) = self.pull_request() {
+            return pr.source_links();
+        }
+        let (owner, repo) = self.repository.split_once('/')?;
+        Some(SourceLinks {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            head_sha: self.sha.clone(),
+        })
+    }
+
+    /// A client acting as the workflow, for posting the review.
+    pub fn client(&self) -> anyhow::Result<Octocrab> {
+        let token = self
+            .token
+            .clone()
+            .context("GITHUB_TOKEN not set; pass it to the step as `env: GITHUB_TOKEN`")?;
+        let mut builder = Octocrab::builder().personal_token(token);
+        if let Some(url) = &self.api_url {
+            builder = builder.base_uri(url.as_str())?;
+        }
+        Ok(builder.build()?)
+    }
+
+    /// Appends `markdown` to the job summary, if the runner provides one.
+    pub fn write_step_summary(&self, markdown: &str) -> anyhow::Result<()> {
+        use std::io::Write;
+        let Some(path) = &self.step_summary else {
+            return Ok(());
+        };
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open {}", path.display()))?;
+        writeln!(file, "{}", markdown)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context(event_name: &str, event: serde_json::Value) -> ActionsContext {
+        ActionsContext {
+            event_name: event_name.into(),
+            event,
+            repository: "acme/ehr".into(),
+            sha: "merge".into(),
+            token: None,
+            api_url: None,
+            step_summary: None,
+        }
+    }
+
+    #[test]
+    fn reads_ranges_from_pull_request_and_push_events() {
+        let pr = context(
+            "pull_request",
+            json!({
+                "repository": {"full_name": "acme/ehr"},
+                "pull_request": {
+                    "number": 7,
+                    "base": {"sha": "base"},
+                    "head": {"sha": "head", "ref": "fix", "repo": {"full_name": "acme/ehr"}}
+                }
+            }),
+        );
+        assert_eq!(pr.range().as_deref(), Some("base...head"));
+        assert_eq!(pr.pull_request().map(|p| p.number), Some(7));
+        assert_eq!(
+            pr.source_links().map(|l| l.head_sha).as_deref(),
+            Some("head")
+        );
+
+        let push = context(
+            "push",
+            json!({"before": "0000000000000000000000000000000000000000"}),
+        );
+        assert_eq!(push.range().as_deref(), Some("merge~1..merge"));
+        assert!(push.pull_request().is_none());
+        let push = context("push", json!({"before": "old"}));
+        assert_eq!(push.range().as_deref(), Some("old..merge"));
+    }
+}
@@ -0,0 +1,330 @@
+//! Versioned mapping from finding categories to regulatory controls.
+//!
+//! Auditors ask which safeguard a finding relates to; the answer lives here
+//! rather than in prose. Bump `CATALOG_VERSION` whenever a mapping changes so
+//! stored reports say which edition they were tagged with.
+
+use crate::models::{Finding, Severity};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+pub const CATALOG_VERSION: &str = "2026.10";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Framework {
+    Hipaa,
+    Gdpr,
+    Soc2,
+    Hitrust,
+}
+
+impl fmt::Display for Framework {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Hipaa => "HIPAA",
+            Self::Gdpr => "GDPR",
+            Self::Soc2 => "SOC 2",
+            Self::Hitrust => "HITRUST",
+        })
+    }
+}
+
+impl FromStr for Framework {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().replace(' ', "").as_str() {
+            "hipaa" => Ok(Self::Hipaa),
+            "gdpr" => Ok(Self::Gdpr),
+            "soc2" => Ok(Self::Soc2),
+            "hitrust" | "hitrustcsf" => Ok(Self::Hitrust),
+            other => anyhow::bail!("Unknown framework: {}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Control {
+    pub framework: Framework,
+    pub id: &'static str,
+    pub title: &'static str,
+}
+
+impl Control {
+    /// Stable reference stored on findings, e.g. `HIPAA §164.312(b)`.
+    pub fn key(&self) -> String {
+        format!("{} {}", self.framework, self.id)
+    }
+}
+
+const fn control(framework: Framework, id: &'static str, title: &'static str) -> Control {
+    Control {
+        framework,
+        id,
+        title,
+    }
+}
+
+use Framework::{Gdpr, Hipaa, Hitrust, Soc2};
+
+pub const CONTROLS: &[Control] = &[
+    control(Hipaa, "§164.312(a)(1)", "Access control"),
+    control(Hipaa, "§164.312(a)(2)(iv)", "Encryption and decryption"),
+    control(Hipaa, "§164.312(b)", "Audit controls"),
+    control(Hipaa, "§164.312(c)(1)", "Integrity"),
+    control(Hipaa, "§164.312(d)", "Person or entity authentication"),
+    control(Hipaa, "§164.312(e)(1)", "Transmission security"),
+    control(Hipaa, "§164.502(b)", "Minimum necessary"),
+    control(Gdpr, "Art. 5(1)(f)", "Integrity and confidentiality"),
+    control(Gdpr, "Art. 25", "Data protection by design and by default"),
+    control(Gdpr, "Art. 32", "Security of processing"),
+    control(Soc2, "CC6.1", "Logical and physical access controls"),
+    control(Soc2, "CC6.7", "Restriction of data transmission"),
+    control(
+        Soc2,
+        "CC7.1",
+        "Detection of vulnerabilities and configuration changes",
+    ),
+    control(Soc2, "CC7.2", "Monitoring of system components"),
+    control(Soc2, "CC8.1", "Change management"),
+    control(Hitrust, "01.v", "Information access restriction"),
+    control(
+        Hitrust,
+        "06.d",
+        "Data protection and privacy of covered information",
+    ),
+    control(Hitrust, "09.aa", "Audit logging"),
+    control(
+        Hitrust,
+        "09.s",
+        "Information exchange policies and procedures",
+    ),
+    control(Hitrust, "10.b", "Input data validation"),
+    control(Hitrust, "10.c", "Control of internal processing"),
+    control(Hitrust, "10.g", "Management of cryptographic keys"),
+    control(Hitrust, "10.m", "Control of technical vulnerabilities"),
+];
+
+const MEMORY_SAFETY: &[&str] = &[
+    "HIPAA §164.312(c)(1)",
+    "GDPR Art. 32",
+    "SOC 2 CC7.1",
+    "SOC 2 CC8.1",
+    "HITRUST 10.c",
+    "HITRUST 10.m",
+];
+
+const PHI_DISCLOSURE: &[&str] = &[
+    "HIPAA §164.312(a)(1)",
+    "HIPAA §164.502(b)",
+    "GDPR Art. 5(1)(f)",
+    "GDPR Art. 25",
+    "GDPR Art. 32",
+    "SOC 2 CC6.1",
+    "HITRUST 06.d",
+];
+
+/// Category to control keys. Every category an analyzer can emit must be listed.
+const MAPPINGS: &[(&str, &[&str])] = &[
+    ("PHI_LOGGING", PHI_DISCLOSURE),
+    ("PHI_EXPOSURE", PHI_DISCLOSURE),
+    ("DEBUG_TRAIT_LEAK", PHI_DISCLOSURE),
+    ("SEMGREP", PHI_DISCLOSURE),
+    ("PHI_LITERAL", PHI_DISCLOSURE),
+    (
+        "HARDCODED_SECRET",
+        &[
+            "HIPAA §164.312(a)(1)",
+            "HIPAA §164.312(d)",
+            "GDPR Art. 32",
+            "SOC 2 CC6.1",
+            "HITRUST 10.g",
+        ],
+    ),
+    (
+        "UNENCRYPTED_TRANSIT",
+        &[
+            "HIPAA §164.312(e)(1)",
+            "GDPR Art. 32",
+            "SOC 2 CC6.7",
+            "HITRUST 09.s",
+        ],
+    ),
+    (
+        "INSECURE_STORAGE",
+        &[
+            "HIPAA §164.312(a)(2)(iv)",
+            "HIPAA §164.312(c)(1)",
+            "GDPR Art. 32",
+            "SOC 2 CC6.1",
+            "HITRUST 06.d",
+        ],
+    ),
+    (
+        "MISSING_ACCESS_CONTROL",
+        &[
+            "HIPAA §164.312(a)(1)",
+            "HIPAA §164.312(d)",
+            "GDPR Art. 25",
+            "GDPR Art. 32",
+            "SOC 2 CC6.1",
+            "HITRUST 01.v",
+        ],
+    ),
+    (
+        "MISSING_AUDIT_LOG",
+        &[
+            "HIPAA §164.312(b)",
+            "GDPR Art. 32",
+            "SOC 2 CC7.2",
+            "HITRUST 09.aa",
+        ],
+    ),
+    (
+        "SENSITIVE_FUNCTION",
+        &[
+            "HIPAA §164.312(a)(1)",
+            "HIPAA §164.312(b)",
+            "SOC 2 CC6.1",
+            "HITRUST 01.v",
+        ],
+    ),
+    (
+        "PROMPT_INJECTION",
+        &["HIPAA §164.312(c)(1)", "SOC 2 CC8.1", "HITRUST 10.b"],
+    ),
+    ("UNSAFE_BLOCK", MEMORY_SAFETY),
+    ("UNSAFE_FUNCTION", MEMORY_SAFETY),
+    ("UNSAFE_IMPL", MEMORY_SAFETY),
+    ("UNSAFE_TRAIT", MEMORY_SAFETY),
+    ("UNSAFE_INCREASE", MEMORY_SAFETY),
+    ("UNSAFE_NOT_FORBIDDEN", MEMORY_SAFETY),
+    ("FFI_FUNCTION", MEMORY_SAFETY),
+    ("EXTERN_BLOCK", MEMORY_SAFETY),
+    ("NO_MANGLE", MEMORY_SAFETY),
+    ("RAW_POINTER_DEREF", MEMORY_SAFETY),
+    ("UNSAFE_DEREF", MEMORY_SAFETY),
+    ("TRANSMUTE", MEMORY_SAFETY),
+];
+
+pub fn find(key: &str) -> Option<&'static Control> {
+    CONTROLS.iter().find(|c| c.key() == key)
+}
+
+/// Controls a category relates to; empty for informational categories.
+pub fn controls_for(category: &str) -> Vec<&'static Control> {
+    MAPPINGS
+        .iter()
+        .find(|(c, _)| *c == category)
+        .map(|(_, keys)| keys.iter().filter_map(|k| find(k)).collect())
+        .unwrap_or_default()
+}
+
+/// Tags each finding with the keys of the controls it relates to.
+pub fn annotate(issues: &mut [Finding]) {
+    for issue in issues {
+        issue.controls = controls_for(&issue.category)
+            .iter()
+            .map(|c| c.key())
+            .collect();
+    }
+}
+
+/// Findings that relate to `key`.
+pub fn filter_by_control<'a>(issues: &'a [Finding], key: &str) -> Vec<&'a Finding> {
+    issues
+        .iter()
+        .filter(|i| i.controls.iter().any(|c| c == key))
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlPosture {
+    pub key: String,
+    pub title: String,
+    pub findings: usize,
+    pub highest_severity: Severity,
+}
+
+/// Finding counts per affected control, optionally limited to one framework,
+/// ordered as in the catalog.
+pub fn posture(issues: &[Finding], framework: Option<Framework>) -> Vec<ControlPosture> {
+    let mut counts: BTreeMap<String, (usize, Severity)> = BTreeMap::new();
+    for issue in issues {
+        for key in &issue.controls {
+            let entry = counts.entry(key.clone()).or_default();
+            entry.0 += 1;
+            entry.1 = entry.1.max(issue.severity);
+        }
+    }
+
+    CONTROLS
+        .iter()
+        .filter(|c| framework.map_or(true, |f| c.framework == f))
+        .filter_map(|c| {
+            let (findings, highest_severity) = counts.remove(&c.key())?;
+            Some(ControlPosture {
+                key: c.key(),
+                title: c.title.to_string(),
+                findings,
+                highest_severity,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Analyzer;
+
+    #[test]
+    fn every_analyzer_category_maps_to_known_controls() {
+        let deterministic = [
+            "PHI_LOGGING",
+            "PHI_LITERAL",
+            "UNSAFE_BLOCK",
+            "HARDCODED_SECRET",
+            "SEMGREP",
+            "UNSAFE_FUNCTION",
+            "FFI_FUNCTION",
+            "NO_MANGLE",
+            "SENSITIVE_FUNCTION",
+            "UNSAFE_IMPL",
+            "UNSAFE_TRAIT",
+            "EXTERN_BLOCK",
+            "RAW_POINTER_DEREF",
+            "UNSAFE_DEREF",
+            "TRANSMUTE",
+            "UNSAFE_NOT_FORBIDDEN",
+            "UNSAFE_INCREASE",
+        ];
+        for category in crate::audit::AI_CATEGORIES.iter().chain(&deterministic) {
+            assert!(
+                !controls_for(category).is_empty(),
+                "{} has no controls",
+                category
+            );
+        }
+        for (_, keys) in MAPPINGS {
+            assert!(keys.iter().all(|k| find(k).is_some()));
+        }
+    }
+
+    #[test]
+    fn aggregates_posture_per_control() {
+        let mut issues = vec![
+            Finding::new(Analyzer::Ai, "MISSING_AUDIT_LOG", Severity::Medium, "x"),
+            Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "x"),
+        ];
+        annotate(&mut issues);
+
+        let hipaa = posture(&issues, Some(Framework::Hipaa));
+        assert!(hipaa.iter().all(|p| p.key.starts_with("HIPAA")));
+        assert_eq!(hipaa[0].key, "HIPAA §164.312(a)(1)");
+        assert_eq!(hipaa[0].highest_severity, Severity::High);
+        assert_eq!(filter_by_control(&issues, "HITRUST 09.aa").len(), 1);
+    }
+}
@@ -0,0 +1,463 @@
+//! Unified diff model shared by chunking, grounding and inline review comments.
+
+use std::collections::HashSet;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineKind {
+    Added,
+    Removed,
+    Context,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffLine {
+    pub kind: LineKind,
+    /// Line text without the leading `+`/`-`/` ` marker.
+    pub content: String,
+    pub old_line: Option<u32>,
+    pub new_line: Option<u32>,
+}
+
+impl DiffLine {
+    pub fn render(&self) -> String {
+        let marker = match self.kind {
+            LineKind::Added => '+',
+            LineKind::Removed => '-',
+            LineKind::Context => ' ',
+        };
+        format!("{}{}", marker, self.content)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hunk {
+    pub old_start: u32,
+    pub new_start: u32,
+    /// Section text after the closing `@@`, usually the enclosing function.
+    pub section: String,
+    pub lines: Vec<DiffLine>,
+}
+
+impl Hunk {
+    pub fn header(&self) -> String {
+        let old_len = self
+            .lines
+            .iter()
+            .filter(|l| l.kind != LineKind::Added)
+            .count();
+        let new_len = self
+            .lines
+            .iter()
+            .filter(|l| l.kind != LineKind::Removed)
+            .count();
+        format!(
+            "@@ -{},{} +{},{} @@{}",
+            self.old_start, old_len, self.new_start, new_len, self.section
+        )
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = self.header();
+        for line in &self.lines {
+            out.push('\n');
+            out.push_str(&line.render());
+        }
+        out
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileDiff {
+    /// Path on the new side, or the old path for deletions. Empty for bare
+    /// `+`/`-` snippets that carry no file headers.
+    pub path: String,
+    /// `diff --git`, `index`, `---`, `+++` and similar lines, verbatim.
+    pub header: Vec<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FileDiff {
+    pub fn render(&self) -> String {
+        let mut parts = self.header.clone();
+        parts.extend(self.hunks.iter().map(Hunk::render));
+        parts.join("\n")
+    }
+
+    /// The diff line for `line` on the new side, if it is part of this diff.
+    pub fn new_line(&self, line: u32) -> Option<&DiffLine> {
+        self.hunks
+            .iter()
+            .flat_map(|h| h.lines.iter())
+            .find(|l| l.new_line == Some(line))
+    }
+
+    /// Path on the old side; `None` for an added file.
+    pub fn old_path(&self) -> Option<&str> {
+        for line in &self.header {
+            if let Some(old) = line.strip_prefix("--- ") {
+                return old.strip_prefix("a/");
+            }
+            if let Some(old) = line.strip_prefix("rename from ") {
+                return Some(old);
+            }
+            if line.starts_with("new file mode") {
+                return None;
+            }
+        }
+        Some(&self.path)
+    }
+
+    pub fn added_lines(&self) -> impl Iterator<Item = &DiffLine> {
+        self.hunks
+            .iter()
+            .flat_map(|h| h.lines.iter())
+            .filter(|l| l.kind == LineKind::Added)
+    }
+}
+
+/// Position within the hunk being read.
+#[derive(Default)]
+struct Cursor {
+    old_no: u32,
+    new_no: u32,
+    /// Lines still expected per side, from the hunk header.
+    old_left: u32,
+    new_left: u32,
+}
+
+impl Cursor {
+    fn start(old_start: u32, old_len: u32, new_start: u32, new_len: u32) -> Self {
+        Self {
+            old_no: old_start,
+            new_no: new_start,
+            old_left: old_len,
+            new_left: new_len,
+        }
+    }
+
+    fn in_hunk(&self) -> bool {
+        self.old_left > 0 || self.new_left > 0
+    }
+
+    fn read(&mut self, raw: &str) -> DiffLine {
+        let (kind, content) = match raw.chars().next() {
+            Some('+') => (LineKind::Added, &raw[1..]),
+            Some('-') => (LineKind::Removed, &raw[1..]),
+            Some(' ') => (LineKind::Context, &raw[1..]),
+            _ => (LineKind::Context, raw),
+        };
+        let old_line = (kind != LineKind::Added).then_some(self.old_no);
+        let new_line = (kind != LineKind::Removed).then_some(self.new_no);
+        if old_line.is_some() {
+            self.old_no += 1;
+            self.old_left = self.old_left.saturating_sub(1);
+        }
+        if new_line.is_some() {
+            self.new_no += 1;
+            self.new_left = self.new_left.saturating_sub(1);
+        }
+        DiffLine {
+            kind,
+            content: content.to_string(),
+            old_line,
+            new_line,
+        }
+    }
+}
+
+/// A diff that adds `content` as a new file, so whole files can go through
+/// the diff-based scanners.
+pub fn added_file(path: &str, content: &str) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut out = format!(
+        "diff --git a/{0} b/{0}\n--- /dev/null\n+++ b/{0}\n@@ -0,0 +1,{1} @@\n",
+        path,
+        lines.len()
+    );
+    for line in lines {
+        out.push('+');
+        out.push_str(line);
+        out.push('\n');
+    }
+    out
+}
+
+/// A diff presenting lines `start..=end` (1-based) of `content` as added,
+/// with up to `context` unchanged lines either side.
+pub fn added_excerpt(path: &str, content: &str, start: u32, end: u32, context: u32) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let last = (end + context).min(lines.len() as u32);
+    let first = start.saturating_sub(context).max(1).min(last + 1);
+    let added = (start..=end.min(last)).count();
+    let total = (first..=last).count();
+    let mut out = format!(
+        "diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}\n@@ -{1},{2} +{1},{3} @@\n",
+        path,
+        first,
+        total - added,
+        total
+    );
+    for n in first..=last {
+        out.push(if (start..=end).contains(&n) { '+' } else { ' ' });
+        out.push_str(lines[n as usize - 1]);
+        out.push('\n');
+    }
+    out
+}
+
+/// Parses a unified diff. Input without file or hunk headers is treated as a
+/// single anonymous hunk starting at line 1, so hand-written snippets still work.
+pub fn parse(diff: &str) -> Vec<FileDiff> {
+    let mut files: Vec<FileDiff> = vec![];
+    let mut cursor = Cursor::default();
+
+    for raw in diff.lines() {
+        if raw.starts_with('\\') {
+            continue; // "\ No newline at end of file"
+        }
+
+        if !cursor.in_hunk() {
+            if raw.starts_with("diff --git ") {
+                files.push(FileDiff {
+                    path: git_header_path(raw),
+                    header: vec![raw.to_string()],
+                    hunks: vec![],
+                });
+                continue;
+            }
+            if let Some(path) = raw.strip_prefix("--- ") {
+                let file = current_file(&mut files);
+                if file.path.is_empty() && path != "/dev/null" {
+                    file.path = strip_prefix_dir(path);
+                }
+                file.header.push(raw.to_string());
+                continue;
+            }
+            if let Some(path) = raw.strip_prefix("+++ ") {
+                let file = current_file(&mut files);
+                if path != "/dev/null" {
+                    file.path = strip_prefix_dir(path);
+                }
+                file.header.push(raw.to_string());
+                continue;
+            }
+            if is_meta_line(raw) {
+                last_file(&mut files).header.push(raw.to_string());
+                continue;
+            }
+
+            let (old_start, old_len, new_start, new_len, section) = match parse_hunk_header(raw) {
+                Some(h) => (h.old_start, h.old_len, h.new_start, h.new_len, h.section),
+                // Bare snippet: one open-ended hunk from line 1.
+                None if !raw.trim().is_empty() => (1, u32::MAX, 1, u32::MAX, String::new()),
+                None => continue,
+            };
+            cursor = Cursor::start(old_start, old_len, new_start, new_len);
+            last_file(&mut files).hunks.push(Hunk {
+                old_start,
+                new_start,
+                section,
+                lines: vec![],
+            });
+            if raw.starts_with("@@ ") {
+                continue;
+            }
+        }
+
+        let line = cursor.read(raw);
+        if let Some(hunk) = files.last_mut().and_then(|f| f.hunks.last_mut()) {
+            hunk.lines.push(line);
+        }
+    }
+
+    files
+}
+
+/// Indexes (0-based) of the lines of `diff` that are headers rather than
+/// content: `diff --git`, `---`/`+++`, `index` and similar, and `@@` lines.
+/// Follows hunk lengths like `parse`, so a removed `-- x` line is content.
+pub fn header_lines(diff: &str) -> HashSet<usize> {
+    let mut headers = HashSet::new();
+    let mut cursor = Cursor::default();
+    for (i, raw) in diff.lines().enumerate() {
+        if raw.starts_with('\\') {
+            continue;
+        }
+        if !cursor.in_hunk() {
+            let is_header = raw.starts_with("diff --git ")
+                || raw.starts_with("--- ")
+                || raw.starts_with("+++ ")
+                || is_meta_line(raw);
+            if let Some(h) = parse_hunk_header(raw) {
+                cursor = Cursor::start(h.old_start, h.old_len, h.new_start, h.new_len);
+                headers.insert(i);
+                continue;
+            }
+            if is_header {
+                headers.insert(i);
+                continue;
+            }
+            if raw.trim().is_empty() {
+                continue;
+            }
+            cursor = Cursor::start(1, u32::MAX, 1, u32::MAX);
+        }
+        cursor.read(raw);
+    }
+    headers
+}
+
+/// File for a `---`/`+++` header: a new one unless the last is still collecting headers.
+fn current_file(files: &mut Vec<FileDiff>) -> &mut FileDiff {
+    if files.last().map_or(true, |f| !f.hunks.is_empty()) {
+        files.push(FileDiff::default());
+    }
+    last_file(files)
+}
+
+fn last_file(files: &mut Vec<FileDiff>) -> &mut FileDiff {
+    if files.is_empty() {
+        files.push(FileDiff::default());
+    }
+    let last = files.len() - 1;
+    &mut files[last]
+}
+
+fn is_meta_line(line: &str) -> bool {
+    [
+        "index ",
+        "new file mode",
+        "deleted file mode",
+        "old mode",
+        "new mode",
+        "similarity index",
+        "rename from",
+        "rename to",
+        "Binary files",
+    ]
+    .iter()
+    .any(|p| line.starts_with(p))
+}
+
+fn git_header_path(line: &str) -> String {
+    line.rsplit_once(" b/")
+        .map(|(_, path)| path.to_string())
+        .unwrap_or_default()
+}
+
+fn strip_prefix_dir(path: &str) -> String {
+    let path = path.split('\t').next().unwrap_or(path);
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+        .to_string()
+}
+
+struct HunkHeader {
+    old_start: u32,
+    old_len: u32,
+    new_start: u32,
+    new_len: u32,
+    section: String,
+}
+
+/// `@@ -12,5 +14,7 @@ fn main()`. A missing length means 1.
+fn parse_hunk_header(line: &str) -> Option<HunkHeader> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (ranges, section) = rest.split_once(" @@")?;
+    let (old, new) = ranges.split_once(" +")?;
+    let range = |r: &str| -> Option<(u32, u32)> {
+        match r.split_once(',') {
+            Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_len) = range(old)?;
+    let (new_start, new_len) = range(new)?;
+    Some(HunkHeader {
+        old_start,
+        old_len,
+        new_start,
+        new_len,
+        section: section.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIFF: &str = "diff --git a/src/a.rs b/src/a.rs
+index 1111111..2222222 100644
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -10,3 +10,4 @@ fn main() {
+     let x = 1;
+-    old();
++    new();
++    println!(\"{}\", ssn);
+ }
+diff --git a/src/b.rs b/src/b.rs
+new file mode 100644
+--- /dev/null
++++ b/src/b.rs
+@@ -0,0 +1,2 @@
++fn b() {}
++fn c() {}
+";
+
+    #[test]
+    fn parses_files_hunks_and_line_numbers() {
+        let files = parse(DIFF);
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path, "src/a.rs");
+        assert_eq!(files[1].path, "src/b.rs");
+        assert_eq!(files[0].old_path(), Some("src/a.rs"));
+        assert_eq!(files[1].old_path(), None);
+
+        let added: Vec<u32> = files[0].added_lines().filter_map(|l| l.new_line).collect();
+        assert_eq!(added, vec![11, 12]);
+        assert_eq!(
+            files[0].new_line(13).map(|l| l.kind),
+            Some(LineKind::Context)
+        );
+    }
+
+    #[test]
+    fn round_trips_rendering() {
+        let files = parse(DIFF);
+        assert_eq!(files[0].hunks[0].header(), "@@ -10,3 +10,4 @@ fn main() {");
+        assert!(files[1]
+            .render()
+            .starts_with("diff --git a/src/b.rs b/src/b.rs"));
+    }
+
+    #[test]
+    fn tells_headers_from_content() {
+        let diff =
+            "diff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1,2 +1,1 @@\n--- not a header\n context\n";
+        let headers = header_lines(diff);
+        assert_eq!(
+            (0..6).filter(|i| headers.contains(i)).collect::<Vec<_>>(),
+            [0, 1, 2, 3]
+        );
+        assert!(header_lines("+ let x = 1;\n+++ y\n").is_empty());
+    }
+
+    #[test]
+    fn excerpts_mark_only_the_chosen_lines_as_added() {
+        let excerpt = added_excerpt("src/a.rs", "a\nb\nc\nd\ne\n", 3, 3, 1);
+        let files = parse(&excerpt);
+        let added: Vec<_> = files[0].added_lines().map(|l| l.new_line).collect();
+        assert_eq!(added, vec![Some(3)]);
+        assert!(excerpt.contains("@@ -2,2 +2,3 @@\n b\n+c\n d\n"));
+    }
+
+    #[test]
+    fn bare_snippets_become_one_hunk() {
+        let files = parse("+ fn update_user() {\n+    let ssn = 1;\n+ }\n");
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "");
+        assert_eq!(files[0].added_lines().count(), 3);
+    }
+}
@@ -0,0 +1,480 @@
+//! Serializes an `AuditResult` for tools outside the PR review: CI test
+//! reports, code-quality widgets and spreadsheets for compliance officers.
+
+use crate::hash::generate_hash;
+use crate::models::{AuditResult, Finding, Severity};
+use crate::sarif;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Format {
+    /// The stored report shape.
+    #[default]
+    Json,
+    Sarif,
+    Junit,
+    /// GitLab Code Quality report.
+    Gitlab,
+    Checkstyle,
+    Csv,
+}
+
+impl Format {
+    pub const ALL: [Format; 6] = [
+        Self::Json,
+        Self::Sarif,
+        Self::Junit,
+        Self::Gitlab,
+        Self::Checkstyle,
+        Self::Csv,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Sarif => "sarif",
+            Self::Junit => "junit",
+            Self::Gitlab => "gitlab",
+            Self::Checkstyle => "checkstyle",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "sarif" => Ok(Self::Sarif),
+            "junit" | "junit-xml" => Ok(Self::Junit),
+            "gitlab" | "codequality" | "code-quality" => Ok(Self::Gitlab),
+            "checkstyle" => Ok(Self::Checkstyle),
+            "csv" => Ok(Self::Csv),
+            other => anyhow::bail!("Unknown output format: {}", other),
+        }
+    }
+}
+
+/// Turns a result into one report document.
+pub trait ReportWriter: Send + Sync {
+    fn format(&self) -> Format;
+
+    /// File extension for the written report, without the dot.
+    fn extension(&self) -> &'static str;
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String>;
+}
+
+/// The writer registered for `format`.
+pub fn writer(format: Format) -> &'static dyn ReportWriter {
+    match format {
+        Format::Json => &JsonWriter,
+        Format::Sarif => &SarifWriter,
+        Format::Junit => &JunitWriter,
+        Format::Gitlab => &GitlabWriter,
+        Format::Checkstyle => &CheckstyleWriter,
+        Format::Csv => &CsvWriter,
+    }
+}
+
+pub fn render(result: &AuditResult, format: Format) -> anyhow::Result<String> {
+    writer(format).write(result)
+}
+
+// ─────────────────────────────────────────────
+// Per-tenant destinations
+// ─────────────────────────────────────────────
+
+/// Which formats each destination receives, e.g. `{"ci": ["junit", "gitlab"],
+/// "compliance": ["csv"]}`. Stored in `tenants.report_formats`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ReportSettings {
+    pub destinations: BTreeMap<String, Vec<Format>>,
+}
+
+impl Default for ReportSettings {
+    fn default() -> Self {
+        Self {
+            destinations: BTreeMap::from([("archive".to_string(), vec![Format::Json])]),
+        }
+    }
+}
+
+impl ReportSettings {
+    pub async fn for_tenant(db: &PgPool, tenant_id: uuid::Uuid) -> anyhow::Result<Self> {
+        let value: Option<Option<serde_json::Value>> =
+            sqlx::query_scalar("SELECT report_formats FROM tenants WHERE id = $1")
+                .bind(tenant_id)
+                .fetch_optional(db)
+                .await
+                .context("Failed to load tenant report formats")?;
+        match value.flatten() {
+            Some(value) => serde_json::from_value(value).context("Invalid report_formats"),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn formats_for(&self, destination: &str) -> &[Format] {
+        self.destinations
+            .get(destination)
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Renders every configured report as `(destination, format, document)`.
+    pub fn render_all(
+        &self,
+        result: &AuditResult,
+    ) -> anyhow::Result<Vec<(String, Format, String)>> {
+        let mut reports = vec![];
+        for (destination, formats) in &self.destinations {
+            for &format in formats {
+                reports.push((destination.clone(), format, render(result, format)?));
+            }
+        }
+        Ok(reports)
+    }
+}
+
+// ─────────────────────────────────────────────
+// Writers
+// ─────────────────────────────────────────────
+pub struct JsonWriter;
+
+impl ReportWriter for JsonWriter {
+    fn format(&self) -> Format {
+        Format::Json
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(result)?)
+    }
+}
+
+pub struct SarifWriter;
+
+impl ReportWriter for SarifWriter {
+    fn format(&self) -> Format {
+        Format::Sarif
+    }
+
+    fn extension(&self) -> &'static str {
+        "sarif"
+    }
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String> {
+        Ok(serde_json::to_string_pretty(&sarif::to_sarif(result))?)
+    }
+}
+
+/// One testcase per rule and file. A case fails when any of its findings is
+/// above INFO; a clean scan reports a single passing case.
+pub struct JunitWriter;
+
+impl ReportWriter for JunitWriter {
+    fn format(&self) -> Format {
+        Format::Junit
+    }
+
+    fn extension(&self) -> &'static str {
+        "xml"
+    }
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String> {
+        let mut cases: BTreeMap<(&str, &str), Vec<&Finding>> = BTreeMap::new();
+        for issue in &result.issues {
+            cases
+                .entry((issue.rule_id.as_str(), file_of(issue)))
+                .or_default()
+                .push(issue);
+        }
+
+        let mut body = String::new();
+        let mut failures = 0;
+        for ((rule_id, file), issues) in &cases {
+            body.push_str(&format!(
+                "    <testcase classname=\"{}\" name=\"{}\">\n",
+                xml_escape(rule_id),
+                xml_escape(file)
+            ));
+            if let Some(worst) = issues
+                .iter()
+                .map(|i| i.severity)
+                .max()
+                .filter(|s| *s > Severity::Info)
+            {
+                failures += 1;
+                let details: Vec<String> = issues
+                    .iter()
+                    .map(|i| format!("[{}] {}", i.severity, i.message))
+                    .collect();
+                body.push_str(&format!(
+                    "      <failure type=\"{}\" message=\"{} {} finding(s)\">{}</failure>\n",
+                    xml_escape(&issues[0].category),
+                    worst,
+                    issues.len(),
+                    xml_escape(&details.join("\n"))
+                ));
+            }
+            body.push_str("    </testcase>\n");
+        }
+        if cases.is_empty() {
+            body.push_str("    <testcase classname=\"ghosthealth\" name=\"scan\"/>\n");
+        }
+
+        let tests = cases.len().max(1);
+        Ok(format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"{tool}\" tests=\"{tests}\" failures=\"{failures}\">\n  <testsuite name=\"{tool}\" tests=\"{tests}\" failures=\"{failures}\">\n{body}  </testsuite>\n</testsuites>\n",
+            tool = sarif::TOOL_NAME,
+        ))
+    }
+}
+
+/// GitLab Code Quality: a JSON array shown in the merge request widget.
+pub struct GitlabWriter;
+
+impl ReportWriter for GitlabWriter {
+    fn format(&self) -> Format {
+        Format::Gitlab
+    }
+
+    fn extension(&self) -> &'static str {
+        "json"
+    }
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String> {
+        let entries: Vec<serde_json::Value> = result
+            .issues
+            .iter()
+            .map(|issue| {
+                json!({
+                    "description": issue.message,
+                    "check_name": issue.rule_id,
+                    "fingerprint": issue.fingerprint.clone().unwrap_or_else(|| generate_hash(&format!(
+                        "{}\0{}\0{}",
+                        issue.rule_id,
+                        location_of(issue),
+                        issue.message
+                    ))),
+                    "severity": match issue.severity {
+                        Severity::Info => "info",
+                        Severity::Low => "minor",
+                        Severity::Medium => "major",
+                        Severity::High => "critical",
+                        Severity::Critical => "blocker",
+                    },
+                    "location": {
+                        "path": file_of(issue),
+                        "lines": { "begin": issue.location.as_ref().map_or(1, |l| l.start_line.max(1)) },
+                    },
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&entries)?)
+    }
+}
+
+pub struct CheckstyleWriter;
+
+impl ReportWriter for CheckstyleWriter {
+    fn format(&self) -> Format {
+        Format::Checkstyle
+    }
+
+    fn extension(&self) -> &'static str {
+        "xml"
+    }
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String> {
+        let mut files: BTreeMap<&str, Vec<&Finding>> = BTreeMap::new();
+        for issue in &result.issues {
+            files.entry(file_of(issue)).or_default().push(issue);
+        }
+
+        let mut out = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n",
+        );
+        for (file, issues) in files {
+            out.push_str(&format!("  <file name=\"{}\">\n", xml_escape(file)));
+            for issue in issues {
+                let position = issue
+                    .location
+                    .as_ref()
+                    .map(|l| match l.start_column {
+                        Some(column) => format!(" line=\"{}\" column=\"{}\"", l.start_line, column),
+                        None => format!(" line=\"{}\"", l.start_line),
+                    })
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "    <error{} severity=\"{}\" message=\"{}\" source=\"ghosthealth.{}\"/>\n",
+                    position,
+                    match issue.severity {
+                        Severity::Critical | Severity::High => "error",
+                        Severity::Medium => "warning",
+                        Severity::Low | Severity::Info => "info",
+                    },
+                    xml_escape(&issue.message),
+                    xml_escape(&issue.rule_id)
+                ));
+            }
+            out.push_str("  </file>\n");
+        }
+        out.push_str("</checkstyle>\n");
+        Ok(out)
+    }
+}
+
+/// One row per finding, for spreadsheets.
+pub struct CsvWriter;
+
+const CSV_HEADER: &[&str] = &[
+    "rule_id",
+    "category",
+    "severity",
+    "cwe",
+    "file",
+    "line",
+    "message",
+    "controls",
+    "analyzer",
+    "confidence",
+    "fingerprint",
+];
+
+impl ReportWriter for CsvWriter {
+    fn format(&self) -> Format {
+        Format::Csv
+    }
+
+    fn extension(&self) -> &'static str {
+        "csv"
+    }
+
+    fn write(&self, result: &AuditResult) -> anyhow::Result<String> {
+        let mut out = CSV_HEADER.join(",");
+        out.push_str("\r\n");
+        for issue in &result.issues {
+            let row = [
+                issue.rule_id.clone(),
+                issue.category.clone(),
+                issue.severity.to_string(),
+                issue.cwe.map(|c| format!("CWE-{}", c)).unwrap_or_default(),
+                issue
+                    .location
+                    .as_ref()
+                    .map(|l| l.file.clone())
+                    .unwrap_or_default(),
+                issue
+                    .location
+                    .as_ref()
+                    .map(|l| l.start_line.to_string())
+                    .unwrap_or_default(),
+                issue.message.clone(),
+                issue.controls.join("; "),
+                issue.analyzer.as_str().to_string(),
+                issue.confidence.as_str().to_string(),
+                issue.fingerprint.clone().unwrap_or_default(),
+            ];
+            let cells: Vec<String> = row.iter().map(|c| csv_cell(c)).collect();
+            out.push_str(&cells.join(","));
+            out.push_str("\r\n");
+        }
+        Ok(out)
+    }
+}
+
+// ─────────────────────────────────────────────
+// Helpers
+// ─────────────────────────────────────────────
+fn file_of(issue: &Finding) -> &str {
+    issue
+        .location
+        .as_ref()
+        .map_or("(repository)", |l| l.file.as_str())
+}
+
+fn location_of(issue: &Finding) -> String {
+    issue
+        .location
+        .as_ref()
+        .map(|l| l.to_string())
+        .unwrap_or_default()
+}
+
+fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\n' => out.push_str("&#10;"),
+            c if c.is_control() && c != '\t' => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// RFC 4180 quoting. Cells that a spreadsheet would evaluate as a formula
+/// get a leading `'`.
+fn csv_cell(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_quotes_and_defuses_formulas() {
+        assert_eq!(csv_cell("a,b"), "\"a,b\"");
+        assert_eq!(csv_cell("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_cell("=HYPERLINK(1)"), "'=HYPERLINK(1)");
+    }
+
+    #[test]
+    fn every_format_parses_and_has_a_writer() {
+        for format in Format::ALL {
+            assert_eq!(format.as_str().parse::<Format>().ok(), Some(format));
+            assert_eq!(writer(format).format(), format);
+        }
+        let settings: ReportSettings =
+            serde_json::from_str(r#"{"ci": ["junit", "gitlab"]}"#).unwrap_or_default();
+        assert_eq!(settings.formats_for("ci"), &[Format::Junit, Format::Gitlab]);
+        assert!(settings.formats_for("compliance").is_empty());
+    }
+}
@@ -1,9 +1,17 @@
-use crate::models::AuditResult;
-use crate::{audit, scanner};
+use crate::audit::ReviewOptions;
+use crate::autofix::SourceFile;
+use crate::diff::FileDiff;
+use crate::llm::LlmReviewer;
+use crate::models::{Analyzer, AuditResult, Finding, Status, FINDING_SCHEMA_VERSION};
+use crate::{
+    audit, chatops, checks, controls, injection, inline, lifecycle, markdown, sarif, scanner,
+    scoring,
+};
+use anyhow::Context;
 use octocrab::Octocrab;
 use serde_json::json;
+use std::collections::HashSet;
 
-#[allow(dead_code)]
 pub async fn get_pr_diff(
     client: &Octocrab,
     owner: &str,
@@ -13,67 +21,561 @@ pub async fn get_pr_diff(
     Ok(client.pulls(owner, repo).get_diff(pr_number).await?)
 }
 
-pub async fn process_diff(diff: &str) -> anyhow::Result<AuditResult> {
-    // 1. Run deterministic regex scan
+pub async fn process_diff(
+    reviewer: &dyn LlmReviewer,
+    options: &ReviewOptions,
+    diff: &str,
+) -> anyhow::Result<AuditResult> {
+    process_diff_with(Some(reviewer), options, diff, vec![]).await
+}
+
+/// `process_diff` with the AI review optional, plus `analyzed` findings from
+/// analyzers that need whole files (AST, Semgrep). An analyzed finding on the
+/// same rule and line as a diff finding is dropped.
+pub async fn process_diff_with(
+    reviewer: Option<&dyn LlmReviewer>,
+    options: &ReviewOptions,
+    diff: &str,
+    analyzed: Vec<Finding>,
+) -> anyhow::Result<AuditResult> {
+    // 1. Run deterministic regex scan and look for text aimed at the AI reviewer
     let mut issues = scanner::deterministic_scan(diff);
+    issues.extend(injection::detect(diff));
+    let key = |f: &Finding| {
+        (
+            f.rule_id.clone(),
+            f.location.clone().map(|l| (l.file, l.start_line)),
+        )
+    };
+    let seen: HashSet<_> = issues.iter().map(key).collect();
+    issues.extend(analyzed.into_iter().filter(|f| !seen.contains(&key(f))));
 
-    // 2. Run LLM review (Gemini 1.5 Flash Free Tier)
-    let mut ai = audit::llm_review(diff).await?;
+    // 2. Run LLM review with the tenant's provider, never trusting CLEAN over serious findings
+    let mut ai = match reviewer {
+        Some(reviewer) => {
+            let mut ai = audit::llm_review(reviewer, options, diff).await?;
+            audit::reconcile(&issues, &mut ai);
+            ai
+        }
+        None => AuditResult::default(),
+    };
 
     // If AI was blocked, we still report what the regex found
     issues.append(&mut ai.issues);
 
-    let risk_score = if ai.status == "VIOLATION" {
-        ai.risk_score.max((issues.len() * 10).min(100) as u8)
-    } else {
-        (issues.len() * 15).min(100) as u8
-    };
+    controls::annotate(&mut issues);
+    lifecycle::assign_fingerprints(&mut issues);
+    // Waived findings stay out of the score and the review.
+    let (waived, issues): (Vec<_>, Vec<_>) = issues.into_iter().partition(|f| {
+        f.fingerprint
+            .as_ref()
+            .is_some_and(|fp| options.waived.contains(fp))
+    });
+
+    // The model's own risk_score is ignored; every finding is weighed the same way.
+    let breakdown = scoring::score(&options.scoring, &issues);
+    let risk_score = breakdown.total;
 
-    let status = if risk_score > 30 || ai.status == "VIOLATION" {
-        "VIOLATION"
+    let status = if risk_score > options.scoring.violation_threshold {
+        Status::Violation
     } else {
-        "CLEAN"
+        // A BLOCKED AI verdict alone does not change the outcome of the deterministic scan,
+        // and an AI VIOLATION only counts while one of its findings survives grounding.
+        match ai.status {
+            Status::Blocked => Status::Clean,
+            Status::Violation if !issues.iter().any(|f| f.analyzer == Analyzer::Ai) => {
+                Status::Clean
+            }
+            s => s,
+        }
     };
 
     Ok(AuditResult {
-        status: status.into(),
+        schema_version: FINDING_SCHEMA_VERSION,
+        status,
         risk_score,
         issues,
+        ai_outcome: ai.ai_outcome,
+        redactions: ai.redactions,
+        failed_chunks: ai.failed_chunks,
+        grounding: ai.grounding,
+        catalog_version: Some(controls::CATALOG_VERSION.to_string()),
+        score_breakdown: Some(breakdown),
+        waived: waived.into_iter().filter_map(|f| f.fingerprint).collect(),
     })
 }
 
+/// The pull request a review, comment or upload is for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PullRequest {
+    pub owner: String,
+    pub repo: String,
+    pub number: u64,
+    pub head_sha: Option<String>,
+    /// Commit the PR's diff is taken against.
+    pub base_sha: Option<String>,
+    /// Head branch, when it is in this repository rather than a fork.
+    pub head_ref: Option<String>,
+}
+
+impl PullRequest {
+    /// Reads a `pull_request` webhook payload.
+    pub fn from_payload(payload: &serde_json::Value) -> Option<Self> {
+        let (owner, repo) = payload["repository"]["full_name"]
+            .as_str()?
+            .split_once('/')?;
+        Some(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: payload["pull_request"]["number"].as_u64()?,
+            head_sha: payload["pull_request"]["head"]["sha"]
+                .as_str()
+                .map(str::to_string),
+            base_sha: payload["pull_request"]["base"]["sha"]
+                .as_str()
+                .map(str::to_string),
+            head_ref: payload["pull_request"]["head"]["ref"]
+                .as_str()
+                .filter(|_| {
+                    payload["pull_request"]["head"]["repo"]["full_name"]
+                        == payload["repository"]["full_name"]
+                })
+                .map(str::to_string),
+        })
+    }
+
+    /// Reads the PR a webhook is about: a `pull_request` event, or a re-run
+    /// requested from a check run or check suite. Check events on commits
+    /// that are not the head of an open PR in this repository carry no PR.
+    pub fn from_event(event: &str, payload: &serde_json::Value) -> Option<Self> {
+        let check = match event {
+            "pull_request" => return Self::from_payload(payload),
+            "check_run" => &payload["check_run"],
+            "check_suite" => &payload["check_suite"],
+            _ => return None,
+        };
+        let (owner, repo) = payload["repository"]["full_name"]
+            .as_str()?
+            .split_once('/')?;
+        let pulls = check["pull_requests"]
+            .as_array()
+            .filter(|pulls| !pulls.is_empty())
+            .or_else(|| check["check_suite"]["pull_requests"].as_array())?;
+        let pull = pulls.first()?;
+        Some(Self {
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            number: pull["number"].as_u64()?,
+            head_sha: check["head_sha"].as_str().map(str::to_string),
+            base_sha: pull["base"]["sha"].as_str().map(str::to_string),
+            head_ref: None,
+        })
+    }
+
+    /// Links to the changed lines at the head commit, when it is known.
+    pub fn source_links(&self) -> Option<markdown::SourceLinks> {
+        Some(markdown::SourceLinks {
+            owner: self.owner.clone(),
+            repo: self.repo.clone(),
+            head_sha: self.head_sha.clone()?,
+        })
+    }
+}
+
+/// Reports a scan on the PR without piling up bot output:
+///
+/// - the full report goes in one summary comment, edited in place each scan;
+/// - a review is posted only to request changes or to carry line comments
+///   for located findings on lines of `files`. If GitHub rejects the line
+///   comments (e.g. the diff moved on), they are listed in the summary;
+/// - a clean scan dismisses the Guard's earlier blocking reviews and posts a
+///   note linking their audit hashes to `audit_hash`.
+///
+/// `audit_hash` is the scan's audit-chain hash, or a `sha256:` standalone
+/// digest (see `audit::standalone_digest`) when nothing was chained.
 pub async fn post_review(
     client: &Octocrab,
-    owner: &str,
-    repo: &str,
-    pr: u64,
+    pr: &PullRequest,
+    files: &[FileDiff],
     result: &AuditResult,
+    template: &markdown::Template,
+    audit_hash: &str,
 ) -> anyhow::Result<()> {
-    // Determine the header based on status
-    let header = match result.status.as_str() {
-        "VIOLATION" => "⚠️ **GhostHealth Guard: Action Required**",
-        "BLOCKED" => "ℹ️ **GhostHealth Guard: Scan Partial**",
-        _ => "✅ **GhostHealth Guard: Clean**",
-    };
+    let links = pr.source_links();
+    let plan = inline::plan(files, &result.issues);
+    let blocking = matches!(result.status, Status::Violation | Status::NeedsReview);
+    let mut inlined = plan.inlined.clone();
 
-    let body = json!({
-        "body": format!(
-            "{}\n\n**Status:** {}\n**Risk Score:** {}/100\n\n### Findings:\n{:#?}",
-            header, result.status, result.risk_score, result.issues
+    if blocking || !plan.comments.is_empty() {
+        let route = format!(
+            "/repos/{}/{}/pulls/{}/reviews",
+            pr.owner, pr.repo, pr.number
+        );
+        let mut body = json!({
+            "body": markdown::review_body(result, audit_hash),
+            "event": if blocking { "REQUEST_CHANGES" } else { "COMMENT" },
+            "comments": plan.comments,
+        });
+        if let Some(sha) = &pr.head_sha {
+            body["commit_id"] = json!(sha);
+        }
+
+        let posted = client
+            .post::<_, serde_json::Value>(route.clone(), Some(&body))
+            .await;
+        match posted {
+            Err(e) if !plan.comments.is_empty() => {
+                tracing::warn!(
+                    "Inline review comments rejected, listing them in the summary: {}",
+                    e
+                );
+                inlined.clear();
+                if blocking {
+                    body["comments"] = json!([]);
+                    client
+                        .post::<_, serde_json::Value>(route, Some(&body))
+                        .await?;
+                }
+            }
+            other => {
+                other?;
+            }
+        }
+    }
+
+    let summary = markdown::render_summary(result, template, links.as_ref(), &inlined);
+    upsert_summary_comment(client, pr, &markdown::summary_comment(&summary)).await?;
+
+    if !blocking {
+        let dismissed = dismiss_blocking_reviews(client, pr, audit_hash).await?;
+        if !dismissed.is_empty() {
+            let hashes: Vec<&str> = dismissed.iter().map(String::as_str).collect();
+            post_comment(client, pr, &markdown::resolved_note(&hashes, audit_hash)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Edits the Guard's summary comment, or creates it on the first scan.
+async fn upsert_summary_comment(
+    client: &Octocrab,
+    pr: &PullRequest,
+    body: &str,
+) -> anyhow::Result<()> {
+    let comments = list_all(
+        client,
+        &format!(
+            "/repos/{}/{}/issues/{}/comments",
+            pr.owner, pr.repo, pr.number
         ),
-        "event": if result.status == "VIOLATION" { "REQUEST_CHANGES" } else { "COMMENT" }
+    )
+    .await?;
+    let existing = comments.iter().find(|comment| {
+        is_bot(comment)
+            && comment["body"]
+                .as_str()
+                .is_some_and(|body| body.starts_with(markdown::SUMMARY_MARKER))
     });
 
-    let route = format!("/repos/{owner}/{repo}/pulls/{pr}/reviews");
+    if let Some(id) = existing.and_then(|comment| comment["id"].as_u64()) {
+        let route = format!("/repos/{}/{}/issues/comments/{}", pr.owner, pr.repo, id);
+        match client
+            .patch::<serde_json::Value, _, _>(route, Some(&json!({ "body": body })))
+            .await
+        {
+            Ok(_) => return Ok(()),
+            // Not ours after all (another bot copied the marker); start a new one.
+            Err(e) => tracing::warn!("Could not edit summary comment {}: {}", id, e),
+        }
+    }
+    post_comment(client, pr, body).await
+}
+
+pub async fn post_comment(client: &Octocrab, pr: &PullRequest, body: &str) -> anyhow::Result<()> {
+    let route = format!(
+        "/repos/{}/{}/issues/{}/comments",
+        pr.owner, pr.repo, pr.number
+    );
+    client
+        .post::<_, serde_json::Value>(route, Some(&json!({ "body": body })))
+        .await?;
+    Ok(())
+}
+
+/// Dismisses the Guard's reviews that still request changes and returns the
+/// audit hashes recorded in them.
+async fn dismiss_blocking_reviews(
+    client: &Octocrab,
+    pr: &PullRequest,
+    audit_hash: &str,
+) -> anyhow::Result<Vec<String>> {
+    let reviews = list_all(
+        client,
+        &format!(
+            "/repos/{}/{}/pulls/{}/reviews",
+            pr.owner, pr.repo, pr.number
+        ),
+    )
+    .await?;
+
+    let mut dismissed = vec![];
+    for review in &reviews {
+        if !is_bot(review) || review["state"] != "CHANGES_REQUESTED" {
+            continue;
+        }
+        let (Some(id), Some(hash)) = (
+            review["id"].as_u64(),
+            review["body"]
+                .as_str()
+                .and_then(markdown::review_audit_hash),
+        ) else {
+            continue;
+        };
+        let route = format!(
+            "/repos/{}/{}/pulls/{}/reviews/{}/dismissals",
+            pr.owner, pr.repo, pr.number, id
+        );
+        let body = json!({
+            "message": format!("Resolved by a clean GhostHealth Guard scan (audit {}).", audit_hash),
+            "event": "DISMISS",
+        });
+        client
+            .put::<serde_json::Value, _, _>(route, Some(&body))
+            .await?;
+        dismissed.push(hash.to_string());
+    }
+    Ok(dismissed)
+}
+
+/// Contents of `paths` at `git_ref`. Files that cannot be fetched are
+/// logged and left out.
+pub async fn fetch_files<'a>(
+    client: &Octocrab,
+    owner: &str,
+    repo: &str,
+    paths: impl IntoIterator<Item = &'a str>,
+    git_ref: &str,
+) -> Vec<SourceFile> {
+    let mut files = vec![];
+    for path in paths {
+        let page = client
+            .repos(owner, repo)
+            .get_content()
+            .path(path)
+            .r#ref(git_ref)
+            .send()
+            .await;
+        match page.map(|p| p.items.first().and_then(|item| item.decoded_content())) {
+            Ok(Some(content)) => files.push(SourceFile {
+                path: path.to_string(),
+                content,
+            }),
+            Ok(None) => tracing::warn!("No content for {} at {}", path, git_ref),
+            Err(e) => tracing::warn!("Could not fetch {}: {}", path, e),
+        }
+    }
+    files
+}
+
+/// The PR as returned by `GET /pulls/{n}`, shaped like the `pull_request`
+/// object of a webhook payload.
+pub async fn get_pull_request(
+    client: &Octocrab,
+    pr: &PullRequest,
+) -> anyhow::Result<serde_json::Value> {
+    let route = format!("/repos/{}/{}/pulls/{}", pr.owner, pr.repo, pr.number);
+    Ok(client.get(route, None::<&()>).await?)
+}
+
+/// The user's role on the repository; `Role::None` if they are not a
+/// collaborator.
+pub async fn collaborator_role(
+    client: &Octocrab,
+    owner: &str,
+    repo: &str,
+    user: &str,
+) -> anyhow::Result<chatops::Role> {
+    let route = format!(
+        "/repos/{}/{}/collaborators/{}/permission",
+        owner, repo, user
+    );
+    let response: serde_json::Value = client.get(route, None::<&()>).await?;
+    Ok(chatops::Role::from_permission(&response))
+}
 
-    // Post the review to GitHub
+/// Reacts to an issue comment, e.g. `eyes` to acknowledge a command.
+pub async fn react_to_comment(
+    client: &Octocrab,
+    owner: &str,
+    repo: &str,
+    comment_id: u64,
+    content: &str,
+) -> anyhow::Result<()> {
+    let route = format!(
+        "/repos/{}/{}/issues/comments/{}/reactions",
+        owner, repo, comment_id
+    );
     client
-        .post::<_, serde_json::Value>(route, Some(&body))
+        .post::<_, serde_json::Value>(route, Some(&json!({ "content": content })))
         .await?;
+    Ok(())
+}
+
+fn is_bot(item: &serde_json::Value) -> bool {
+    item["user"]["type"] == "Bot"
+}
+
+/// GETs every page of a list endpoint.
+async fn list_all(client: &Octocrab, route: &str) -> anyhow::Result<Vec<serde_json::Value>> {
+    const PER_PAGE: usize = 100;
+    let mut items = vec![];
+    for page in 1.. {
+        let batch: Vec<serde_json::Value> = client
+            .get(
+                format!("{}?per_page={}&page={}", route, PER_PAGE, page),
+                None::<&()>,
+            )
+            .await?;
+        let done = batch.len() < PER_PAGE;
+        items.extend(batch);
+        if done {
+            break;
+        }
+    }
+    Ok(items)
+}
 
+/// Uploads the result as SARIF to code scanning for the PR's head commit, so
+/// findings appear in the Security tab. Needs the `security_events: write`
+/// permission. Returns the upload id GitHub assigns.
+pub async fn upload_sarif(
+    client: &Octocrab,
+    pr: &PullRequest,
+    result: &AuditResult,
+) -> anyhow::Result<String> {
+    let commit_sha = pr
+        .head_sha
+        .as_deref()
+        .context("SARIF upload needs the head commit")?;
+    let body = json!({
+        "commit_sha": commit_sha,
+        "ref": format!("refs/pull/{}/head", pr.number),
+        "sarif": sarif::encode_for_upload(&sarif::to_sarif(result))?,
+        "tool_name": sarif::TOOL_NAME,
+    });
+    let route = format!("/repos/{}/{}/code-scanning/sarifs", pr.owner, pr.repo);
+
+    let response: serde_json::Value = client.post(route, Some(&body)).await?;
+    Ok(response["id"].as_str().unwrap_or_default().to_string())
+}
+
+/// Creates a queued check run on the PR's head commit and returns its id.
+/// Needs the `checks: write` permission.
+pub async fn create_check_run(client: &Octocrab, pr: &PullRequest) -> anyhow::Result<u64> {
+    let head_sha = pr
+        .head_sha
+        .as_deref()
+        .context("A check run needs the head commit")?;
+    let body = json!({
+        "name": checks::CHECK_NAME,
+        "head_sha": head_sha,
+        "status": "queued",
+        "external_id": pr.number.to_string(),
+    });
+    let route = format!("/repos/{}/{}/check-runs", pr.owner, pr.repo);
+
+    let response: serde_json::Value = client.post(route, Some(&body)).await?;
+    response["id"]
+        .as_u64()
+        .context("Check run response has no id")
+}
+
+pub async fn start_check_run(client: &Octocrab, pr: &PullRequest, id: u64) -> anyhow::Result<()> {
+    let body = json!({
+        "status": "in_progress",
+        "started_at": chrono::Utc::now().to_rfc3339(),
+    });
+    update_check_run(client, pr, id, &body).await
+}
+
+/// Completes the check run with the verdict, the review summary and one
+/// annotation per located finding, sent in batches of 50.
+pub async fn complete_check_run(
+    client: &Octocrab,
+    pr: &PullRequest,
+    id: u64,
+    result: &AuditResult,
+    template: &markdown::Template,
+) -> anyhow::Result<()> {
+    let summary = markdown::render(result, template, pr.source_links().as_ref());
+    for body in checks::completion_updates(result, &summary) {
+        update_check_run(client, pr, id, &body).await?;
+    }
+    Ok(())
+}
+
+pub async fn fail_check_run(
+    client: &Octocrab,
+    pr: &PullRequest,
+    id: u64,
+    error: &anyhow::Error,
+) -> anyhow::Result<()> {
+    update_check_run(
+        client,
+        pr,
+        id,
+        &checks::failure_update(&format!("{:#}", error)),
+    )
+    .await
+}
+
+async fn update_check_run(
+    client: &Octocrab,
+    pr: &PullRequest,
+    id: u64,
+    body: &serde_json::Value,
+) -> anyhow::Result<()> {
+    let route = format!("/repos/{}/{}/check-runs/{}", pr.owner, pr.repo, id);
+    client
+        .patch::<serde_json::Value, _, _>(route, Some(body))
+        .await?;
     Ok(())
 }
+
 #[allow(dead_code)]
 pub fn post_review_dummy() {
     println!("Mocking GitHub Review: Analysis report would be posted here.");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rerequested_checks_resolve_their_pull_request() {
+        let payload = json!({
+            "action": "rerequested",
+            "repository": { "full_name": "acme/ehr" },
+            "check_run": {
+                "head_sha": "abc123",
+                "pull_requests": [],
+                "check_suite": { "pull_requests": [{ "number": 7, "base": { "sha": "def456" } }] },
+            },
+        });
+        let pr = PullRequest::from_event("check_run", &payload);
+        assert_eq!(pr.as_ref().map(|pr| pr.number), Some(7));
+        assert_eq!(
+            pr.as_ref().and_then(|pr| pr.head_sha.as_deref()),
+            Some("abc123")
+        );
+        assert_eq!(pr.and_then(|pr| pr.base_sha).as_deref(), Some("def456"));
+
+        let fork = json!({
+            "repository": { "full_name": "acme/ehr" },
+            "check_suite": { "head_sha": "abc123", "pull_requests": [] },
+        });
+        assert_eq!(PullRequest::from_event("check_suite", &fork), None);
+        assert_eq!(PullRequest::from_event("push", &payload), None);
+    }
+}
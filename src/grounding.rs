@@ -0,0 +1,178 @@
+use crate::diff::{FileDiff, LineKind};
+use crate::models::{Confidence, Finding, GroundingStats, Location};
+
+/// Checks each AI finding's location and quoted snippet against the parsed diff.
+///
+/// A finding whose quote sits on the cited added line is kept as is. If the
+/// quote is on another added line of the same file, the citation is moved
+/// there, to the matching line nearest the cited one. If only the line is
+/// right, severity drops one level. Anything that cannot be tied to a changed
+/// line is removed. Quotes shorter than `MIN_QUOTE_CHARS` never count as
+/// found, and a quote must start and end on token boundaries, so `}` or
+/// `let` cannot ground or move a made-up finding.
+pub fn ground_findings(files: &[FileDiff], issues: Vec<Finding>) -> (Vec<Finding>, GroundingStats) {
+    let mut stats = GroundingStats::default();
+    let mut kept = vec![];
+
+    for mut issue in issues {
+        stats.cited += 1;
+        let quote = issue.snippet.as_deref().map(normalize).unwrap_or_default();
+        let file = issue.location.as_ref().map(|l| l.file.as_str());
+        let candidates: Vec<&FileDiff> = files
+            .iter()
+            .filter(|f| f.path.is_empty() || Some(f.path.as_str()) == file)
+            .collect();
+
+        let cited = issue
+            .location
+            .as_ref()
+            .map(|l| l.start_line)
+            .and_then(|line| {
+                candidates
+                    .iter()
+                    .find_map(|f| f.new_line(line))
+                    .filter(|l| l.kind == LineKind::Added)
+            });
+        if let Some(line) = cited {
+            if quotes(&line.content, &quote) {
+                stats.grounded += 1;
+                kept.push(issue);
+                continue;
+            }
+        }
+
+        let cited_line = issue.location.as_ref().map_or(0, |l| l.start_line);
+        let relocated = candidates
+            .iter()
+            .flat_map(|f| {
+                f.added_lines()
+                    .filter(|l| quotes(&l.content, &quote))
+                    .map(|l| (f.path.clone(), l.new_line))
+            })
+            .min_by_key(|(_, line)| line.unwrap_or_default().abs_diff(cited_line));
+
+        match (relocated, cited) {
+            (Some((path, line)), _) => {
+                stats.relocated += 1;
+                let file = if path.is_empty() {
+                    file.unwrap_or("diff").to_string()
+                } else {
+                    path
+                };
+                issue.location = Some(Location::line(file, line.unwrap_or_default()));
+                kept.push(issue);
+            }
+            (None, Some(_)) => {
+                stats.downgraded += 1;
+                issue.severity = issue.severity.downgrade();
+                issue.confidence = Confidence::Low;
+                issue.message = format!("{} (quoted code not found on cited line)", issue.message);
+                kept.push(issue);
+            }
+            (None, None) => {
+                stats.dropped += 1;
+                tracing::warn!(
+                    "Dropping ungrounded AI finding {} at {}",
+                    issue.category,
+                    issue
+                        .location
+                        .as_ref()
+                        .map_or_else(|| "?".to_string(), |l| l.to_string())
+                );
+            }
+        }
+    }
+
+    (kept, stats)
+}
+
+/// Fewest non-whitespace characters a quote needs to locate a finding.
+pub const MIN_QUOTE_CHARS: usize = 8;
+
+/// Whether `quote` (already normalized) appears in `line` on token boundaries.
+fn quotes(line: &str, quote: &str) -> bool {
+    if quote.chars().filter(|c| !c.is_whitespace()).count() < MIN_QUOTE_CHARS {
+        return false;
+    }
+    let line = normalize(line);
+    let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+    let first = quote.chars().next();
+    let last = quote.chars().next_back();
+    line.match_indices(quote).any(|(at, _)| {
+        let before = line[..at].chars().next_back();
+        let after = line[at + quote.len()..].chars().next();
+        !(is_word(before) && is_word(first) || is_word(after) && is_word(last))
+    })
+}
+
+fn normalize(code: &str) -> String {
+    code.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff;
+    use crate::models::{Analyzer, Severity};
+
+    const DIFF: &str = "diff --git a/src/a.rs b/src/a.rs
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -10,2 +10,3 @@
+ let x = 1;
++let ssn = load();
++println!(\"{}\", ssn);
+";
+
+    fn cite(line: u32, quote: &str) -> Finding {
+        Finding::new(Analyzer::Ai, "PHI_LOGGING", Severity::High, "ssn logged")
+            .at(Location::line("src/a.rs", line))
+            .with_snippet(quote)
+    }
+
+    #[test]
+    fn keeps_relocates_downgrades_and_drops() {
+        let files = diff::parse(DIFF);
+        let (kept, stats) = ground_findings(
+            &files,
+            vec![
+                cite(12, "println!(\"{}\",  ssn);"),
+                cite(11, "println!(\"{}\", ssn);"),
+                cite(11, "eprintln!(patient)"),
+                cite(10, "let x = 1;"),
+                cite(99, "made up"),
+            ],
+        );
+
+        assert_eq!(
+            stats,
+            GroundingStats {
+                cited: 5,
+                grounded: 1,
+                relocated: 1,
+                downgraded: 1,
+                dropped: 2,
+            }
+        );
+        assert_eq!(kept[1].location.as_ref().map(|l| l.start_line), Some(12));
+        assert_eq!(kept[2].severity, Severity::Medium);
+    }
+
+    #[test]
+    fn short_or_partial_quotes_do_not_ground() {
+        let files = diff::parse(DIFF);
+        let (kept, stats) = ground_findings(
+            &files,
+            vec![
+                cite(11, "ssn"),
+                cite(40, "let"),
+                cite(40, "intln!(\"{}\", ss"),
+            ],
+        );
+        assert_eq!((stats.grounded, stats.relocated, stats.dropped), (0, 0, 2));
+        assert_eq!(kept.len(), 1);
+        assert!(kept[0].message.contains("not found on cited line"));
+        assert!(!quotes("let ssn = load();", "ssn = lo"));
+        assert!(quotes("let ssn = load();", "ssn = load()"));
+    }
+}
@@ -0,0 +1,165 @@
+//! Places findings as review comments on the lines of the PR diff.
+//!
+//! GitHub only accepts comments on lines that appear in the diff, addressed
+//! by path, line and side. Anything else is left for the summary body.
+
+use crate::diff::{FileDiff, LineKind};
+use crate::markdown;
+use crate::models::Finding;
+use serde::Serialize;
+
+/// Most comments sent in one review. Larger reviews are slow to render and
+/// tend to be rejected by the API; the rest go in the summary.
+pub const MAX_REVIEW_COMMENTS: usize = 50;
+
+/// One entry of the `comments` array of `POST /pulls/{n}/reviews`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReviewComment {
+    pub path: String,
+    /// Last line of the range on the new side.
+    pub line: u32,
+    pub side: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_line: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_side: Option<&'static str>,
+    pub body: String,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct InlinePlan {
+    pub comments: Vec<ReviewComment>,
+    /// Indexes into the findings that became comments.
+    pub inlined: Vec<usize>,
+}
+
+/// Chooses which findings become line comments: those whose lines are on the
+/// new side of a single hunk, most severe first, up to `MAX_REVIEW_COMMENTS`.
+pub fn plan(files: &[FileDiff], findings: &[Finding]) -> InlinePlan {
+    let mut candidates: Vec<(usize, ReviewComment)> = findings
+        .iter()
+        .enumerate()
+        .filter_map(|(i, finding)| Some((i, anchor(files, finding)?)))
+        .collect();
+    candidates.sort_by(|(a, _), (b, _)| findings[*b].severity.cmp(&findings[*a].severity));
+    candidates.truncate(MAX_REVIEW_COMMENTS);
+    candidates.sort_by_key(|(i, _)| *i);
+
+    InlinePlan {
+        inlined: candidates.iter().map(|(i, _)| *i).collect(),
+        comments: candidates.into_iter().map(|(_, c)| c).collect(),
+    }
+}
+
+fn anchor(files: &[FileDiff], finding: &Finding) -> Option<ReviewComment> {
+    let location = finding.location.as_ref()?;
+    let file = files.iter().find(|f| f.path == location.file)?;
+    let start = location.start_line;
+    let end = location.end_line.unwrap_or(start).max(start);
+
+    // Both ends must be new-side lines of the same hunk.
+    let hunk = file.hunks.iter().find(|h| {
+        h.lines
+            .iter()
+            .any(|l| l.new_line == Some(start) && l.kind != LineKind::Removed)
+    })?;
+    if !hunk
+        .lines
+        .iter()
+        .any(|l| l.new_line == Some(end) && l.kind != LineKind::Removed)
+    {
+        return None;
+    }
+
+    Some(ReviewComment {
+        path: location.file.clone(),
+        line: end,
+        side: "RIGHT",
+        start_line: (end > start).then_some(start),
+        start_side: (end > start).then_some("RIGHT"),
+        body: comment_body(finding),
+    })
+}
+
+/// A finding with a mechanical fix gets a `suggestion` block the author can
+/// apply from the GitHub UI.
+pub fn comment_body(finding: &Finding) -> String {
+    let mut body = markdown::finding_headline(finding);
+    if let Some(fix) = &finding.fix {
+        body.push_str(&format!(
+            "\n\n**Suggested fix:** {}\n\n{}",
+            fix.description,
+            markdown::fenced(&fix.replacement, "suggestion")
+        ));
+    } else if let Some(remediation) = &finding.remediation {
+        body.push_str(&format!("\n\n**Fix:** {}", remediation));
+    }
+    body
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff;
+    use crate::models::{Analyzer, Fix, Location, Severity};
+
+    const DIFF: &str = "diff --git a/src/a.rs b/src/a.rs
+--- a/src/a.rs
++++ b/src/a.rs
+@@ -10,2 +10,4 @@
+ let x = 1;
++let ssn = load();
++println!(\"{}\", ssn);
+ let y = 2;
+";
+
+    fn at(line: u32) -> Finding {
+        Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "PHI logged")
+            .at(Location::line("src/a.rs", line))
+    }
+
+    #[test]
+    fn anchors_diff_lines_and_leaves_the_rest() {
+        let files = diff::parse(DIFF);
+        let mut ranged = at(11);
+        ranged.location = Some(Location {
+            end_line: Some(12),
+            ..Location::line("src/a.rs", 11)
+        });
+        let findings = vec![
+            at(12),
+            at(40),
+            ranged,
+            at(3).at(Location::line("src/b.rs", 3)),
+        ];
+
+        let plan = plan(&files, &findings);
+        assert_eq!(plan.inlined, vec![0, 2]);
+        assert_eq!(plan.comments[0].line, 12);
+        assert_eq!(plan.comments[0].side, "RIGHT");
+        assert_eq!(plan.comments[1].start_line, Some(11));
+    }
+
+    #[test]
+    fn caps_comments_keeping_the_most_severe() {
+        let files = diff::parse(DIFF);
+        let mut findings: Vec<Finding> = (0..MAX_REVIEW_COMMENTS).map(|_| at(12)).collect();
+        findings.iter_mut().for_each(|f| f.severity = Severity::Low);
+        findings.push(at(11));
+
+        let plan = plan(&files, &findings);
+        assert_eq!(plan.comments.len(), MAX_REVIEW_COMMENTS);
+        assert!(plan.inlined.contains(&MAX_REVIEW_COMMENTS));
+    }
+
+    #[test]
+    fn mechanical_fixes_become_suggestions() {
+        let mut finding = at(12);
+        finding.fix = Some(Fix {
+            description: "Log the record id instead".into(),
+            replacement: "tracing::info!(record_id = %id);".into(),
+        });
+        let body = comment_body(&finding);
+        assert!(body.contains("```suggestion\ntracing::info!(record_id = %id);\n```"));
+    }
+}
@@ -1,10 +1,33 @@
 // ─────────────────────────────────────────────
 // Core security logic
 // ─────────────────────────────────────────────
+pub mod ai_fix;
+pub mod ast_scan;
 pub mod audit;
+pub mod autofix;
+pub mod cache;
+#[cfg(feature = "dev-tools")]
+pub mod cassette;
+pub mod chatops;
+pub mod checks;
+pub mod chunk;
+pub mod ci;
+pub mod controls;
+pub mod diff;
+pub mod export;
+pub mod grounding;
+pub mod injection;
+pub mod inline;
+pub mod lifecycle;
+pub mod local;
+pub mod markdown;
 pub mod models;
+pub mod redact;
 pub mod remediation;
+pub mod rules;
+pub mod sarif;
 pub mod scanner;
+pub mod scoring;
 
 // Cryptography & integrity
 pub mod fips;
@@ -21,6 +44,9 @@ pub mod db;
 // External integrations
 pub mod blockchain;
 pub mod github;
+pub mod llm;
+#[cfg(feature = "dev-tools")]
+pub mod llm_stub;
 
 // Domain logic
 pub mod patient_processor;
@@ -0,0 +1,532 @@
+//! Tracks findings across scans so a report can say whether each one is new,
+//! still present or fixed, and for how long PHI issues stayed open.
+
+use crate::hash::generate_hash;
+use crate::models::Finding;
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+/// Identity of a finding that survives line shifts: rule ID, path and the
+/// whitespace-normalized snippet. `occurrence` separates identical snippets
+/// in the same file.
+pub fn fingerprint(finding: &Finding, occurrence: usize) -> String {
+    let path = finding
+        .location
+        .as_ref()
+        .map(|l| l.file.as_str())
+        .unwrap_or_default();
+    let context = finding
+        .snippet
+        .as_deref()
+        .map(|s| s.split_whitespace().collect::<Vec<_>>().join(" "))
+        .unwrap_or_default();
+    generate_hash(&format!(
+        "{}\0{}\0{}\0{}",
+        finding.rule_id, path, context, occurrence
+    ))
+}
+
+/// Leading characters of a fingerprint shown in comments; commands accept
+/// any unambiguous prefix.
+pub fn short_fingerprint(fingerprint: &str) -> &str {
+    fingerprint.get(..12).unwrap_or(fingerprint)
+}
+
+/// Sets `fingerprint` on every finding. Repeats of the same rule, path and
+/// snippet are numbered in line order.
+pub fn assign_fingerprints(findings: &mut [Finding]) {
+    let mut order: Vec<usize> = (0..findings.len()).collect();
+    order.sort_by_key(|&i| findings[i].location.as_ref().map(|l| l.start_line));
+
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for i in order {
+        let base = fingerprint(&findings[i], 0);
+        let occurrence = seen.entry(base.clone()).or_default();
+        findings[i].fingerprint = Some(if *occurrence == 0 {
+            base
+        } else {
+            fingerprint(&findings[i], *occurrence)
+        });
+        *occurrence += 1;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FindingState {
+    Open,
+    Fixed,
+    /// Accepted by a reviewer; scans no longer change its state.
+    Suppressed,
+    /// Fixed once, then reported again.
+    Reopened,
+}
+
+impl FindingState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Open => "open",
+            Self::Fixed => "fixed",
+            Self::Suppressed => "suppressed",
+            Self::Reopened => "reopened",
+        }
+    }
+
+    /// State after a scan that did (`seen`) or did not report the finding.
+    pub fn after_scan(self, seen: bool) -> Self {
+        match (self, seen) {
+            (Self::Suppressed, _) => Self::Suppressed,
+            (Self::Fixed, true) => Self::Reopened,
+            (_, false) => Self::Fixed,
+            (state, true) => state,
+        }
+    }
+}
+
+impl fmt::Display for FindingState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for FindingState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "open" => Ok(Self::Open),
+            "fixed" => Ok(Self::Fixed),
+            "suppressed" => Ok(Self::Suppressed),
+            "reopened" => Ok(Self::Reopened),
+            other => anyhow::bail!("Unknown finding state: {}", other),
+        }
+    }
+}
+
+/// Why a finding is suppressed, who accepted it and until when.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Waiver {
+    pub reason: String,
+    /// `None` never lapses.
+    pub expires: Option<DateTime<Utc>>,
+    pub granted_by: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub fingerprint: String,
+    /// `None` when the finding has not been seen before.
+    pub from: Option<FindingState>,
+    pub to: FindingState,
+}
+
+/// Works out state changes for one scan. `tracked` holds the known states of
+/// findings in scope; `current` the fingerprints this scan reported. Findings
+/// that keep their state produce no transition.
+pub fn plan(tracked: &HashMap<String, FindingState>, current: &[String]) -> Vec<Transition> {
+    let current_set: HashSet<&str> = current.iter().map(String::as_str).collect();
+    let mut transitions = vec![];
+
+    for fp in current {
+        let from = tracked.get(fp).copied();
+        let to = from.map_or(FindingState::Open, |s| s.after_scan(true));
+        if from != Some(to) {
+            transitions.push(Transition {
+                fingerprint: fp.clone(),
+                from,
+                to,
+            });
+        }
+    }
+
+    let mut gone: Vec<(&String, &FindingState)> = tracked
+        .iter()
+        .filter(|(fp, _)| !current_set.contains(fp.as_str()))
+        .collect();
+    gone.sort_by_key(|(fp, _)| *fp);
+    for (fp, &from) in gone {
+        let to = from.after_scan(false);
+        if from != to {
+            transitions.push(Transition {
+                fingerprint: fp.clone(),
+                from: Some(from),
+                to,
+            });
+        }
+    }
+
+    transitions
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncSummary {
+    pub opened: usize,
+    pub reopened: usize,
+    pub fixed: usize,
+    /// Reported again without a state change.
+    pub persisting: usize,
+}
+
+/// How long a PHI finding has been, or was, open.
+#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+pub struct Exposure {
+    pub fingerprint: String,
+    pub rule_id: String,
+    pub severity: String,
+    pub state: String,
+    pub file: Option<String>,
+    pub first_seen: DateTime<Utc>,
+    pub fixed_at: Option<DateTime<Utc>>,
+    pub open_seconds: i64,
+}
+
+// ─────────────────────────────────────────────
+// Postgres
+// ─────────────────────────────────────────────
+pub struct PgFindingStore {
+    db: PgPool,
+}
+
+impl PgFindingStore {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Records one scan of `pr_number`. Findings last seen on this PR and
+    /// missing from the scan are marked fixed; findings tracked from other PRs
+    /// are left alone.
+    pub async fn sync(
+        &self,
+        tenant_id: Uuid,
+        repo: &str,
+        pr_number: u64,
+        findings: &[Finding],
+    ) -> anyhow::Result<SyncSummary> {
+        let current: Vec<String> = findings
+            .iter()
+            .filter_map(|f| f.fingerprint.clone())
+            .collect();
+        let mut tx = self.db.begin().await?;
+
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            r#"
+            SELECT fingerprint, state FROM findings
+            WHERE tenant_id = $1 AND repo_name = $2 AND (last_pr = $3 OR fingerprint = ANY($4))
+            FOR UPDATE
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(repo)
+        .bind(pr_number as i32)
+        .bind(&current)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to load tracked findings")?;
+        let tracked = rows
+            .into_iter()
+            .map(|(fp, state)| Ok((fp, state.parse()?)))
+            .collect::<anyhow::Result<HashMap<String, FindingState>>>()?;
+
+        let transitions: HashMap<String, Transition> = plan(&tracked, &current)
+            .into_iter()
+            .map(|t| (t.fingerprint.clone(), t))
+            .collect();
+        let mut summary = SyncSummary::default();
+
+        for finding in findings {
+            let Some(fp) = &finding.fingerprint else {
+                continue;
+            };
+            let state = transitions
+                .get(fp)
+                .map(|t| t.to)
+                .or_else(|| tracked.get(fp).copied())
+                .unwrap_or(FindingState::Open);
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                INSERT INTO findings
+                (tenant_id, repo_name, fingerprint, rule_id, category, severity, file, line, state, last_pr)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+                ON CONFLICT (tenant_id, repo_name, fingerprint) DO UPDATE SET
+                    severity = EXCLUDED.severity,
+                    file = EXCLUDED.file,
+                    line = EXCLUDED.line,
+                    state = EXCLUDED.state,
+                    last_pr = EXCLUDED.last_pr,
+                    last_seen = NOW(),
+                    fixed_at = NULL
+                RETURNING id
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(repo)
+            .bind(fp)
+            .bind(&finding.rule_id)
+            .bind(&finding.category)
+            .bind(finding.severity.as_str())
+            .bind(finding.location.as_ref().map(|l| l.file.clone()))
+            .bind(finding.location.as_ref().map(|l| l.start_line as i32))
+            .bind(state.as_str())
+            .bind(pr_number as i32)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to upsert finding")?;
+
+            match transitions.get(fp) {
+                Some(t) => {
+                    record_event(&mut tx, id, t, pr_number).await?;
+                    match t.to {
+                        FindingState::Reopened => summary.reopened += 1,
+                        _ => summary.opened += 1,
+                    }
+                }
+                None => summary.persisting += 1,
+            }
+        }
+
+        for t in transitions.values().filter(|t| t.to == FindingState::Fixed) {
+            let id: Uuid = sqlx::query_scalar(
+                r#"
+                UPDATE findings SET state = 'fixed', fixed_at = NOW()
+                WHERE tenant_id = $1 AND repo_name = $2 AND fingerprint = $3
+                RETURNING id
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(repo)
+            .bind(&t.fingerprint)
+            .fetch_one(&mut *tx)
+            .await
+            .context("Failed to mark finding fixed")?;
+            record_event(&mut tx, id, t, pr_number).await?;
+            summary.fixed += 1;
+        }
+
+        tx.commit().await?;
+        Ok(summary)
+    }
+
+    /// Suppresses a finding under `waiver`. Returns false if it is not tracked.
+    pub async fn suppress(
+        &self,
+        tenant_id: Uuid,
+        repo: &str,
+        fingerprint: &str,
+        waiver: &Waiver,
+    ) -> anyhow::Result<bool> {
+        self.set_state(
+            tenant_id,
+            repo,
+            fingerprint,
+            FindingState::Suppressed,
+            Some(waiver),
+        )
+        .await
+    }
+
+    /// Lifts a suppression; the next scan decides whether it is fixed.
+    pub async fn unsuppress(
+        &self,
+        tenant_id: Uuid,
+        repo: &str,
+        fingerprint: &str,
+    ) -> anyhow::Result<bool> {
+        self.set_state(tenant_id, repo, fingerprint, FindingState::Open, None)
+            .await
+    }
+
+    /// Fingerprints under a waiver that has not expired. Lapsed waivers are
+    /// lifted first, so those findings count again.
+    pub async fn active_waivers(
+        &self,
+        tenant_id: Uuid,
+        repo: &str,
+    ) -> anyhow::Result<HashSet<String>> {
+        let mut tx = self.db.begin().await?;
+        let lapsed: Vec<(Uuid, String, i32)> = sqlx::query_as(
+            r#"
+            UPDATE findings
+            SET state = 'open', suppressed_reason = NULL, suppressed_until = NULL, suppressed_by = NULL
+            WHERE tenant_id = $1 AND repo_name = $2 AND state = 'suppressed'
+              AND suppressed_until <= NOW()
+            RETURNING id, fingerprint, last_pr
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(repo)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to lift lapsed waivers")?;
+        for (id, fingerprint, last_pr) in lapsed {
+            let transition = Transition {
+                fingerprint,
+                from: Some(FindingState::Suppressed),
+                to: FindingState::Open,
+            };
+            record_event(&mut tx, id, &transition, last_pr as u64).await?;
+        }
+
+        let active: Vec<String> = sqlx::query_scalar(
+            "SELECT fingerprint FROM findings WHERE tenant_id = $1 AND repo_name = $2 AND state = 'suppressed'",
+        )
+        .bind(tenant_id)
+        .bind(repo)
+        .fetch_all(&mut *tx)
+        .await
+        .context("Failed to load waivers")?;
+        tx.commit().await?;
+        Ok(active.into_iter().collect())
+    }
+
+    async fn set_state(
+        &self,
+        tenant_id: Uuid,
+        repo: &str,
+        fingerprint: &str,
+        to: FindingState,
+        waiver: Option<&Waiver>,
+    ) -> anyhow::Result<bool> {
+        let mut tx = self.db.begin().await?;
+        let row: Option<(Uuid, String, i32)> = sqlx::query_as(
+            r#"
+            SELECT id, state, last_pr FROM findings
+            WHERE tenant_id = $1 AND repo_name = $2 AND fingerprint = $3
+            FOR UPDATE
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(repo)
+        .bind(fingerprint)
+        .fetch_optional(&mut *tx)
+        .await?;
+        let Some((id, from, last_pr)) = row else {
+            return Ok(false);
+        };
+
+        sqlx::query(
+            r#"
+            UPDATE findings
+            SET state = $2, suppressed_reason = $3, suppressed_until = $4, suppressed_by = $5
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .bind(to.as_str())
+        .bind(waiver.map(|w| w.reason.as_str()))
+        .bind(waiver.and_then(|w| w.expires))
+        .bind(waiver.map(|w| w.granted_by.as_str()))
+        .execute(&mut *tx)
+        .await?;
+        let transition = Transition {
+            fingerprint: fingerprint.to_string(),
+            from: Some(from.parse()?),
+            to,
+        };
+        record_event(&mut tx, id, &transition, last_pr as u64).await?;
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// PHI findings for a repo with how long each stayed open, longest first.
+    /// Open findings count up to now.
+    pub async fn phi_exposure(&self, tenant_id: Uuid, repo: &str) -> anyhow::Result<Vec<Exposure>> {
+        sqlx::query_as(
+            r#"
+            SELECT fingerprint, rule_id, severity, state, file, first_seen, fixed_at,
+                   EXTRACT(EPOCH FROM open_for)::BIGINT AS open_seconds
+            FROM phi_finding_exposure
+            WHERE tenant_id = $1 AND repo_name = $2
+            ORDER BY open_for DESC
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(repo)
+        .fetch_all(&self.db)
+        .await
+        .context("Failed to query PHI exposure")
+    }
+}
+
+async fn record_event(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    finding_id: Uuid,
+    transition: &Transition,
+    pr_number: u64,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO finding_events (finding_id, from_state, to_state, pr_number) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(finding_id)
+    .bind(transition.from.map(|s| s.as_str()))
+    .bind(transition.to.as_str())
+    .bind(pr_number as i32)
+    .execute(&mut **tx)
+    .await
+    .context("Failed to record finding event")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location, Severity};
+
+    fn phi(line: u32, snippet: &str) -> Finding {
+        Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "x")
+            .at(Location::line("src/a.rs", line))
+            .with_snippet(snippet)
+    }
+
+    #[test]
+    fn fingerprints_survive_line_shifts_and_separate_repeats() {
+        let mut before = vec![phi(4, "println!(\"{}\", ssn);")];
+        let mut after = vec![
+            phi(30, "println!(\"{}\", ssn);"),
+            phi(9, "println!(\"{}\",   ssn);"),
+        ];
+        assign_fingerprints(&mut before);
+        assign_fingerprints(&mut after);
+
+        assert_eq!(before[0].fingerprint, after[1].fingerprint);
+        assert_ne!(after[0].fingerprint, after[1].fingerprint);
+    }
+
+    #[test]
+    fn plans_open_fixed_reopened_and_suppressed() {
+        let tracked: HashMap<String, FindingState> = [
+            ("kept", FindingState::Open),
+            ("gone", FindingState::Reopened),
+            ("back", FindingState::Fixed),
+            ("muted", FindingState::Suppressed),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v))
+        .collect();
+        let current = ["kept", "back", "new"].map(String::from);
+
+        let transitions = plan(&tracked, &current);
+        let to = |fp: &str| {
+            transitions
+                .iter()
+                .find(|t| t.fingerprint == fp)
+                .map(|t| (t.from, t.to))
+        };
+
+        assert_eq!(transitions.len(), 3);
+        assert_eq!(
+            to("back"),
+            Some((Some(FindingState::Fixed), FindingState::Reopened))
+        );
+        assert_eq!(to("new"), Some((None, FindingState::Open)));
+        assert_eq!(
+            to("gone"),
+            Some((Some(FindingState::Reopened), FindingState::Fixed))
+        );
+    }
+}
@@ -0,0 +1,560 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use secrecy::{ExposeSecret, SecretString};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use tokio::time::{sleep, Duration};
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+const MAX_ATTEMPTS: u64 = 3;
+
+// ─────────────────────────────────────────────
+// Reviewer trait
+// ─────────────────────────────────────────────
+#[derive(Debug, Clone, Default)]
+pub struct LlmRequest {
+    pub system: String,
+    pub prompt: String,
+    pub max_output_tokens: u32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LlmResponse {
+    Text(String),
+    /// The provider refused to answer, e.g. a safety or content filter tripped.
+    Blocked(String),
+}
+
+#[async_trait]
+pub trait LlmReviewer: Send + Sync {
+    /// Provider and model, e.g. `openai/gpt-4o`. Recorded alongside results.
+    fn model(&self) -> String;
+
+    /// Where requests go, for reviewers that call a service. Part of the
+    /// review cache scope, so the same model name behind another endpoint
+    /// never shares verdicts.
+    fn endpoint(&self) -> Option<String> {
+        None
+    }
+
+    /// Context window in tokens, used to size diff chunks.
+    fn context_tokens(&self) -> usize {
+        8_192
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> anyhow::Result<LlmResponse>;
+}
+
+// ─────────────────────────────────────────────
+// Gemini
+// ─────────────────────────────────────────────
+pub struct GeminiReviewer {
+    pub base_url: String,
+    pub model: String,
+    pub api_key: SecretString,
+}
+
+#[async_trait]
+impl LlmReviewer for GeminiReviewer {
+    fn model(&self) -> String {
+        format!("gemini/{}", self.model)
+    }
+
+    fn endpoint(&self) -> Option<String> {
+        Some(self.base_url.clone())
+    }
+
+    fn context_tokens(&self) -> usize {
+        1_000_000
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> anyhow::Result<LlmResponse> {
+        let url = format!(
+            "{}/v1beta/models/{}:generateContent",
+            self.base_url.trim_end_matches('/'),
+            self.model
+        );
+        let body = json!({
+            "systemInstruction": { "parts": [{ "text": request.system }] },
+            "contents": [{ "role": "user", "parts": [{ "text": request.prompt }] }],
+            // Don't let the safety filters block mock PII data like SSNs
+            "safetySettings": [
+                { "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_NONE" },
+                { "category": "HARM_CATEGORY_HATE_SPEECH", "threshold": "BLOCK_NONE" },
+                { "category": "HARM_CATEGORY_SEXUALLY_EXPLICIT", "threshold": "BLOCK_NONE" },
+                { "category": "HARM_CATEGORY_DANGEROUS_CONTENT", "threshold": "BLOCK_NONE" }
+            ],
+            "generationConfig": {
+                "temperature": 0.1,
+                "maxOutputTokens": request.max_output_tokens,
+                "responseMimeType": "application/json"
+            }
+        });
+
+        let response = post_json("Gemini", || {
+            client()
+                .post(&url)
+                .header("x-goog-api-key", self.api_key.expose_secret())
+                .json(&body)
+        })
+        .await?;
+
+        let candidate = &response["candidates"][0];
+        if candidate["finishReason"].as_str() == Some("SAFETY") {
+            return Ok(LlmResponse::Blocked(
+                "Gemini safety filters blocked this scan because it detected sensitive content."
+                    .to_string(),
+            ));
+        }
+
+        candidate["content"]["parts"][0]["text"]
+            .as_str()
+            .map(|text| LlmResponse::Text(text.to_string()))
+            .context("No content in Gemini response")
+    }
+}
+
+// ─────────────────────────────────────────────
+// OpenAI Chat Completions (and compatible local servers)
+// ─────────────────────────────────────────────
+pub struct OpenAiReviewer {
+    pub base_url: String,
+    pub model: String,
+    /// `None` for local servers such as Ollama or llama.cpp that need no auth.
+    pub api_key: Option<SecretString>,
+    /// Label used in `model()`, `openai` or `local`.
+    pub label: &'static str,
+}
+
+#[async_trait]
+impl LlmReviewer for OpenAiReviewer {
+    fn model(&self) -> String {
+        format!("{}/{}", self.label, self.model)
+    }
+
+    fn endpoint(&self) -> Option<String> {
+        Some(self.base_url.clone())
+    }
+
+    fn context_tokens(&self) -> usize {
+        match self.label {
+            "openai" => 128_000,
+            _ => 8_192,
+        }
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> anyhow::Result<LlmResponse> {
+        let url = format!(
+            "{}/v1/chat/completions",
+            self.base_url.trim_end_matches('/')
+        );
+        let mut body = chat_completion_body(request);
+        body["model"] = json!(self.model);
+
+        let response = post_json("OpenAI", || {
+            let builder = client().post(&url).json(&body);
+            match &self.api_key {
+                Some(key) => builder.bearer_auth(key.expose_secret()),
+                None => builder,
+            }
+        })
+        .await?;
+
+        parse_chat_completion("OpenAI", &response)
+    }
+}
+
+// ─────────────────────────────────────────────
+// Azure OpenAI
+// ─────────────────────────────────────────────
+pub struct AzureOpenAiReviewer {
+    /// Resource endpoint, e.g. `https://my-resource.openai.azure.com`.
+    pub endpoint: String,
+    pub deployment: String,
+    pub api_version: String,
+    pub api_key: SecretString,
+}
+
+#[async_trait]
+impl LlmReviewer for AzureOpenAiReviewer {
+    fn model(&self) -> String {
+        format!("azure/{}", self.deployment)
+    }
+
+    fn endpoint(&self) -> Option<String> {
+        Some(self.endpoint.clone())
+    }
+
+    fn context_tokens(&self) -> usize {
+        128_000
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> anyhow::Result<LlmResponse> {
+        let url = format!(
+            "{}/openai/deployments/{}/chat/completions?api-version={}",
+            self.endpoint.trim_end_matches('/'),
+            self.deployment,
+            self.api_version
+        );
+        let body = chat_completion_body(request);
+
+        let response = post_json("Azure OpenAI", || {
+            client()
+                .post(&url)
+                .header("api-key", self.api_key.expose_secret())
+                .json(&body)
+        })
+        .await?;
+
+        parse_chat_completion("Azure OpenAI", &response)
+    }
+}
+
+fn chat_completion_body(request: &LlmRequest) -> Value {
+    json!({
+        "messages": [
+            { "role": "system", "content": request.system },
+            { "role": "user", "content": request.prompt }
+        ],
+        "temperature": 0.1,
+        "max_tokens": request.max_output_tokens,
+        "response_format": { "type": "json_object" }
+    })
+}
+
+fn parse_chat_completion(provider: &str, response: &Value) -> anyhow::Result<LlmResponse> {
+    let choice = &response["choices"][0];
+    if choice["finish_reason"].as_str() == Some("content_filter") {
+        return Ok(LlmResponse::Blocked(format!(
+            "{} content filter blocked this scan because it detected sensitive content.",
+            provider
+        )));
+    }
+
+    choice["message"]["content"]
+        .as_str()
+        .map(|text| LlmResponse::Text(text.to_string()))
+        .with_context(|| format!("No content in {} response", provider))
+}
+
+fn client() -> &'static reqwest::Client {
+    CLIENT.get_or_init(reqwest::Client::new)
+}
+
+/// Sends a JSON request, retrying on 429 and 5xx with linear backoff.
+async fn post_json(
+    provider: &str,
+    build: impl Fn() -> reqwest::RequestBuilder,
+) -> anyhow::Result<Value> {
+    let mut attempts = 0;
+
+    loop {
+        attempts += 1;
+
+        let response = build()
+            .send()
+            .await
+            .with_context(|| format!("Failed to send request to {}", provider))?;
+
+        let status = response.status();
+
+        if (status.as_u16() == 429 || status.is_server_error()) && attempts < MAX_ATTEMPTS {
+            let wait_secs = attempts * 2;
+            tracing::warn!(
+                "{} API busy ({}). Retry attempt {}/{} in {}s...",
+                provider,
+                status,
+                attempts,
+                MAX_ATTEMPTS,
+                wait_secs
+            );
+            sleep(Duration::from_secs(wait_secs)).await;
+            continue;
+        }
+
+        let body = response
+            .json::<Value>()
+            .await
+            .with_context(|| format!("Failed to parse {} response", provider))?;
+
+        if !status.is_success() {
+            let error = body["error"]["message"]
+                .as_str()
+                .unwrap_or("Unknown API error");
+            anyhow::bail!("{} API error: {}", provider, error);
+        }
+
+        return Ok(body);
+    }
+}
+
+// ─────────────────────────────────────────────
+// Provider selection
+// ─────────────────────────────────────────────
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LlmProvider {
+    Gemini,
+    OpenAi,
+    AzureOpenAi,
+    /// OpenAI-compatible local endpoint (Ollama, llama.cpp, vLLM).
+    Local,
+}
+
+impl FromStr for LlmProvider {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gemini" => Ok(Self::Gemini),
+            "openai" => Ok(Self::OpenAi),
+            "azure" | "azure_openai" | "azure-openai" => Ok(Self::AzureOpenAi),
+            "local" | "ollama" | "llamacpp" | "llama.cpp" => Ok(Self::Local),
+            other => anyhow::bail!("Unknown LLM provider: {}", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct LlmConfig {
+    pub provider: LlmProvider,
+    pub model: Option<String>,
+    pub base_url: Option<String>,
+    pub api_key: Option<SecretString>,
+    /// Azure deployment name.
+    pub deployment: Option<String>,
+    /// Azure REST API version.
+    pub api_version: Option<String>,
+}
+
+impl LlmConfig {
+    /// Reads `LLM_PROVIDER` (default `gemini`) and the matching provider variables.
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::from_vars(&env_var)
+    }
+
+    fn from_vars(var: &impl Fn(&str) -> Option<String>) -> anyhow::Result<Self> {
+        let provider = var("LLM_PROVIDER")
+            .map(|p| p.parse())
+            .unwrap_or(Ok(LlmProvider::Gemini))?;
+        Ok(Self::for_provider(provider, true, var))
+    }
+
+    /// `provider`'s settings from the environment. `LLM_MODEL` and
+    /// `LLM_BASE_URL` describe the server's own provider, so they are only
+    /// read when `server` is set; the Azure variables are Azure's alone.
+    fn for_provider(
+        provider: LlmProvider,
+        server: bool,
+        var: &impl Fn(&str) -> Option<String>,
+    ) -> Self {
+        let azure = |name: &str| var(name).filter(|_| provider == LlmProvider::AzureOpenAi);
+        Self {
+            provider,
+            model: var("LLM_MODEL").filter(|_| server),
+            base_url: match provider {
+                LlmProvider::AzureOpenAi => var("AZURE_OPENAI_ENDPOINT"),
+                _ => var("LLM_BASE_URL").filter(|_| server),
+            },
+            api_key: var(default_key_env(provider)).map(SecretString::new),
+            deployment: azure("AZURE_OPENAI_DEPLOYMENT"),
+            api_version: azure("AZURE_OPENAI_API_VERSION"),
+        }
+    }
+
+    /// Environment defaults overlaid with the tenant's `llm_*` columns.
+    /// Tenants store the *name* of the env var holding their key, never the
+    /// key, and only names under `TENANT_KEY_PREFIX` are read. A tenant that
+    /// points `llm_base_url` elsewhere never gets the server's default key,
+    /// and one that picks another provider starts from that provider's own
+    /// variables rather than the server provider's endpoint.
+    pub async fn for_tenant(db: &PgPool, tenant_id: uuid::Uuid) -> anyhow::Result<Self> {
+        let config = Self::from_env()?;
+
+        let row: Option<TenantLlmRow> = sqlx::query_as(
+            "SELECT llm_provider, llm_model, llm_base_url, llm_api_key_env, llm_deployment, llm_api_version
+             FROM tenants WHERE id = $1",
+        )
+        .bind(tenant_id)
+        .fetch_optional(db)
+        .await
+        .context("Failed to load tenant LLM settings")?;
+
+        match row {
+            Some(row) => config.with_tenant(row, &env_var),
+            None => Ok(config),
+        }
+    }
+
+    fn with_tenant(
+        mut self,
+        row: TenantLlmRow,
+        var: &impl Fn(&str) -> Option<String>,
+    ) -> anyhow::Result<Self> {
+        if let Some(provider) = row.llm_provider {
+            let provider = provider.parse()?;
+            if provider != self.provider {
+                self = Self::for_provider(provider, false, var);
+            }
+        }
+        if row.llm_base_url.is_some() {
+            self.api_key = None;
+        }
+        if let Some(key_env) = row.llm_api_key_env {
+            self.api_key = var(tenant_key_env(&key_env)?).map(SecretString::new);
+        }
+        self.model = row.llm_model.or(self.model);
+        self.base_url = row.llm_base_url.or(self.base_url);
+        self.deployment = row.llm_deployment.or(self.deployment);
+        self.api_version = row.llm_api_version.or(self.api_version);
+
+        Ok(self)
+    }
+
+    /// The reviewer for this config. A missing key is an error.
+    pub fn build(&self) -> anyhow::Result<Box<dyn LlmReviewer>> {
+        let base_url = |default: &str| self.base_url.clone().unwrap_or_else(|| default.into());
+        let model = |default: &str| self.model.clone().unwrap_or_else(|| default.into());
+        let require_key = || {
+            self.api_key
+                .clone()
+                .with_context(|| format!("{} not set", default_key_env(self.provider)))
+        };
+
+        Ok(match self.provider {
+            LlmProvider::Gemini => Box::new(GeminiReviewer {
+                base_url: base_url("https://generativelanguage.googleapis.com"),
+                model: model("gemini-1.5-flash"),
+                api_key: require_key()?,
+            }),
+            LlmProvider::OpenAi => Box::new(OpenAiReviewer {
+                base_url: base_url("https://api.openai.com"),
+                model: model("gpt-4o"),
+                api_key: Some(require_key()?),
+                label: "openai",
+            }),
+            LlmProvider::AzureOpenAi => Box::new(AzureOpenAiReviewer {
+                endpoint: self
+                    .base_url
+                    .clone()
+                    .context("AZURE_OPENAI_ENDPOINT not set")?,
+                deployment: self
+                    .deployment
+                    .clone()
+                    .or_else(|| self.model.clone())
+                    .context("AZURE_OPENAI_DEPLOYMENT not set")?,
+                api_version: self
+                    .api_version
+                    .clone()
+                    .unwrap_or_else(|| "2024-06-01".into()),
+                api_key: require_key()?,
+            }),
+            LlmProvider::Local => Box::new(OpenAiReviewer {
+                base_url: base_url("http://localhost:11434"),
+                model: model("llama3.1"),
+                api_key: self.api_key.clone(),
+                label: "local",
+            }),
+        })
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+#[derive(sqlx::FromRow, Default)]
+struct TenantLlmRow {
+    llm_provider: Option<String>,
+    llm_model: Option<String>,
+    llm_base_url: Option<String>,
+    llm_api_key_env: Option<String>,
+    llm_deployment: Option<String>,
+    llm_api_version: Option<String>,
+}
+
+/// Prefix every tenant-named key variable must carry, so a tenant can't
+/// name `DATABASE_URL` or the GitHub App key and have it sent to its base URL.
+pub const TENANT_KEY_PREFIX: &str = "GHOSTHEALTH_LLM_KEY_";
+
+/// Checks a tenant's `llm_api_key_env`: `TENANT_KEY_PREFIX` followed by at
+/// least one of `A-Z`, `0-9` or `_`.
+pub fn tenant_key_env(name: &str) -> anyhow::Result<&str> {
+    let valid = name.strip_prefix(TENANT_KEY_PREFIX).is_some_and(|suffix| {
+        !suffix.is_empty()
+            && suffix
+                .bytes()
+                .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_')
+    });
+    if !valid {
+        anyhow::bail!(
+            "llm_api_key_env {:?} is not allowed; tenant keys must be in {}<NAME> variables",
+            name,
+            TENANT_KEY_PREFIX
+        );
+    }
+    Ok(name)
+}
+
+fn default_key_env(provider: LlmProvider) -> &'static str {
+    match provider {
+        LlmProvider::Gemini => "GEMINI_API_KEY",
+        LlmProvider::OpenAi => "OPENAI_API_KEY",
+        LlmProvider::AzureOpenAi => "AZURE_OPENAI_API_KEY",
+        LlmProvider::Local => "LOCAL_LLM_API_KEY",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn tenant_switching_provider_reads_that_providers_variables() {
+        let env: HashMap<&str, &str> = HashMap::from([
+            ("LLM_PROVIDER", "openai"),
+            ("LLM_MODEL", "gpt-4o"),
+            ("LLM_BASE_URL", "https://openai-proxy.internal"),
+            ("OPENAI_API_KEY", "sk-server"),
+            ("AZURE_OPENAI_ENDPOINT", "https://acme.openai.azure.com"),
+            ("AZURE_OPENAI_DEPLOYMENT", "gpt4o-prod"),
+            ("AZURE_OPENAI_API_KEY", "az-key"),
+            ("GEMINI_API_KEY", "gm-key"),
+        ]);
+        let var = |name: &str| env.get(name).map(|v| v.to_string());
+        let server = LlmConfig::from_vars(&var);
+        assert!(server
+            .as_ref()
+            .is_ok_and(|c| c.base_url.as_deref() == Some("https://openai-proxy.internal")));
+
+        let tenant = |provider: &str| {
+            LlmConfig::from_vars(&var).and_then(|c| {
+                c.with_tenant(
+                    TenantLlmRow {
+                        llm_provider: Some(provider.into()),
+                        ..Default::default()
+                    },
+                    &var,
+                )
+            })
+        };
+        let azure = tenant("azure");
+        assert!(azure
+            .as_ref()
+            .is_ok_and(|c| c.provider == LlmProvider::AzureOpenAi
+                && c.base_url.as_deref() == Some("https://acme.openai.azure.com")
+                && c.deployment.as_deref() == Some("gpt4o-prod")
+                && c.model.is_none()
+                && c.api_key.as_ref().map(|k| k.expose_secret().as_str()) == Some("az-key")));
+        assert!(azure.and_then(|c| c.build()).is_ok());
+
+        // Gemini's key never goes to the OpenAI proxy.
+        assert!(tenant("gemini").is_ok_and(|c| c.base_url.is_none() && c.model.is_none()));
+        // Same provider keeps the server's settings.
+        assert!(tenant("openai")
+            .is_ok_and(|c| c.base_url.as_deref() == Some("https://openai-proxy.internal")));
+    }
+}
@@ -0,0 +1,151 @@
+//! A local OpenAI-compatible endpoint that answers with scripted verdicts.
+//! Point the `local` provider at it to exercise the HTTP retry, safety-block
+//! and parse-error paths without a real model.
+
+use anyhow::Context;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// One scripted answer. Replies are served in order; the last one repeats.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StubReply {
+    Verdict {
+        status: String,
+        risk_score: u8,
+        /// Issues in the AI answer format (`category`, `severity`, `message`, `file`, `line`, `quote`).
+        #[serde(default)]
+        issues: Vec<Value>,
+    },
+    /// Raw message content, e.g. prose to trigger the repair path.
+    Text { content: String },
+    /// `finish_reason: content_filter`.
+    Blocked,
+    /// An HTTP error such as 429 or 503.
+    Error { status: u16, message: String },
+}
+
+impl StubReply {
+    pub fn clean() -> Self {
+        Self::Verdict {
+            status: "CLEAN".into(),
+            risk_score: 0,
+            issues: vec![],
+        }
+    }
+
+    /// Reads a JSON array of replies.
+    pub fn load_script(path: &Path) -> anyhow::Result<Vec<Self>> {
+        let raw = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read stub script {}", path.display()))?;
+        serde_json::from_str(&raw)
+            .with_context(|| format!("Invalid stub script {}", path.display()))
+    }
+}
+
+struct StubState {
+    replies: Vec<StubReply>,
+    served: AtomicUsize,
+}
+
+pub struct StubServer {
+    addr: SocketAddr,
+    state: Arc<StubState>,
+    handle: Option<tokio::task::JoinHandle<()>>,
+}
+
+impl StubServer {
+    /// Serves `replies` on `addr` (use port 0 for an ephemeral port).
+    pub async fn start(addr: &str, replies: Vec<StubReply>) -> anyhow::Result<Self> {
+        let state = Arc::new(StubState {
+            replies: if replies.is_empty() {
+                vec![StubReply::clean()]
+            } else {
+                replies
+            },
+            served: AtomicUsize::new(0),
+        });
+        let app = Router::new()
+            .route("/v1/chat/completions", post(chat_completion))
+            .with_state(state.clone());
+
+        let listener = tokio::net::TcpListener::bind(addr)
+            .await
+            .with_context(|| format!("Failed to bind LLM stub to {}", addr))?;
+        let addr = listener.local_addr()?;
+        let handle = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("LLM stub server stopped: {}", e);
+            }
+        });
+
+        Ok(Self {
+            addr,
+            state,
+            handle: Some(handle),
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// Requests answered so far.
+    pub fn requests(&self) -> usize {
+        self.state.served.load(Ordering::SeqCst)
+    }
+
+    /// Runs until the server task ends.
+    pub async fn wait(mut self) -> anyhow::Result<()> {
+        match self.handle.take() {
+            Some(handle) => handle.await.context("LLM stub server task failed"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for StubServer {
+    fn drop(&mut self) {
+        if let Some(handle) = &self.handle {
+            handle.abort();
+        }
+    }
+}
+
+async fn chat_completion(State(state): State<Arc<StubState>>, Json(_): Json<Value>) -> Response {
+    let n = state.served.fetch_add(1, Ordering::SeqCst);
+    let reply = &state.replies[n.min(state.replies.len() - 1)];
+
+    let content = match reply {
+        StubReply::Verdict {
+            status,
+            risk_score,
+            issues,
+        } => json!({ "status": status, "risk_score": risk_score, "issues": issues }).to_string(),
+        StubReply::Text { content } => content.clone(),
+        StubReply::Blocked => {
+            return Json(json!({
+                "choices": [{ "message": { "content": null }, "finish_reason": "content_filter" }]
+            }))
+            .into_response()
+        }
+        StubReply::Error { status, message } => {
+            let status = StatusCode::from_u16(*status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return (status, Json(json!({ "error": { "message": message } }))).into_response();
+        }
+    };
+
+    Json(json!({
+        "choices": [{ "message": { "content": content }, "finish_reason": "stop" }]
+    }))
+    .into_response()
+}
@@ -0,0 +1,575 @@
+//! Offline scans for the `ghosthealth` CLI: a working tree, a diff or a git
+//! range, checked without the server, database or GitHub.
+
+use crate::ast_scan::{self, UnsafeInventory};
+use crate::autofix::SourceFile;
+use crate::diff::{self, LineKind};
+use crate::models::{AuditResult, Finding, Severity};
+use crate::scanner;
+use anyhow::Context;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use walkdir::WalkDir;
+
+/// Files larger than this are skipped in tree scans; they are rarely source.
+pub const MAX_FILE_BYTES: u64 = 1_000_000;
+
+/// Directories never scanned as part of a tree.
+const SKIPPED_DIRS: &[&str] = &["target", "node_modules", "vendor"];
+
+/// What a local scan looks at: a diff, plus the new-side content of the
+/// changed Rust files for the analyzers that need whole files.
+#[derive(Debug, Default, Clone)]
+pub struct Workspace {
+    pub diff: String,
+    pub files: Vec<SourceFile>,
+    /// Old-side content of the changed Rust files, keyed by their old path,
+    /// when the old side is known; used for the unsafe inventory delta.
+    pub base: Option<Vec<SourceFile>>,
+}
+
+impl Workspace {
+    /// Every text file under `root`, as if newly added. Paths are relative
+    /// to `root`.
+    pub fn tree(root: &Path) -> anyhow::Result<Self> {
+        if !root.is_dir() {
+            anyhow::bail!("{} is not a directory", root.display());
+        }
+        let mut workspace = Self::default();
+        let entries = WalkDir::new(root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|e| {
+                let name = e.file_name().to_string_lossy();
+                e.depth() == 0 || !(name.starts_with('.') || SKIPPED_DIRS.contains(&name.as_ref()))
+            })
+            .filter_map(Result::ok)
+            .filter(|e| e.file_type().is_file())
+            .filter(|e| e.metadata().is_ok_and(|m| m.len() <= MAX_FILE_BYTES));
+        for entry in entries {
+            // Binary and non-UTF-8 files are not source.
+            let Ok(content) = std::fs::read_to_string(entry.path()) else {
+                continue;
+            };
+            let path = entry
+                .path()
+                .strip_prefix(root)
+                .unwrap_or(entry.path())
+                .to_string_lossy()
+                .replace('\\', "/");
+            workspace.diff.push_str(&diff::added_file(&path, &content));
+            if path.ends_with(".rs") {
+                workspace.files.push(SourceFile { path, content });
+            }
+        }
+        Ok(workspace)
+    }
+
+    /// A unified diff. Changed Rust files are read from `root`, which should
+    /// hold the new side (e.g. the output of `git diff` in a working tree).
+    pub fn from_diff(diff: String, root: &Path) -> Self {
+        let files = changed_rust_files(&diff)
+            .into_iter()
+            .filter_map(|(path, _)| {
+                let content = std::fs::read_to_string(root.join(&path)).ok()?;
+                Some(SourceFile { path, content })
+            })
+            .collect();
+        Self {
+            diff,
+            files,
+            base: None,
+        }
+    }
+
+    /// `git diff <base>..<head>` in the repository at `root`, with changed
+    /// Rust files as of `head` (`HEAD` if omitted).
+    pub fn from_git(range: &str, root: &Path) -> anyhow::Result<Self> {
+        let (symmetric, (base, head)) = match range.split_once("...") {
+            Some(sides) => (true, sides),
+            None => (
+                false,
+                range
+                    .split_once("..")
+                    .with_context(|| format!("expected <base>..<head>, got {}", range))?,
+            ),
+        };
+        let or_head = |side: &str| {
+            if side.is_empty() {
+                "HEAD".to_string()
+            } else {
+                side.to_string()
+            }
+        };
+        let (base, head) = (or_head(base), or_head(head));
+        // `a...b` diffs against the merge base.
+        let base = if symmetric {
+            git(root, &["merge-base", &base, &head])?.trim().to_string()
+        } else {
+            base
+        };
+
+        let diff = git(root, &["diff", "--no-color", "--no-ext-diff", range])?;
+        let changed = changed_rust_files(&diff);
+        Ok(Self {
+            files: files_at(root, &head, changed.iter().map(|(new, _)| new)),
+            base: Some(files_at(
+                root,
+                &base,
+                changed.iter().filter_map(|(_, old)| old.as_ref()),
+            )),
+            diff,
+        })
+    }
+
+    /// What `git commit` would record: the staged changes, with changed Rust
+    /// files read from the index rather than the working tree.
+    pub fn staged(root: &Path) -> anyhow::Result<Self> {
+        let diff = git(
+            root,
+            &[
+                "diff",
+                "--cached",
+                "--no-color",
+                "--no-ext-diff",
+                "--diff-filter=ACMR",
+            ],
+        )?;
+        let changed = changed_rust_files(&diff);
+        // Before the first commit there is no HEAD to compare against.
+        let base = git(root, &["rev-parse", "--verify", "--quiet", "HEAD"])
+            .ok()
+            .map(|_| {
+                files_at(
+                    root,
+                    "HEAD",
+                    changed.iter().filter_map(|(_, old)| old.as_ref()),
+                )
+            });
+        Ok(Self {
+            files: files_at(root, "", changed.iter().map(|(new, _)| new)),
+            base,
+            diff,
+        })
+    }
+
+    /// The changes `commit` makes to its first parent.
+    pub fn commit(root: &Path, commit: &str) -> anyhow::Result<Self> {
+        let diff = git(
+            root,
+            &[
+                "diff-tree",
+                "-p",
+                "--root",
+                "-m",
+                "--first-parent",
+                "--no-commit-id",
+                "--no-color",
+                "--no-ext-diff",
+                "--diff-filter=ACMR",
+                commit,
+            ],
+        )?;
+        let changed = changed_rust_files(&diff);
+        let parent = format!("{}^", commit);
+        let base = git(root, &["rev-parse", "--verify", "--quiet", &parent])
+            .ok()
+            .map(|_| {
+                files_at(
+                    root,
+                    &parent,
+                    changed.iter().filter_map(|(_, old)| old.as_ref()),
+                )
+            });
+        Ok(Self {
+            files: files_at(root, commit, changed.iter().map(|(new, _)| new)),
+            base,
+            diff,
+        })
+    }
+
+    /// AST and (optionally) Semgrep findings on added lines of the diff, plus
+    /// `UNSAFE_INCREASE` for crates whose unsafe surface grew, when the old
+    /// side is known.
+    pub fn analyze(&self, semgrep: bool) -> Vec<Finding> {
+        let mut findings: Vec<Finding> = self
+            .files
+            .iter()
+            .flat_map(|f| ast_scan::scan_source(&f.path, &f.content))
+            .collect();
+        if semgrep {
+            findings.extend(self.semgrep());
+        }
+
+        let added = added_lines(&self.diff);
+        findings.retain(|f| {
+            f.location.as_ref().is_some_and(|l| {
+                added
+                    .get(&l.file)
+                    .is_some_and(|lines| lines.contains(&l.start_line))
+            })
+        });
+
+        if let Some(base) = &self.base {
+            let inventory = |files: &[SourceFile]| {
+                ast_scan::inventory_of(files.iter().map(|f| (f.path.as_str(), f.content.as_str())))
+            };
+            findings.extend(UnsafeInventory::regressions(
+                &inventory(base),
+                &inventory(&self.files),
+            ));
+        }
+        findings
+    }
+
+    /// Semgrep over a scratch copy of `files`, so it sees exactly the new side.
+    /// The copy goes in a fresh private directory, never a predictable path
+    /// another user of the host could have prepared.
+    fn semgrep(&self) -> Vec<Finding> {
+        let scratch = match tempfile::Builder::new()
+            .prefix("ghosthealth-scan-")
+            .tempdir()
+        {
+            Ok(dir) => dir,
+            Err(e) => {
+                tracing::warn!("Could not create a scratch directory for semgrep: {}", e);
+                return vec![];
+            }
+        };
+        for file in &self.files {
+            let path = Path::new(&file.path);
+            if !path.components().all(|c| matches!(c, Component::Normal(_))) {
+                continue;
+            }
+            let target = scratch.path().join(path);
+            let written = target
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&target, &file.content));
+            if let Err(e) = written {
+                tracing::warn!("Could not stage {} for semgrep: {}", file.path, e);
+            }
+        }
+        scanner::run_semgrep(scratch.path())
+    }
+}
+
+/// Line numbers added per file in `diff`.
+pub fn added_lines(diff: &str) -> HashMap<String, HashSet<u32>> {
+    let mut added: HashMap<String, HashSet<u32>> = HashMap::new();
+    for file in diff::parse(diff) {
+        let lines = added.entry(file.path).or_default();
+        for line in file.hunks.iter().flat_map(|h| h.lines.iter()) {
+            if let (LineKind::Added, Some(n)) = (line.kind, line.new_line) {
+                lines.insert(n);
+            }
+        }
+    }
+    added
+}
+
+/// New and old paths of the Rust files `diff` changes; the old path is
+/// `None` for added files.
+pub fn changed_rust_files(diff: &str) -> Vec<(String, Option<String>)> {
+    diff::parse(diff)
+        .into_iter()
+        .filter(|f| f.path.ends_with(".rs"))
+        .map(|f| (f.path.clone(), f.old_path().map(str::to_string)))
+        .collect()
+}
+
+/// Contents of `paths` at `rev` (the index if empty). Paths missing there,
+/// such as deleted files, are left out.
+fn files_at<'a>(
+    root: &Path,
+    rev: &str,
+    paths: impl Iterator<Item = &'a String>,
+) -> Vec<SourceFile> {
+    paths
+        .filter_map(|path| {
+            let content = git(root, &["show", &format!("{}:{}", rev, path)]).ok()?;
+            Some(SourceFile {
+                path: path.clone(),
+                content,
+            })
+        })
+        .collect()
+}
+
+fn git(root: &Path, args: &[&str]) -> anyhow::Result<String> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(root)
+        .args(args)
+        .output()
+        .context("Failed to run git")?;
+    if !out.status.success() {
+        anyhow::bail!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&out.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+// ─────────────────────────────────────────────
+// Git hooks
+// ─────────────────────────────────────────────
+/// Object name git uses for "no commit" in ref updates.
+const NULL_SHA: &str = "0000000000000000000000000000000000000000";
+
+/// Marks hook scripts as ours, so reinstalling may overwrite them.
+const HOOK_MARKER: &str =
+    "Installed by `ghosthealth install-hooks`; scans changes for PHI and secrets.";
+
+/// The hooks `ghosthealth install-hooks` can write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    /// Scans the staged changes of each commit on a developer's machine.
+    PreCommit,
+    /// Scans every pushed commit on a self-hosted git server.
+    PreReceive,
+}
+
+impl Hook {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PreCommit => "pre-commit",
+            Self::PreReceive => "pre-receive",
+        }
+    }
+
+    /// Hook script that hands over to `exe`, keeping stdin for pre-receive.
+    pub fn script(&self, exe: &str) -> String {
+        format!(
+            "#!/bin/sh\n# {}\nexec '{}' hook {} \"$@\"\n",
+            HOOK_MARKER,
+            exe.replace('\'', r"'\''"),
+            self.as_str()
+        )
+    }
+}
+
+impl FromStr for Hook {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "pre-commit" => Ok(Self::PreCommit),
+            "pre-receive" => Ok(Self::PreReceive),
+            other => anyhow::bail!("Unknown hook: {}", other),
+        }
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Writes `hook` into the hooks directory of the repository at `root`.
+/// An existing hook that is not ours is only replaced with `force`.
+pub fn install_hook(root: &Path, hook: Hook, exe: &str, force: bool) -> anyhow::Result<PathBuf> {
+    let dir = git(root, &["rev-parse", "--git-path", "hooks"])?;
+    let dir = root.join(dir.trim());
+    std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(hook.as_str());
+    if let Ok(existing) = std::fs::read_to_string(&path) {
+        if !existing.contains(HOOK_MARKER) && !force {
+            anyhow::bail!(
+                "{} already exists; pass --force to replace it",
+                path.display()
+            );
+        }
+    }
+    std::fs::write(&path, hook.script(exe))
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+/// One line of pre-receive input: `<old> <new> <ref>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefUpdate {
+    pub old: String,
+    pub new: String,
+    pub name: String,
+}
+
+impl RefUpdate {
+    pub fn parse_all(input: &str) -> Vec<Self> {
+        input
+            .lines()
+            .filter_map(|line| {
+                let mut parts = line.split_whitespace();
+                Some(Self {
+                    old: parts.next()?.to_string(),
+                    new: parts.next()?.to_string(),
+                    name: parts.next()?.to_string(),
+                })
+            })
+            .collect()
+    }
+
+    pub fn is_delete(&self) -> bool {
+        self.new == NULL_SHA
+    }
+}
+
+/// Commits the push adds to the repository, oldest first. Commits already
+/// reachable from an existing ref were scanned when they arrived.
+pub fn pushed_commits(root: &Path, updates: &[RefUpdate]) -> anyhow::Result<Vec<String>> {
+    let mut commits: Vec<String> = vec![];
+    for update in updates.iter().filter(|u| !u.is_delete()) {
+        let listed = git(
+            root,
+            &["rev-list", "--reverse", &update.new, "--not", "--all"],
+        )?;
+        for commit in listed.lines() {
+            if !commits.iter().any(|c| c == commit) {
+                commits.push(commit.to_string());
+            }
+        }
+    }
+    Ok(commits)
+}
+
+// ─────────────────────────────────────────────
+// Output and exit status
+// ─────────────────────────────────────────────
+/// Findings one per line, most severe first, then a one-line verdict.
+pub fn human(result: &AuditResult) -> String {
+    let mut issues: Vec<&Finding> = result.issues.iter().collect();
+    issues.sort_by(|a, b| {
+        b.severity.cmp(&a.severity).then_with(|| {
+            let at = |f: &Finding| f.location.as_ref().map(|l| (l.file.clone(), l.start_line));
+            at(a).cmp(&at(b))
+        })
+    });
+
+    let mut out = String::new();
+    for issue in &issues {
+        let place = issue
+            .location
+            .as_ref()
+            .map_or("-".to_string(), |l| l.to_string());
+        out.push_str(&format!(
+            "{:<8} {:<12} {}  {}\n",
+            issue.severity.as_str(),
+            issue.rule_id,
+            place,
+            issue.message
+        ));
+        if let Some(remediation) = &issue.remediation {
+            out.push_str(&format!("{:<22}fix: {}\n", "", remediation));
+        }
+    }
+
+    let mut counts: BTreeMap<Severity, usize> = BTreeMap::new();
+    for issue in &issues {
+        *counts.entry(issue.severity).or_default() += 1;
+    }
+    let counts: Vec<String> = counts
+        .iter()
+        .rev()
+        .map(|(severity, n)| format!("{} {}", n, severity.as_str().to_lowercase()))
+        .collect();
+    if !issues.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&format!(
+        "{} finding(s){} · risk score {} · {}\n",
+        issues.len(),
+        if counts.is_empty() {
+            String::new()
+        } else {
+            format!(" ({})", counts.join(", "))
+        },
+        result.risk_score,
+        result.status
+    ));
+    out
+}
+
+/// Whether any finding is at or above `threshold`; `None` never fails.
+pub fn fails(result: &AuditResult, threshold: Option<Severity>) -> bool {
+    threshold.is_some_and(|t| result.issues.iter().any(|f| f.severity >= t))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location};
+
+    #[test]
+    fn analyzes_only_added_lines() {
+        let content = "unsafe fn a() {}\nunsafe fn b() {}\n";
+        let diff = "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -1,1 +1,2 @@\n unsafe fn a() {}\n+unsafe fn b() {}\n";
+        let mut workspace = Workspace {
+            diff: diff.to_string(),
+            files: vec![SourceFile {
+                path: "src/a.rs".into(),
+                content: content.into(),
+            }],
+            base: None,
+        };
+        let findings = workspace.analyze(false);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].location.as_ref().map(|l| l.start_line), Some(2));
+
+        workspace.base = Some(vec![SourceFile {
+            path: "src/a.rs".into(),
+            content: "unsafe fn a() {}\n".into(),
+        }]);
+        let findings = workspace.analyze(false);
+        assert_eq!(findings.len(), 2);
+        assert!(findings[1].message.contains(".: 1 → 2 (+1)"));
+    }
+
+    #[test]
+    fn reads_ref_updates_and_writes_hook_scripts() {
+        let updates = RefUpdate::parse_all(&format!(
+            "{0} 1111111111111111111111111111111111111111 refs/heads/main\n\n2222222222222222222222222222222222222222 {0} refs/heads/old\n",
+            NULL_SHA
+        ));
+        assert_eq!(updates.len(), 2);
+        assert_eq!(updates[0].name, "refs/heads/main");
+        assert!(!updates[0].is_delete() && updates[1].is_delete());
+
+        let script = Hook::PreReceive.script("/opt/it's/ghosthealth");
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("exec '/opt/it'\\''s/ghosthealth' hook pre-receive \"$@\""));
+        assert_eq!("pre-commit".parse::<Hook>().ok(), Some(Hook::PreCommit));
+    }
+
+    #[test]
+    fn prints_findings_and_fails_on_threshold() {
+        let result = AuditResult {
+            risk_score: 40,
+            issues: vec![
+                Finding::new(Analyzer::Ast, "UNSAFE_BLOCK", Severity::Medium, "unsafe")
+                    .at(Location::line("src/b.rs", 3)),
+                Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "logged")
+                    .at(Location::line("src/a.rs", 9)),
+            ],
+            ..Default::default()
+        };
+        let out = human(&result);
+        let first = out.lines().next().unwrap_or_default();
+        assert!(first.starts_with("HIGH") && first.contains("src/a.rs:9  logged"));
+        assert!(out.ends_with("2 finding(s) (1 high, 1 medium) · risk score 40 · CLEAN\n"));
+
+        assert!(fails(&result, Some(Severity::High)));
+        assert!(!fails(&result, Some(Severity::Critical)));
+        assert!(!fails(&result, None));
+    }
+}
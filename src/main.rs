@@ -16,7 +16,6 @@ use jsonwebtoken::EncodingKey;
 use octocrab::Octocrab;
 use secrecy::{ExposeSecret, SecretString};
 use sha2::Sha256;
-use sqlx::Row;
 use std::sync::Arc;
 use subtle::ConstantTimeEq;
 use tracing::info;
@@ -65,6 +64,15 @@ async fn main() -> anyhow::Result<()> {
 
     info!("Database connection established to PostgreSQL");
 
+    // Verdicts cached under an older prompt or rule pack can never be hit again.
+    match cache::PgReviewCache::new(db.clone())
+        .purge_stale(audit::PROMPT_VERSION, scanner::RULE_PACK_VERSION)
+        .await
+    {
+        Ok(purged) => info!("Purged {} stale AI review cache entries", purged),
+        Err(e) => tracing::warn!("AI review cache purge failed: {:#}", e),
+    }
+
     // 5. GitHub Configuration (with local fallbacks to prevent crashes)
     let webhook_secret = SecretString::new(
         std::env::var("GITHUB_WEBHOOK_SECRET").unwrap_or_else(|_| "local_test_secret".to_string()),
@@ -124,9 +132,11 @@ async fn handle_webhook(
     headers: HeaderMap,
     body: Bytes,
 ) -> impl IntoResponse {
-    // SECURITY BYPASS: If verification fails, we log it but KEEP GOING for testing
+    // Unsigned deliveries never reach a job: commands trust the commenter's
+    // login from the payload, so a forged one could waive findings.
     if verify_webhook(&state, &headers, &body).is_err() {
-        tracing::warn!("Webhook verification failed (Bypassing for local testing)");
+        tracing::warn!("Rejected webhook with a missing or invalid signature");
+        return StatusCode::UNAUTHORIZED;
     }
 
     let event_type = headers
@@ -134,12 +144,6 @@ async fn handle_webhook(
         .and_then(|h| h.to_str().ok())
         .unwrap_or("unknown");
 
-    // Allow "unknown" events so you can test with Postman/Insomnia easily
-    if event_type != "pull_request" && event_type != "unknown" {
-        tracing::info!("Ignoring non-PR event: {}", event_type);
-        return StatusCode::ACCEPTED;
-    }
-
     let payload: serde_json::Value = match serde_json::from_slice(&body) {
         Ok(p) => p,
         Err(e) => {
@@ -148,17 +152,43 @@ async fn handle_webhook(
         }
     };
 
+    // Signed requests without an event header are scanned, for local testing.
+    // "Re-run" in the Checks tab arrives as a rerequested check run or suite,
+    // and slash commands as new comments on a PR.
+    let action = payload["action"].as_str().unwrap_or_default();
+    let job = match event_type {
+        "pull_request" | "unknown" => Job::Scan,
+        "check_run" | "check_suite" if action == "rerequested" => Job::Scan,
+        "issue_comment" if action == "created" && payload["issue"]["pull_request"].is_object() => {
+            Job::Command
+        }
+        _ => {
+            tracing::info!("Ignoring event: {} {}", event_type, action);
+            return StatusCode::ACCEPTED;
+        }
+    };
+
+    let event = event_type.to_string();
     let state_clone = state.clone();
     tokio::spawn(async move {
-        if let Err(e) = process_pull_request(state_clone, payload).await {
-            tracing::error!("Failed to process PR: {:?}", e);
+        let outcome = match job {
+            Job::Scan => process_pull_request(state_clone, &event, payload).await,
+            Job::Command => process_command(state_clone, payload).await,
+        };
+        if let Err(e) = outcome {
+            tracing::error!("Failed to process {}: {:?}", event, e);
         }
     });
 
-    tracing::info!("Queued PR processing job");
+    tracing::info!("Queued {} job", event_type);
     StatusCode::ACCEPTED
 }
 
+enum Job {
+    Scan,
+    Command,
+}
+
 fn verify_webhook(state: &AppState, headers: &HeaderMap, body: &Bytes) -> Result<(), ()> {
     let sig = match headers.get("X-Hub-Signature-256") {
         Some(s) => s.to_str().map_err(|_| ())?,
@@ -180,77 +210,208 @@ fn verify_webhook(state: &AppState, headers: &HeaderMap, body: &Bytes) -> Result
 
 async fn process_pull_request(
     state: Arc<AppState>,
+    event: &str,
     payload: serde_json::Value,
 ) -> anyhow::Result<()> {
-    let repo_name = payload["repository"]["full_name"]
-        .as_str()
-        .unwrap_or("ghosthealth/test-repo")
-        .to_string();
-
-    let pr_number = payload["pull_request"]["number"].as_u64().unwrap_or(0);
+    let pr = github::PullRequest::from_event(event, &payload);
+    if pr.is_none() && event.starts_with("check_") {
+        info!(
+            "Ignoring {} with no associated pull request (e.g. a push to a branch without a PR)",
+            event
+        );
+        return Ok(());
+    }
+    let pr = pr.unwrap_or_else(|| {
+        let (owner, repo) = payload["repository"]["full_name"]
+            .as_str()
+            .and_then(|name| name.split_once('/'))
+            .unwrap_or(("ghosthealth", "test-repo"));
+        github::PullRequest {
+            owner: owner.into(),
+            repo: repo.into(),
+            number: payload["pull_request"]["number"].as_u64().unwrap_or(0),
+            head_sha: None,
+            base_sha: None,
+            head_ref: None,
+        }
+    });
     let installation_id = payload["installation"]["id"].as_u64().unwrap_or(0);
     let action = payload["action"].as_str().unwrap_or("opened");
 
     info!(
-        "Processing PR #{} in {} (action: {})",
-        pr_number, repo_name, action
+        "Processing PR #{} in {}/{} ({} {})",
+        pr.number, pr.owner, pr.repo, event, action
     );
 
-    // TEST MODE: Hardcoded leak for Gemini AI to discover
-    let diff = "
-+ fn update_user() {
-+    let ssn = \"666-44-1111\";
-+    let name = \"John Ghost\";
-+    println!(\"Checking record for {}\", name);
-+ }
-"
-    .to_string();
-
-    // 1. Run AI Analysis
-    let result = github::process_diff(&diff)
+    let octo = github_client(&state, installation_id)?;
+
+    // The check run is what branch protection requires, so it is queued
+    // before anything else can fail.
+    let check_run = match &octo {
+        Some(octo) if pr.head_sha.is_some() => match github::create_check_run(octo, &pr).await {
+            Ok(id) => Some(id),
+            Err(e) => {
+                tracing::warn!("Check run creation failed: {:#}", e);
+                None
+            }
+        },
+        _ => None,
+    };
+
+    let outcome = scan_pull_request(&state, octo.as_ref(), &pr, check_run).await;
+    if let (Some(octo), Some(id), Err(e)) = (&octo, check_run, &outcome) {
+        if let Err(e) = github::fail_check_run(octo, &pr, id, e).await {
+            tracing::warn!("Failed to mark check run as failed: {:#}", e);
+        }
+    }
+    outcome
+}
+
+async fn scan_pull_request(
+    state: &AppState,
+    octo: Option<&Octocrab>,
+    pr: &github::PullRequest,
+    check_run: Option<u64>,
+) -> anyhow::Result<()> {
+    let repo_name = format!("{}/{}", pr.owner, pr.repo);
+    let pr_number = pr.number;
+
+    let Some(octo) = octo else {
+        tracing::warn!(
+            "Skipping PR #{} in {}: no GitHub App key configured to fetch its diff",
+            pr_number,
+            repo_name
+        );
+        return Ok(());
+    };
+    let diff = github::get_pr_diff(octo, &pr.owner, &pr.repo, pr_number)
         .await
-        .context("Gemini AI Analysis failed")?;
-
-    // 2. Blockchain Audit Chain Hashing
-    let last_record: Option<sqlx::postgres::PgRow> =
-        sqlx::query("SELECT current_hash FROM audit_logs ORDER BY created_at DESC LIMIT 1")
-            .fetch_optional(&state.db)
-            .await?;
-
-    let prev_hash = if let Some(row) = last_record {
-        row.try_get::<String, _>("current_hash")?
-    } else {
-        "GENESIS_BLOCK".to_string()
+        .context("Failed to fetch PR diff")?;
+
+    // Changed Rust files on both sides, for the AST scan and unsafe inventory.
+    let (new_paths, old_paths): (Vec<String>, Vec<Option<String>>) =
+        local::changed_rust_files(&diff).into_iter().unzip();
+    let files = match &pr.head_sha {
+        Some(sha) => {
+            let paths = new_paths.iter().map(String::as_str);
+            github::fetch_files(octo, &pr.owner, &pr.repo, paths, sha).await
+        }
+        None => vec![],
     };
+    let base = match &pr.base_sha {
+        Some(sha) => {
+            let paths = old_paths.iter().flatten().map(String::as_str);
+            Some(github::fetch_files(octo, &pr.owner, &pr.repo, paths, sha).await)
+        }
+        None => None,
+    };
+    let workspace = local::Workspace { diff, files, base };
 
-    let entry = audit::AuditEntry::new(
-        &format!("{}{}", repo_name, serde_json::to_string(&result)?),
-        &prev_hash,
-    );
-    let new_hash = entry.entry_hash.clone();
+    // 1. Resolve tenant and its AI provider
+    let tenant_id = resolve_tenant(&state.db).await?;
+
+    let reviewer = llm::LlmConfig::for_tenant(&state.db, tenant_id)
+        .await?
+        .build()
+        .context("Failed to configure AI reviewer")?;
 
-    // 3. Database Persistence
-    let tenant_row: (String,) = sqlx::query_as("SELECT id FROM tenants LIMIT 1")
+    let mut options = audit::ReviewOptions::for_tenant(&state.db, tenant_id).await?;
+    let findings = lifecycle::PgFindingStore::new(state.db.clone());
+    options.waived = findings.active_waivers(tenant_id, &repo_name).await?;
+
+    // 2. Run AI Analysis
+    if let Some(id) = check_run {
+        if let Err(e) = github::start_check_run(octo, pr, id).await {
+            tracing::warn!("Failed to start check run: {:#}", e);
+        }
+    }
+    let mut result = github::process_diff_with(
+        Some(reviewer.as_ref()),
+        &options,
+        &workspace.diff,
+        workspace.analyze(false),
+    )
+    .await
+    .context("AI Analysis failed")?;
+
+    let ai_fixes: bool = sqlx::query_scalar("SELECT ai_fixes FROM tenants WHERE id = $1")
+        .bind(tenant_id)
         .fetch_one(&state.db)
         .await
-        .context("No tenant found. Run your SQL setup scripts first.")?;
-
-    sqlx::query(
-        r#"
-        INSERT INTO audit_logs
-        (tenant_id, repo_name, pr_number, status, risk_score, report, previous_hash, current_hash)
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
-        "#,
+        .unwrap_or(false);
+    if ai_fixes {
+        let attempts =
+            ai_fix::propose_all(reviewer.as_ref(), &workspace.files, &mut result.issues).await;
+        for attempt in &attempts {
+            sqlx::query(
+                r#"
+                INSERT INTO ai_fix_attempts
+                (tenant_id, repo_name, pr_number, model, rule_id, fingerprint, location, accepted, reason)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+                "#,
+            )
+            .bind(tenant_id)
+            .bind(&repo_name)
+            .bind(pr_number as i32)
+            .bind(reviewer.model())
+            .bind(&attempt.rule_id)
+            .bind(&attempt.fingerprint)
+            .bind(attempt.location.as_ref().map(|l| l.to_string()))
+            .bind(attempt.rejected.is_none())
+            .bind(&attempt.rejected)
+            .execute(&state.db)
+            .await
+            .context("Failed to record AI fix attempt")?;
+        }
+        info!(
+            "AI fixes: {} of {} proposals verified",
+            attempts.iter().filter(|a| a.rejected.is_none()).count(),
+            attempts.len()
+        );
+    }
+
+    if let Some(grounding) = &result.grounding {
+        sqlx::query(
+            r#"
+            INSERT INTO ai_grounding_stats
+            (tenant_id, model, cited, grounded, relocated, downgraded, dropped)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(reviewer.model())
+        .bind(grounding.cited as i32)
+        .bind(grounding.grounded as i32)
+        .bind(grounding.relocated as i32)
+        .bind(grounding.downgraded as i32)
+        .bind(grounding.dropped as i32)
+        .execute(&state.db)
+        .await
+        .context("Failed to record AI grounding stats")?;
+    }
+
+    let lifecycle = findings
+        .sync(tenant_id, &repo_name, pr_number, &result.issues)
+        .await
+        .context("Failed to track findings")?;
+    info!(
+        "Findings: {} new, {} reopened, {} fixed, {} persisting",
+        lifecycle.opened, lifecycle.reopened, lifecycle.fixed, lifecycle.persisting
+    );
+
+    // 3. Blockchain Audit Chain Hashing and 4. Database Persistence
+    let entry = audit::append_to_chain(
+        &state.db,
+        &audit::ChainEntry {
+            tenant_id,
+            repo_name: &repo_name,
+            pr_number,
+            kind: "scan",
+            status: result.status.as_str(),
+            risk_score: result.risk_score,
+            report: &result,
+        },
     )
-    .bind(uuid::Uuid::parse_str(&tenant_row.0).context("Invalid tenant ID format")?)
-    .bind(&repo_name)
-    .bind(pr_number as i32)
-    .bind(&result.status)
-    .bind(result.risk_score as i32)
-    .bind(serde_json::to_value(&result)?)
-    .bind(prev_hash)
-    .bind(new_hash)
-    .execute(&state.db)
     .await?;
 
     info!(
@@ -258,18 +419,334 @@ async fn process_pull_request(
         entry.entry_hash
     );
 
-    // 4. GitHub Review (Wrapped in error handling so local runs don't crash without real keys)
-    if !state.private_key.is_empty() {
-        let app_key = EncodingKey::from_rsa_pem(&state.private_key).context("Invalid RSA key")?;
-        let octo = Octocrab::builder()
-            .app(state.app_id.into(), app_key)
-            .build()?
-            .installation(octocrab::models::InstallationId(installation_id));
+    let reports = export::ReportSettings::for_tenant(&state.db, tenant_id)
+        .await?
+        .render_all(&result)?;
+    for (destination, format, content) in reports {
+        sqlx::query(
+            r#"
+            INSERT INTO scan_reports (tenant_id, repo_name, pr_number, destination, format, content)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+        )
+        .bind(tenant_id)
+        .bind(&repo_name)
+        .bind(pr_number as i32)
+        .bind(&destination)
+        .bind(format.as_str())
+        .bind(content)
+        .execute(&state.db)
+        .await
+        .context("Failed to store report")?;
+    }
 
-        let (owner, repo) = repo_name.split_once('/').unwrap_or(("ghost", "repo"));
-        let _ = github::post_review(&octo, owner, repo, pr_number, &result).await;
-        info!("Review posted to GitHub PR #{}", pr_number);
+    // 5. GitHub Review and check run
+    let template = markdown::Template::for_tenant(&state.db, tenant_id).await?;
+    // A failed review still lets the check run complete below.
+    match github::post_review(
+        octo,
+        pr,
+        &diff::parse(&workspace.diff),
+        &result,
+        &template,
+        &entry.entry_hash,
+    )
+    .await
+    {
+        Ok(()) => info!("Review posted to GitHub PR #{}", pr_number),
+        Err(e) => tracing::error!("Failed to post review to PR #{}: {:#}", pr_number, e),
     }
 
+    if let Some(id) = check_run {
+        github::complete_check_run(octo, pr, id, &result, &template)
+            .await
+            .context("Failed to complete check run")?;
+        info!("Check run {} completed", id);
+    }
+
+    let upload_sarif: bool = sqlx::query_scalar("SELECT upload_sarif FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(false);
+    if upload_sarif && pr.head_sha.is_some() {
+        match github::upload_sarif(octo, pr, &result).await {
+            Ok(id) => info!("SARIF uploaded to code scanning ({})", id),
+            Err(e) => tracing::warn!("SARIF upload failed: {:#}", e),
+        }
+    }
+
+    let autofix: bool = sqlx::query_scalar("SELECT autofix FROM tenants WHERE id = $1")
+        .bind(tenant_id)
+        .fetch_one(&state.db)
+        .await
+        .unwrap_or(false);
+    if let (true, models::Status::Violation, Some(head_ref)) =
+        (autofix, result.status, &pr.head_ref)
+    {
+        match remediation::open_remediation_pr(
+            octo,
+            &pr.owner,
+            &pr.repo,
+            head_ref,
+            &result.issues,
+            Some(pr.number),
+        )
+        .await
+        {
+            Ok(Some(url)) => {
+                info!("Opened remediation PR {}", url);
+                let note = format!(
+                    "🔧 **GhostHealth Guard** opened {} with verified fixes for some of these findings.",
+                    url
+                );
+                if let Err(e) = github::post_comment(octo, pr, &note).await {
+                    tracing::warn!("Failed to link remediation PR: {:#}", e);
+                }
+            }
+            Ok(None) => info!(
+                "No finding on PR #{} could be fixed automatically",
+                pr_number
+            ),
+            Err(e) => tracing::warn!("Remediation PR failed: {:#}", e),
+        }
+    }
+
+    Ok(())
+}
+
+async fn process_command(state: Arc<AppState>, payload: serde_json::Value) -> anyhow::Result<()> {
+    // The Guard's own comments quote commands; never act on a bot.
+    if payload["comment"]["user"]["type"] == "Bot" {
+        return Ok(());
+    }
+    let body = payload["comment"]["body"].as_str().unwrap_or_default();
+    let Some(parsed) = chatops::Command::parse(body, chrono::Utc::now()) else {
+        return Ok(());
+    };
+    let Some((owner, repo)) = payload["repository"]["full_name"]
+        .as_str()
+        .and_then(|name| name.split_once('/'))
+    else {
+        anyhow::bail!("Comment event without a repository");
+    };
+    let pr = github::PullRequest {
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        number: payload["issue"]["number"].as_u64().unwrap_or(0),
+        head_sha: None,
+        base_sha: None,
+        head_ref: None,
+    };
+    let repo_name = format!("{}/{}", pr.owner, pr.repo);
+    let actor = payload["comment"]["user"]["login"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let comment_id = payload["comment"]["id"].as_u64().unwrap_or(0);
+    let installation_id = payload["installation"]["id"].as_u64().unwrap_or(0);
+
+    let Some(octo) = github_client(&state, installation_id)? else {
+        tracing::warn!("Ignoring /ghosthealth command: no GitHub App key configured");
+        return Ok(());
+    };
+    let tenant_id = resolve_tenant(&state.db).await?;
+    info!(
+        "{} ran a /ghosthealth command on {}#{}",
+        actor, repo_name, pr.number
+    );
+
+    let mut record = chatops::CommandRecord {
+        actor: actor.clone(),
+        comment_id,
+        role: None,
+        command: None,
+        outcome: chatops::Outcome::Invalid,
+        detail: String::new(),
+    };
+    let (reaction, reply) = match parsed {
+        Err(e) => {
+            record.detail = e.to_string();
+            (chatops::REACTION_INVALID, Some(e.to_string()))
+        }
+        Ok(command) => {
+            let role = github::collaborator_role(&octo, &pr.owner, &pr.repo, &actor)
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::warn!("Could not read {}'s role: {:#}", actor, e);
+                    chatops::Role::None
+                });
+            record.role = Some(role);
+            record.command = Some(command.clone());
+            if role < command.required_role() {
+                record.outcome = chatops::Outcome::Denied;
+                (
+                    chatops::REACTION_DENIED,
+                    Some(chatops::denied_comment(&command, role)),
+                )
+            } else {
+                if let Err(e) = github::react_to_comment(
+                    &octo,
+                    &pr.owner,
+                    &pr.repo,
+                    comment_id,
+                    chatops::REACTION_ACCEPTED,
+                )
+                .await
+                {
+                    tracing::warn!("Failed to acknowledge command: {:#}", e);
+                }
+                match run_command(&state, &octo, &payload, &pr, tenant_id, &actor, &command).await {
+                    Ok(reply) => {
+                        record.outcome = chatops::Outcome::Done;
+                        (chatops::REACTION_ACCEPTED, reply)
+                    }
+                    Err(e) => {
+                        tracing::error!("/ghosthealth {} failed: {:?}", command.name(), e);
+                        record.outcome = chatops::Outcome::Failed;
+                        record.detail = format!("{:#}", e);
+                        (
+                            chatops::REACTION_INVALID,
+                            Some(format!(
+                                "`{} {}` failed: {}",
+                                chatops::PREFIX,
+                                command.name(),
+                                e
+                            )),
+                        )
+                    }
+                }
+            }
+        }
+    };
+
+    // Replies are best effort; the audit record below is not.
+    if reaction != chatops::REACTION_ACCEPTED {
+        if let Err(e) =
+            github::react_to_comment(&octo, &pr.owner, &pr.repo, comment_id, reaction).await
+        {
+            tracing::warn!("Failed to react to command: {:#}", e);
+        }
+    }
+    if let Some(reply) = reply {
+        if let Err(e) = github::post_comment(&octo, &pr, &reply).await {
+            tracing::warn!("Failed to reply to command: {:#}", e);
+        }
+    }
+
+    let entry = audit::append_to_chain(
+        &state.db,
+        &audit::ChainEntry {
+            tenant_id,
+            repo_name: &repo_name,
+            pr_number: pr.number,
+            kind: "command",
+            status: record.outcome.as_str(),
+            risk_score: 0,
+            report: &record,
+        },
+    )
+    .await?;
+    info!(
+        "Command recorded in audit chain with hash {}",
+        entry.entry_hash
+    );
     Ok(())
 }
+
+/// Runs a permitted command and returns the reply to post, if any.
+async fn run_command(
+    state: &Arc<AppState>,
+    octo: &Octocrab,
+    payload: &serde_json::Value,
+    pr: &github::PullRequest,
+    tenant_id: uuid::Uuid,
+    actor: &str,
+    command: &chatops::Command,
+) -> anyhow::Result<Option<String>> {
+    let repo_name = format!("{}/{}", pr.owner, pr.repo);
+    let latest = || async {
+        audit::latest_report(&state.db, tenant_id, &repo_name, pr.number)
+            .await?
+            .context("This PR has not been scanned yet")
+    };
+
+    match command {
+        chatops::Command::Rescan => {
+            let scan = serde_json::json!({
+                "action": "rescan",
+                "repository": payload["repository"],
+                "installation": payload["installation"],
+                "pull_request": github::get_pull_request(octo, pr).await?,
+            });
+            process_pull_request(state.clone(), "pull_request", scan).await?;
+            Ok(None)
+        }
+        chatops::Command::Explain { finding } => {
+            let report = latest().await?;
+            let finding = chatops::find_finding(&report, finding)?;
+            let reviewer = llm::LlmConfig::for_tenant(&state.db, tenant_id)
+                .await?
+                .build()
+                .context("Failed to configure AI reviewer")?;
+            let answer = reviewer
+                .complete(&chatops::explain_request(finding))
+                .await?;
+            Ok(Some(chatops::explain_comment(
+                finding,
+                &answer,
+                &reviewer.model(),
+            )))
+        }
+        chatops::Command::Waive {
+            fingerprint,
+            reason,
+            expires,
+        } => {
+            let report = latest().await?;
+            let finding = chatops::find_finding(&report, fingerprint)?;
+            let waiver = lifecycle::Waiver {
+                reason: reason.clone(),
+                expires: Some(*expires),
+                granted_by: actor.to_string(),
+            };
+            let tracked = lifecycle::PgFindingStore::new(state.db.clone())
+                .suppress(
+                    tenant_id,
+                    &repo_name,
+                    finding.fingerprint.as_deref().unwrap_or_default(),
+                    &waiver,
+                )
+                .await?;
+            anyhow::ensure!(tracked, "That finding is not tracked for this repository");
+            Ok(Some(chatops::waive_comment(finding, &waiver)))
+        }
+        chatops::Command::Report => {
+            let proof =
+                audit::ChainProof::latest_scan(&state.db, tenant_id, &repo_name, pr.number).await?;
+            Ok(Some(chatops::report_comment(proof.as_ref())))
+        }
+    }
+}
+
+async fn resolve_tenant(db: &PgPool) -> anyhow::Result<uuid::Uuid> {
+    sqlx::query_scalar("SELECT id FROM tenants LIMIT 1")
+        .fetch_one(db)
+        .await
+        .context("No tenant found. Run your SQL setup scripts first.")
+}
+
+/// Installation client, or `None` without a private key so local runs don't
+/// crash without real keys.
+fn github_client(state: &AppState, installation_id: u64) -> anyhow::Result<Option<Octocrab>> {
+    if state.private_key.is_empty() {
+        return Ok(None);
+    }
+    let app_key = EncodingKey::from_rsa_pem(&state.private_key).context("Invalid RSA key")?;
+    Ok(Some(
+        Octocrab::builder()
+            .app(state.app_id.into(), app_key)
+            .build()?
+            .installation(octocrab::models::InstallationId(installation_id)),
+    ))
+}
@@ -0,0 +1,616 @@
+//! Renders an `AuditResult` as the Markdown of the Guard's PR summary comment
+//! and reviews.
+//!
+//! The layout comes from a template with `{{placeholder}}` slots, so tenants
+//! can reorder or drop sections. Findings are filled in last and cut, least
+//! severe first, to keep the body under GitHub's size limit.
+
+use crate::controls;
+use crate::lifecycle;
+use crate::models::{AiOutcome, AuditResult, Finding, Severity, Status};
+use anyhow::Context;
+use sqlx::PgPool;
+use std::collections::BTreeMap;
+
+/// GitHub rejects review and comment bodies longer than 65,536 characters.
+/// Byte length is used, which is never less than the character count.
+pub const BODY_LIMIT: usize = 65_536;
+
+/// Longest snippet shown per finding.
+const SNIPPET_LIMIT: usize = 800;
+
+pub const PLACEHOLDERS: &[&str] = &[
+    "header",
+    "notes",
+    "status",
+    "risk_score",
+    "summary",
+    "score_breakdown",
+    "controls",
+    "findings",
+];
+
+pub const DEFAULT_TEMPLATE: &str = "{{header}}
+{{notes}}
+**Status:** {{status}} · **Risk Score:** {{risk_score}}/100
+
+{{summary}}
+{{score_breakdown}}
+{{controls}}
+{{findings}}";
+
+/// Where findings link to: `https://github.com/{owner}/{repo}/blob/{head_sha}/…`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLinks {
+    pub owner: String,
+    pub repo: String,
+    pub head_sha: String,
+}
+
+impl SourceLinks {
+    pub fn url(&self, finding: &Finding) -> Option<String> {
+        let location = finding.location.as_ref()?;
+        let mut url = format!(
+            "https://github.com/{}/{}/blob/{}/{}#L{}",
+            self.owner,
+            self.repo,
+            self.head_sha,
+            location.file.replace(' ', "%20"),
+            location.start_line
+        );
+        if let Some(end) = location.end_line.filter(|end| *end > location.start_line) {
+            url.push_str(&format!("-L{}", end));
+        }
+        Some(url)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template(String);
+
+impl Default for Template {
+    fn default() -> Self {
+        Self(DEFAULT_TEMPLATE.to_string())
+    }
+}
+
+impl Template {
+    /// Accepts a template whose `{{…}}` slots are all known placeholders.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            let after = &rest[start + 2..];
+            let end = after.find("}}").context("Unclosed {{ in review template")?;
+            let name = after[..end].trim();
+            if !PLACEHOLDERS.contains(&name) {
+                anyhow::bail!("Unknown review template placeholder: {{{{{}}}}}", name);
+            }
+            rest = &after[end + 2..];
+        }
+        Ok(Self(text.to_string()))
+    }
+
+    /// The tenant's `review_template`, or the default. An invalid template is
+    /// logged and ignored so a typo never blocks reviews.
+    pub async fn for_tenant(db: &PgPool, tenant_id: uuid::Uuid) -> anyhow::Result<Self> {
+        let text: Option<Option<String>> =
+            sqlx::query_scalar("SELECT review_template FROM tenants WHERE id = $1")
+                .bind(tenant_id)
+                .fetch_optional(db)
+                .await
+                .context("Failed to load tenant review template")?;
+        Ok(match text.flatten() {
+            Some(text) => Self::parse(&text).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring tenant review template: {:#}", e);
+                Self::default()
+            }),
+            None => Self::default(),
+        })
+    }
+
+    /// Substitutes in one pass, so `{{…}}` inside a value is left alone.
+    fn fill(&self, values: &BTreeMap<&str, String>) -> String {
+        let mut out = String::with_capacity(self.0.len());
+        let mut rest = self.0.as_str();
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                rest = &rest[start..];
+                break;
+            };
+            out.push_str(values.get(after[..end].trim()).map_or("", String::as_str));
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+pub fn render(result: &AuditResult, template: &Template, links: Option<&SourceLinks>) -> String {
+    render_summary(result, template, links, &[])
+}
+
+/// Like `render`, but the findings at `inlined` are left out of the findings
+/// list because they were posted as line comments.
+pub fn render_summary(
+    result: &AuditResult,
+    template: &Template,
+    links: Option<&SourceLinks>,
+    inlined: &[usize],
+) -> String {
+    let mut values: BTreeMap<&str, String> = BTreeMap::from([
+        ("header", header(result.status).to_string()),
+        ("notes", notes(result)),
+        ("status", result.status.to_string()),
+        ("risk_score", result.risk_score.to_string()),
+        ("summary", summary(&result.issues)),
+        (
+            "score_breakdown",
+            result
+                .score_breakdown
+                .as_ref()
+                .map(|b| {
+                    format!(
+                        "<details><summary>Why this score?</summary>\n\n{}\n</details>\n",
+                        b.render_markdown()
+                    )
+                })
+                .unwrap_or_default(),
+        ),
+        ("controls", controls_section(&result.issues)),
+        ("findings", String::new()),
+    ]);
+
+    let frame = template.fill(&values);
+    let budget = BODY_LIMIT.saturating_sub(frame.len() + 200);
+    values.insert(
+        "findings",
+        findings_section(&result.issues, links, inlined, budget),
+    );
+
+    let body = template.fill(&values);
+    if body.len() <= BODY_LIMIT {
+        return body;
+    }
+    // Only reachable when the non-finding sections alone are too long.
+    let notice = "\n\n> Review truncated: the full report is stored with the audit log.";
+    let mut cut = BODY_LIMIT - notice.len();
+    while !body.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}{}", &body[..cut], notice)
+}
+
+fn header(status: Status) -> &'static str {
+    match status {
+        Status::Violation => "⚠️ **GhostHealth Guard: Action Required**",
+        Status::NeedsReview => "🔍 **GhostHealth Guard: Human Review Required**",
+        Status::Blocked => "ℹ️ **GhostHealth Guard: Scan Partial**",
+        Status::Clean => "✅ **GhostHealth Guard: Clean**",
+    }
+}
+
+fn notes(result: &AuditResult) -> String {
+    let mut notes = vec![];
+    match &result.ai_outcome {
+        Some(AiOutcome::FailedClosed { .. }) => notes.push(
+            "> The AI reviewer did not return a valid answer. A human must review this PR."
+                .to_string(),
+        ),
+        Some(AiOutcome::FailedOpen { .. }) => notes.push(
+            "> ⚠️ The AI reviewer did not return a valid answer. Findings come from deterministic analyzers only."
+                .to_string(),
+        ),
+        _ => {}
+    }
+    if !result.failed_chunks.is_empty() {
+        notes.push(format!(
+            "> AI review did not complete for: {}",
+            result.failed_chunks.join(", ")
+        ));
+    }
+    let cached = result.issues.iter().filter(|i| i.cached).count();
+    if cached > 0 {
+        notes.push(format!(
+            "> {} finding(s) were reused from an earlier AI review of identical changes.",
+            cached
+        ));
+    }
+    if notes.is_empty() {
+        String::new()
+    } else {
+        format!("\n{}\n", notes.join("\n>\n"))
+    }
+}
+
+fn icon(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "🛑",
+        Severity::High => "🔴",
+        Severity::Medium => "🟠",
+        Severity::Low => "🟡",
+        Severity::Info => "🔵",
+    }
+}
+
+fn summary(issues: &[Finding]) -> String {
+    if issues.is_empty() {
+        return "No findings.\n".to_string();
+    }
+    let mut out = String::from("| Severity | Findings |\n|---|---:|\n");
+    for severity in Severity::ALL.iter().rev() {
+        let count = issues.iter().filter(|i| i.severity == *severity).count();
+        if count > 0 {
+            out.push_str(&format!(
+                "| {} {} | {} |\n",
+                icon(*severity),
+                severity,
+                count
+            ));
+        }
+    }
+    out
+}
+
+fn controls_section(issues: &[Finding]) -> String {
+    let affected = controls::posture(issues, None);
+    if affected.is_empty() {
+        return String::new();
+    }
+    let rows: Vec<String> = affected
+        .iter()
+        .map(|p| {
+            format!(
+                "- **{}** {} — {} finding(s), highest {}",
+                p.key, p.title, p.findings, p.highest_severity
+            )
+        })
+        .collect();
+    format!(
+        "<details><summary>Controls affected ({})</summary>\n\n{}\n</details>\n",
+        affected.len(),
+        rows.join("\n")
+    )
+}
+
+/// Findings grouped by file, most severe first. Entries that do not fit in
+/// `budget` bytes are dropped from the least severe end and counted instead.
+fn findings_section(
+    issues: &[Finding],
+    links: Option<&SourceLinks>,
+    inlined: &[usize],
+    budget: usize,
+) -> String {
+    let inline_note = match inlined.len() {
+        0 => String::new(),
+        n => format!(
+            "\n> {} finding(s) are posted as comments on the changed lines.\n",
+            n
+        ),
+    };
+    if issues.len() == inlined.len() {
+        return inline_note;
+    }
+    let mut ordered: Vec<&Finding> = issues
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !inlined.contains(i))
+        .map(|(_, issue)| issue)
+        .collect();
+    ordered.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| file_of(a).cmp(file_of(b)))
+            .then_with(|| line_of(a).cmp(&line_of(b)))
+    });
+
+    let title = "### Findings\n";
+    let budget = budget.saturating_sub(inline_note.len());
+    let mut used = title.len();
+    let mut shown: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    let mut hidden = 0;
+    for issue in ordered {
+        let entry = finding_entry(issue, links);
+        let heading = if shown.contains_key(file_of(issue)) {
+            0
+        } else {
+            file_heading(file_of(issue)).len()
+        };
+        if hidden > 0 || used + heading + entry.len() > budget {
+            hidden += 1;
+            continue;
+        }
+        used += heading + entry.len();
+        shown.entry(file_of(issue)).or_default().push(entry);
+    }
+
+    let mut out = title.to_string();
+    for (file, entries) in shown {
+        out.push_str(&file_heading(file));
+        for entry in entries {
+            out.push_str(&entry);
+        }
+    }
+    out.push_str(&inline_note);
+    if hidden > 0 {
+        out.push_str(&format!(
+            "\n> {} more finding(s) not shown to stay within GitHub's size limit. The full report is stored with the audit log.\n",
+            hidden
+        ));
+    }
+    out
+}
+
+fn file_heading(file: &str) -> String {
+    format!("\n#### `{}`\n\n", file)
+}
+
+fn finding_entry(issue: &Finding, links: Option<&SourceLinks>) -> String {
+    let place = match (&issue.location, links.and_then(|l| l.url(issue))) {
+        (Some(location), Some(url)) => format!(" at [line {}]({})", location.start_line, url),
+        (Some(location), None) => format!(" at line {}", location.start_line),
+        (None, _) => String::new(),
+    };
+    let mut out = format!(
+        "- {} **{}** `{}`{}: {}\n",
+        icon(issue.severity),
+        issue.severity,
+        issue.rule_id,
+        place,
+        escape(&issue.message)
+    );
+
+    let mut details = vec![];
+    if let Some(snippet) = &issue.snippet {
+        details.push(code_block(snippet));
+    }
+    match (&issue.fix, &issue.remediation) {
+        (Some(fix), _) => details.push(format!(
+            "**Suggested fix:** {}\n\n{}",
+            fix.description,
+            fenced(&fix.replacement, "")
+        )),
+        (None, Some(remediation)) => details.push(format!("**Fix:** {}", remediation)),
+        (None, None) => {}
+    }
+    let mut refs: Vec<String> = issue.cwe.iter().map(|c| format!("CWE-{}", c)).collect();
+    refs.extend(issue.controls.iter().cloned());
+    refs.extend(
+        issue
+            .fingerprint
+            .as_deref()
+            .map(|fp| format!("ID `{}`", lifecycle::short_fingerprint(fp))),
+    );
+    if !refs.is_empty() {
+        details.push(refs.join(" · "));
+    }
+    if !details.is_empty() {
+        out.push_str(&format!(
+            "  <details><summary>Details</summary>\n\n{}\n\n  </details>\n",
+            details
+                .iter()
+                .map(|d| indent(d))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        ));
+    }
+    out
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|l| format!("  {}", l))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn code_block(snippet: &str) -> String {
+    fenced(&truncate(snippet, SNIPPET_LIMIT), "")
+}
+
+/// `text` in a fence longer than any backtick run inside it, so diff content
+/// cannot close the block early. `info` is the info string, e.g. `suggestion`.
+pub fn fenced(text: &str, info: &str) -> String {
+    let longest = text.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{}{}\n{}\n{}", fence, info, text, fence)
+}
+
+/// One-line description of a finding: severity, rule ID and message.
+pub fn finding_headline(issue: &Finding) -> String {
+    format!(
+        "{} **{}** `{}`: {}",
+        icon(issue.severity),
+        issue.severity,
+        issue.rule_id,
+        escape(&issue.message)
+    )
+}
+
+/// Hidden first line of the summary comment, used to find and edit it.
+pub const SUMMARY_MARKER: &str = "<!-- ghosthealth-guard:summary -->";
+
+/// Prefix of the hidden line on blocking reviews; it records the audit hash
+/// of the scan that posted the review.
+const REVIEW_MARKER: &str = "<!-- ghosthealth-guard:review audit=";
+
+/// Body of the summary comment that is edited in place on every scan.
+pub fn summary_comment(summary: &str) -> String {
+    let mut cut = summary.len().min(BODY_LIMIT - SUMMARY_MARKER.len() - 1);
+    while !summary.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}\n{}", SUMMARY_MARKER, &summary[..cut])
+}
+
+/// Short review body; the full report is in the summary comment.
+pub fn review_body(result: &AuditResult, audit_hash: &str) -> String {
+    let count = match result.issues.len() {
+        1 => "1 finding".to_string(),
+        n => format!("{} findings", n),
+    };
+    format!(
+        "{}{} -->\n{}\n\n{}, risk score {}/100. See the GhostHealth Guard summary comment for the full report.",
+        REVIEW_MARKER,
+        audit_hash,
+        header(result.status),
+        count,
+        result.risk_score
+    )
+}
+
+/// The audit hash recorded in a review body, if the Guard posted it.
+pub fn review_audit_hash(body: &str) -> Option<&str> {
+    let rest = body.strip_prefix(REVIEW_MARKER)?;
+    Some(rest[..rest.find(" -->")?].trim()).filter(|hash| !hash.is_empty())
+}
+
+/// Posted once a clean scan has dismissed earlier blocking reviews.
+pub fn resolved_note(blocking_hashes: &[&str], audit_hash: &str) -> String {
+    let blocking = blocking_hashes
+        .iter()
+        .map(|hash| format!("`{}`", hash))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "✅ **GhostHealth Guard: Resolved**\n\nThe findings that blocked this PR are gone, so the earlier review was dismissed.\n\n| Scan | Audit hash |\n|---|---|\n| Blocking | {} |\n| Clean | `{}` |",
+        blocking, audit_hash
+    )
+}
+
+/// Message text is partly model- and diff-derived: keep it from opening HTML
+/// tags or pinging people.
+pub fn escape(text: &str) -> String {
+    text.replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('@', "@\u{200b}")
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut cut = max;
+    while !text.is_char_boundary(cut) {
+        cut -= 1;
+    }
+    format!("{}…", &text[..cut])
+}
+
+fn file_of(issue: &Finding) -> &str {
+    issue
+        .location
+        .as_ref()
+        .map_or("(repository)", |l| l.file.as_str())
+}
+
+fn line_of(issue: &Finding) -> u32 {
+    issue.location.as_ref().map_or(0, |l| l.start_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location};
+
+    fn links() -> SourceLinks {
+        SourceLinks {
+            owner: "acme".into(),
+            repo: "ehr".into(),
+            head_sha: "abc123".into(),
+        }
+    }
+
+    #[test]
+    fn renders_summary_links_and_details() {
+        let result = AuditResult {
+            status: Status::Violation,
+            risk_score: 40,
+            issues: vec![Finding::new(
+                Analyzer::Regex,
+                "PHI_LOGGING",
+                Severity::High,
+                "PHI field logged by @bob",
+            )
+            .at(Location::line("src/a.rs", 4))
+            .with_snippet("println!(\"{}\", ssn);")],
+            ..Default::default()
+        };
+        let body = render(&result, &Template::default(), Some(&links()));
+
+        assert!(body.contains("| 🔴 HIGH | 1 |"));
+        assert!(body.contains("#### `src/a.rs`"));
+        assert!(body.contains("[line 4](https://github.com/acme/ehr/blob/abc123/src/a.rs#L4)"));
+        assert!(body.contains("**Fix:** Log an opaque identifier"));
+        assert!(!body.contains("@bob"));
+        assert!(!body.contains("Finding {"));
+    }
+
+    #[test]
+    fn drops_least_severe_findings_to_fit_the_limit() {
+        let big = "x".repeat(SNIPPET_LIMIT);
+        let mut issues: Vec<Finding> = (1..=400)
+            .map(|n| {
+                Finding::new(Analyzer::Ast, "UNSAFE_BLOCK", Severity::Low, "unsafe block")
+                    .at(Location::line("src/ffi.rs", n))
+                    .with_snippet(big.clone())
+            })
+            .collect();
+        issues.push(
+            Finding::new(
+                Analyzer::Regex,
+                "HARDCODED_SECRET",
+                Severity::Critical,
+                "key",
+            )
+            .at(Location::line("src/z.rs", 1)),
+        );
+        let result = AuditResult {
+            issues,
+            ..Default::default()
+        };
+        let body = render(&result, &Template::default(), None);
+
+        assert!(body.len() <= BODY_LIMIT);
+        assert!(body.contains("#### `src/z.rs`"));
+        assert!(body.contains("more finding(s) not shown"));
+    }
+
+    #[test]
+    fn review_markers_round_trip_the_audit_hash() {
+        let result = AuditResult {
+            status: Status::Violation,
+            issues: vec![Finding::new(
+                Analyzer::Regex,
+                "PHI_LOGGING",
+                Severity::High,
+                "PHI logged",
+            )],
+            ..Default::default()
+        };
+        let body = review_body(&result, "abc123");
+        assert_eq!(review_audit_hash(&body), Some("abc123"));
+        assert!(body.contains("Action Required"));
+        assert_eq!(review_audit_hash("Looks fine to me"), None);
+
+        let comment = summary_comment(&"x".repeat(BODY_LIMIT));
+        assert!(comment.starts_with(SUMMARY_MARKER));
+        assert!(comment.len() <= BODY_LIMIT);
+    }
+
+    #[test]
+    fn templates_only_accept_known_placeholders() {
+        assert!(Template::parse("{{header}}\n{{ findings }}").is_ok());
+        assert!(Template::parse("{{header}} {{secrets}}").is_err());
+        assert!(Template::parse("{{header").is_err());
+
+        let result = AuditResult::default();
+        let body = render(
+            &result,
+            &Template::parse("Score {{risk_score}}").unwrap_or_default(),
+            None,
+        );
+        assert_eq!(body, "Score 0");
+    }
+}
@@ -1,15 +1,466 @@
+use crate::rules;
+use crate::scoring::ScoreBreakdown;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Version of the serialized `AuditResult`/`Finding` shape. Rows written before
+/// versioning deserialize as version 1.
+pub const FINDING_SCHEMA_VERSION: u32 = 2;
+
+fn legacy_schema_version() -> u32 {
+    1
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AuditResult {
-    pub status: String,
+    #[serde(default = "legacy_schema_version")]
+    pub schema_version: u32,
+    pub status: Status,
     pub risk_score: u8,
-    pub issues: Vec<Issue>,
+    pub issues: Vec<Finding>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_outcome: Option<AiOutcome>,
+    /// Values redacted before the diff was sent to the AI, counted per kind.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub redactions: BTreeMap<String, usize>,
+    /// Diff chunks whose AI review failed or was blocked.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub failed_chunks: Vec<String>,
+    /// How many AI citations could be anchored to changed lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grounding: Option<GroundingStats>,
+    /// Edition of the control catalog used to tag `issues`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub catalog_version: Option<String>,
+    /// How `risk_score` was reached, per category.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<ScoreBreakdown>,
+    /// Fingerprints of findings left out of `issues` because they are waived.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub waived: Vec<String>,
+}
+
+impl Default for AuditResult {
+    fn default() -> Self {
+        Self {
+            schema_version: FINDING_SCHEMA_VERSION,
+            status: Status::default(),
+            risk_score: 0,
+            issues: vec![],
+            ai_outcome: None,
+            redactions: BTreeMap::new(),
+            failed_chunks: vec![],
+            grounding: None,
+            catalog_version: None,
+            score_breakdown: None,
+            waived: vec![],
+        }
+    }
+}
+
+/// Overall verdict, ordered from least to most severe.
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Status {
+    #[default]
+    Clean,
+    /// The AI provider refused to review; deterministic results only.
+    Blocked,
+    NeedsReview,
+    Violation,
+}
+
+impl Status {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Clean => "CLEAN",
+            Self::Blocked => "BLOCKED",
+            Self::NeedsReview => "NEEDS_REVIEW",
+            Self::Violation => "VIOLATION",
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Severity {
+    #[default]
+    Info,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 5] = [
+        Self::Info,
+        Self::Low,
+        Self::Medium,
+        Self::High,
+        Self::Critical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Info => "INFO",
+            Self::Low => "LOW",
+            Self::Medium => "MEDIUM",
+            Self::High => "HIGH",
+            Self::Critical => "CRITICAL",
+        }
+    }
+
+    /// One level lower, bottoming out at `Low`.
+    pub fn downgrade(&self) -> Self {
+        match self {
+            Self::Critical => Self::High,
+            Self::High => Self::Medium,
+            _ => Self::Low,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|v| v.as_str().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| anyhow::anyhow!("Unknown severity: {}", s))
+    }
+}
+
+/// Which analyzer produced a finding.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Analyzer {
+    /// Pattern rules over the diff (`scanner`).
+    Regex,
+    /// Syntax-tree analysis of Rust sources (`ast_scan`).
+    Ast,
+    /// Prompt-injection phrases (`injection`).
+    Injection,
+    Semgrep,
+    /// The AI reviewer.
+    Ai,
+    /// Recorded before findings carried their analyzer.
+    #[default]
+    Unknown,
+}
+
+impl Analyzer {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Regex => "regex",
+            Self::Ast => "ast",
+            Self::Injection => "injection",
+            Self::Semgrep => "semgrep",
+            Self::Ai => "ai",
+            Self::Unknown => "unknown",
+        }
+    }
+}
+
+#[derive(
+    Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Confidence {
+    Low,
+    Medium,
+    #[default]
+    High,
+}
+
+impl Confidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct Issue {
+/// A source range. Lines and columns are 1-based; `file` is repo-relative
+/// where known.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Location {
+    pub file: String,
+    pub start_line: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_column: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_column: Option<u32>,
+}
+
+impl Location {
+    pub fn line(file: impl Into<String>, line: u32) -> Self {
+        Self {
+            file: file.into(),
+            start_line: line,
+            ..Default::default()
+        }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.start_line)?;
+        if let Some(column) = self.start_column {
+            write!(f, ":{}", column)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(from = "FindingRecord")]
+pub struct Finding {
+    /// Stable identifier from the rule catalog, e.g. `GHG-PHI-001`.
+    pub rule_id: String,
     pub category: String,
-    pub severity: String,
+    pub severity: Severity,
+    pub analyzer: Analyzer,
+    pub confidence: Confidence,
     pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Location>,
+    /// The offending code with PHI and secrets masked.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub snippet: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwe: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remediation: Option<String>,
+    /// Regulatory controls this finding relates to, e.g. `HIPAA §164.312(b)`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub controls: Vec<String>,
+    /// Identity of the finding across scans; see `lifecycle::fingerprint`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// A mechanical fix for the lines at `location`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fix: Option<Fix>,
+    /// Served from the AI review cache rather than a fresh model call.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cached: bool,
+}
+
+impl Finding {
+    /// A finding with rule ID, CWE and remediation filled in from the rule catalog.
+    pub fn new(
+        analyzer: Analyzer,
+        category: &str,
+        severity: Severity,
+        message: impl Into<String>,
+    ) -> Self {
+        let rule = rules::for_category(category);
+        Self {
+            rule_id: rule.map_or_else(|| category.to_string(), |r| r.id.to_string()),
+            category: category.to_string(),
+            severity,
+            analyzer,
+            confidence: Confidence::High,
+            message: message.into(),
+            cwe: rule.and_then(|r| r.cwe),
+            remediation: rule.map(|r| r.remediation.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn at(mut self, location: Location) -> Self {
+        self.location = Some(location);
+        self
+    }
+
+    pub fn with_snippet(mut self, snippet: impl Into<String>) -> Self {
+        self.snippet = Some(snippet.into());
+        self
+    }
+
+    pub fn with_confidence(mut self, confidence: Confidence) -> Self {
+        self.confidence = confidence;
+        self
+    }
+}
+
+/// Replacement text for the whole line range of a finding's location.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fix {
+    pub description: String,
+    /// New content for lines `start_line..=end_line`, without a trailing newline.
+    pub replacement: String,
+}
+
+/// Accepts both the current shape and version-1 rows, which were a bare
+/// `{category, severity, message}` plus, for AI answers, flat `file`, `line`
+/// and `quote`.
+#[derive(Deserialize)]
+struct FindingRecord {
+    #[serde(default)]
+    rule_id: Option<String>,
+    category: String,
+    severity: Severity,
+    #[serde(default)]
+    analyzer: Option<Analyzer>,
+    #[serde(default)]
+    confidence: Option<Confidence>,
+    message: String,
+    #[serde(default)]
+    location: Option<Location>,
+    #[serde(default)]
+    snippet: Option<String>,
+    #[serde(default)]
+    cwe: Option<u32>,
+    #[serde(default)]
+    remediation: Option<String>,
+    #[serde(default)]
+    controls: Vec<String>,
+    #[serde(default)]
+    fingerprint: Option<String>,
+    #[serde(default)]
+    fix: Option<Fix>,
+    #[serde(default)]
+    cached: bool,
+    // Version 1 / AI answer fields.
+    #[serde(default)]
+    file: Option<String>,
+    #[serde(default)]
+    line: Option<u32>,
+    #[serde(default)]
+    quote: Option<String>,
+}
+
+impl From<FindingRecord> for Finding {
+    fn from(record: FindingRecord) -> Self {
+        let base = Finding::new(
+            record.analyzer.unwrap_or_default(),
+            &record.category,
+            record.severity,
+            record.message,
+        );
+        let legacy_location = match (record.file, record.line) {
+            (Some(file), Some(line)) => Some(Location::line(file, line)),
+            _ => None,
+        };
+        Self {
+            rule_id: record.rule_id.unwrap_or(base.rule_id),
+            confidence: record.confidence.unwrap_or(base.confidence),
+            location: record.location.or(legacy_location),
+            snippet: record.snippet.or(record.quote),
+            cwe: record.cwe.or(base.cwe),
+            remediation: record.remediation.or(base.remediation),
+            controls: record.controls,
+            fingerprint: record.fingerprint,
+            fix: record.fix,
+            cached: record.cached,
+            ..base
+        }
+    }
+}
+
+/// How the AI review concluded, recorded separately from the findings.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AiOutcome {
+    /// First answer passed schema validation.
+    Valid,
+    /// Passed validation after `attempts` repair re-prompts.
+    Repaired { attempts: u32 },
+    /// The provider's safety or content filter refused the diff.
+    Blocked,
+    /// No valid answer; the PR is held for human review.
+    FailedClosed { errors: Vec<String> },
+    /// No valid answer; the scan continued on deterministic findings only.
+    FailedOpen { errors: Vec<String> },
+}
+
+/// Outcome of checking AI citations against the diff.
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub struct GroundingStats {
+    /// AI findings checked.
+    pub cited: u32,
+    /// Quote found on the cited changed line.
+    pub grounded: u32,
+    /// Quote found on a different changed line; the citation was corrected.
+    pub relocated: u32,
+    /// Cited a changed line but the quote did not match; severity lowered.
+    pub downgraded: u32,
+    /// Could not be anchored to any changed line; removed.
+    pub dropped: u32,
+}
+
+impl GroundingStats {
+    /// Share of findings anchored exactly or after relocation, from 0.0 to 1.0.
+    pub fn rate(&self) -> f64 {
+        if self.cited == 0 {
+            return 1.0;
+        }
+        f64::from(self.grounded + self.relocated) / f64::from(self.cited)
+    }
+
+    pub fn add(&mut self, other: &Self) {
+        self.cited += other.cited;
+        self.grounded += other.grounded;
+        self.relocated += other.relocated;
+        self.downgraded += other.downgraded;
+        self.dropped += other.dropped;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_version_one_report_rows() {
+        let row = r#"{"status":"VIOLATION","risk_score":85,"issues":[
+            {"category":"PHI_LOGGING","severity":"HIGH","message":"PHI field logged at line 4"},
+            {"category":"SAFETY_FILTER","severity":"INFO","message":"blocked"}
+        ]}"#;
+        let result: AuditResult = serde_json::from_str(row).unwrap_or_default();
+
+        assert_eq!(result.schema_version, 1);
+        assert_eq!(result.status, Status::Violation);
+        assert_eq!(result.issues[0].rule_id, "GHG-PHI-001");
+        assert_eq!(result.issues[0].cwe, Some(532));
+        assert_eq!(result.issues[1].severity, Severity::Info);
+    }
+
+    #[test]
+    fn round_trips_current_shape() {
+        let finding = Finding::new(Analyzer::Ast, "TRANSMUTE", Severity::High, "transmute")
+            .at(Location::line("src/lib.rs", 7))
+            .with_confidence(Confidence::Medium);
+        let json = serde_json::to_value(&finding).unwrap_or_default();
+        let back: Finding = serde_json::from_value(json.clone()).unwrap_or_default();
+
+        assert_eq!(json["analyzer"], "ast");
+        assert_eq!(json["location"]["start_line"], 7);
+        assert_eq!(back.location, finding.location);
+        assert_eq!(back.confidence, Confidence::Medium);
+    }
 }
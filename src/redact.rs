@@ -0,0 +1,287 @@
+use crate::diff;
+use crate::models::Finding;
+use regex::{Captures, Regex};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::sync::OnceLock;
+
+/// A detector replaces capture group `group` of `pattern` with a `<KIND_n>` placeholder.
+struct Detector {
+    kind: &'static str,
+    pattern: &'static str,
+    group: usize,
+}
+
+// Secrets run first so a token value is never half-eaten by a narrower PHI pattern.
+const DETECTORS: &[Detector] = &[
+    Detector {
+        kind: "SECRET",
+        pattern: r#"(?i)(?:password|secret|api_key|apikey|token|private_key)\s*[:=]\s*"([^"]+)""#,
+        group: 1,
+    },
+    Detector {
+        kind: "AWS_KEY",
+        pattern: r"\b(AKIA[0-9A-Z]{16})\b",
+        group: 1,
+    },
+    Detector {
+        kind: "SSN",
+        pattern: r"\b(\d{3}-\d{2}-\d{4})\b",
+        group: 1,
+    },
+    Detector {
+        kind: "EMAIL",
+        pattern: r"\b([A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,})\b",
+        group: 1,
+    },
+    Detector {
+        kind: "PHONE",
+        pattern: r"(\(?\b\d{3}\)?[-. ]\d{3}[-. ]\d{4})\b",
+        group: 1,
+    },
+    Detector {
+        kind: "MRN",
+        pattern: r"(?i)\bmrn[\s:#=]*([A-Z0-9]{6,})\b",
+        group: 1,
+    },
+    Detector {
+        kind: "DATE",
+        pattern: r"\b(\d{4}-\d{2}-\d{2}|\d{2}/\d{2}/\d{4})\b",
+        group: 1,
+    },
+    Detector {
+        kind: "NAME",
+        pattern: r#"(?i)\b(?:\w*_)?name\s*[:=]\s*"([^"]+)""#,
+        group: 1,
+    },
+];
+
+static COMPILED: OnceLock<Vec<(&'static Detector, Regex)>> = OnceLock::new();
+
+fn detectors() -> &'static [(&'static Detector, Regex)] {
+    COMPILED.get_or_init(|| {
+        DETECTORS
+            .iter()
+            .filter_map(|d| Regex::new(d.pattern).ok().map(|re| (d, re)))
+            .collect()
+    })
+}
+
+/// Where a placeholder came from. The original value stays in memory and never
+/// leaves this struct.
+struct Placeholder {
+    original: String,
+    lines: Vec<usize>,
+}
+
+/// A diff with PHI and secrets swapped for stable placeholders such as `<SSN_1>`.
+pub struct Redaction {
+    pub text: String,
+    placeholders: HashMap<String, Placeholder>,
+    counts: BTreeMap<String, usize>,
+}
+
+// Hand-written so the originals can't end up in logs via `{:?}`.
+impl fmt::Debug for Redaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redaction")
+            .field("placeholders", &self.placeholders.len())
+            .field("counts", &self.counts)
+            .finish()
+    }
+}
+
+impl Redaction {
+    /// Occurrences redacted per kind, safe to persist.
+    pub fn counts(&self) -> BTreeMap<String, usize> {
+        self.counts.clone()
+    }
+
+    /// Diff line numbers (1-based) a placeholder was taken from.
+    pub fn lines_of(&self, token: &str) -> Option<&[usize]> {
+        self.placeholders.get(token).map(|p| p.lines.as_slice())
+    }
+
+    /// Original value behind a placeholder, for in-process use only.
+    pub fn original(&self, token: &str) -> Option<&str> {
+        self.placeholders.get(token).map(|p| p.original.as_str())
+    }
+
+    /// Annotates findings whose message or snippet quotes placeholders with
+    /// the diff lines they refer to, and puts original values back into
+    /// location fields so the finding still points at the file in the diff.
+    /// Values are deliberately not written back into the message or snippet.
+    pub fn restore_locations(&self, issues: &mut [Finding]) {
+        for issue in issues {
+            if let Some(location) = &mut issue.location {
+                for (token, p) in &self.placeholders {
+                    if location.file.contains(token.as_str()) {
+                        location.file = location.file.replace(token.as_str(), &p.original);
+                    }
+                }
+            }
+
+            let quoted = |token: &str| {
+                issue.message.contains(token)
+                    || issue.snippet.as_deref().is_some_and(|s| s.contains(token))
+            };
+            let mut located: Vec<String> = self
+                .placeholders
+                .iter()
+                .filter(|(token, _)| quoted(token.as_str()))
+                .map(|(token, p)| {
+                    let lines: Vec<String> = p.lines.iter().map(|l| l.to_string()).collect();
+                    format!("{} at diff line {}", token, lines.join(", "))
+                })
+                .collect();
+            located.sort();
+
+            if !located.is_empty() {
+                issue.message = format!("{} (redacted: {})", issue.message, located.join("; "));
+            }
+        }
+    }
+}
+
+/// Runs the deterministic detectors over `diff`, replacing each match.
+/// Diff headers are left as they are: file paths and hunk ranges must
+/// survive for findings to be located, and only the section text after a
+/// hunk's closing `@@` is redacted.
+pub fn redact(diff: &str) -> Redaction {
+    let mut by_value: HashMap<(&'static str, String), String> = HashMap::new();
+    let mut next_id: HashMap<&'static str, usize> = HashMap::new();
+    let mut placeholders: HashMap<String, Placeholder> = HashMap::new();
+    let mut counts: BTreeMap<String, usize> = BTreeMap::new();
+    let mut out = Vec::new();
+
+    let headers = diff::header_lines(diff);
+    for (i, raw) in diff.lines().enumerate() {
+        let (kept, rest) = if headers.contains(&i) {
+            match raw.get(3..).and_then(|r| r.find(" @@")) {
+                Some(end) if raw.starts_with("@@ ") => raw.split_at(end + 6),
+                _ => (raw, ""),
+            }
+        } else {
+            ("", raw)
+        };
+        let mut line = rest.to_string();
+
+        for (detector, re) in detectors() {
+            line = re
+                .replace_all(&line, |caps: &Captures| {
+                    let whole = &caps[0];
+                    let Some(m) = caps.get(detector.group) else {
+                        return whole.to_string();
+                    };
+                    let value = m.as_str().to_string();
+
+                    let token = by_value
+                        .entry((detector.kind, value.clone()))
+                        .or_insert_with(|| {
+                            let n = next_id.entry(detector.kind).or_insert(0);
+                            *n += 1;
+                            format!("<{}_{}>", detector.kind, n)
+                        })
+                        .clone();
+
+                    let placeholder = placeholders.entry(token.clone()).or_insert(Placeholder {
+                        original: value,
+                        lines: vec![],
+                    });
+                    if placeholder.lines.last() != Some(&(i + 1)) {
+                        placeholder.lines.push(i + 1);
+                    }
+                    *counts.entry(detector.kind.to_string()).or_insert(0) += 1;
+
+                    let offset = caps.get(0).map(|c| c.start()).unwrap_or(0);
+                    format!(
+                        "{}{}{}",
+                        &whole[..m.start() - offset],
+                        token,
+                        &whole[m.end() - offset..]
+                    )
+                })
+                .into_owned();
+        }
+
+        out.push(format!("{}{}", kept, line));
+    }
+
+    let mut text = out.join("\n");
+    if diff.ends_with('\n') {
+        text.push('\n');
+    }
+
+    Redaction {
+        text,
+        placeholders,
+        counts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location, Severity};
+
+    #[test]
+    fn replaces_values_with_stable_placeholders() {
+        let diff = "+ let ssn = \"666-44-1111\";\n+ let name = \"John Ghost\";\n+ check(\"666-44-1111\");\n";
+        let redaction = redact(diff);
+
+        assert!(!redaction.text.contains("666-44-1111"));
+        assert!(!redaction.text.contains("John Ghost"));
+        assert_eq!(redaction.text.matches("<SSN_1>").count(), 2);
+        assert_eq!(redaction.lines_of("<SSN_1>"), Some(&[1, 3][..]));
+        assert_eq!(redaction.original("<NAME_1>"), Some("John Ghost"));
+        assert_eq!(redaction.counts().get("SSN"), Some(&2));
+    }
+
+    #[test]
+    fn redacts_only_the_secret_value() {
+        let redaction = redact("+ let api_key = \"sk-live-123\";");
+        assert_eq!(redaction.text, "+ let api_key = \"<SECRET_1>\";");
+    }
+
+    #[test]
+    fn maps_placeholders_in_findings_back_to_lines() {
+        let redaction = redact("+ fn f() {}\n+ log(\"a@b.io\");");
+        let mut issues = vec![Finding::new(
+            Analyzer::Ai,
+            "PHI_LOGGING",
+            Severity::High,
+            "Email <EMAIL_1> is logged",
+        )];
+        redaction.restore_locations(&mut issues);
+
+        assert!(issues[0]
+            .message
+            .ends_with("(redacted: <EMAIL_1> at diff line 2)"));
+        assert!(!issues[0].message.contains("a@b.io"));
+    }
+
+    #[test]
+    fn leaves_paths_and_hunk_ranges_alone() {
+        let diff = "diff --git a/db/2024-01-02_init.sql b/db/2024-01-02_init.sql\n--- /dev/null\n+++ b/db/2024-01-02_init.sql\n@@ -0,0 +1,2 @@ -- seeded 2024-01-02\n+-- call 555-123-4567\n+SELECT 1;\n";
+        let redaction = redact(diff);
+        let lines: Vec<&str> = redaction.text.lines().collect();
+        assert_eq!(lines[..3], diff.lines().take(3).collect::<Vec<_>>()[..]);
+        assert_eq!(lines[3], "@@ -0,0 +1,2 @@ -- seeded <DATE_1>");
+        assert_eq!(lines[4], "+-- call <PHONE_1>");
+
+        let mut issues = vec![
+            Finding::new(Analyzer::Ai, "PHI_LOGGING", Severity::High, "x")
+                .at(Location::line("db/<DATE_1>_init.sql", 1))
+                .with_snippet("-- call <PHONE_1>"),
+        ];
+        redaction.restore_locations(&mut issues);
+        assert_eq!(
+            issues[0].location.as_ref().map(|l| l.file.as_str()),
+            Some("db/2024-01-02_init.sql")
+        );
+        assert!(issues[0]
+            .message
+            .ends_with("(redacted: <PHONE_1> at diff line 5)"));
+        assert_eq!(issues[0].snippet.as_deref(), Some("-- call <PHONE_1>"));
+    }
+}
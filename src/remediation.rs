@@ -1,30 +1,104 @@
+//! Opens a pull request with the patches `autofix` can make for a scan's
+//! findings: every changed file in one commit on a new branch.
+
+use crate::autofix::{self, FixPlan};
+use crate::github;
+use crate::hash::generate_hash;
+use crate::lifecycle;
+use crate::markdown::{self, SourceLinks};
+use crate::models::Finding;
+use chrono::{DateTime, Utc};
 use octocrab::{params::repos::Reference, Octocrab};
-use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::BTreeSet;
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct AuditResult {
-    pub status: String,
-    pub risk_score: u8,
-    pub issues: Vec<Issue>,
+/// A branch nobody else will have: timestamp plus a hash of what it fixes.
+pub fn branch_name(plan: &FixPlan, now: DateTime<Utc>) -> String {
+    let fixed: Vec<&str> = plan
+        .fixed
+        .iter()
+        .filter_map(|f| f.fingerprint.as_deref())
+        .collect();
+    let digest = generate_hash(&format!(
+        "{}{}",
+        fixed.join(","),
+        now.timestamp_nanos_opt().unwrap_or_default()
+    ));
+    format!(
+        "ghosthealth/autofix-{}-{}",
+        now.format("%Y%m%d-%H%M%S"),
+        &digest[..8.min(digest.len())]
+    )
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct Issue {
-    pub category: String,
-    pub severity: String,
-    pub message: String,
+/// PR body: the findings fixed, each linked to where it was reported, and
+/// those left for a human.
+pub fn pr_body(plan: &FixPlan, links: &SourceLinks, source_pr: Option<u64>) -> String {
+    let mut body = match source_pr {
+        Some(number) => format!(
+            "Automated fixes for GhostHealth Guard findings on #{}.\n",
+            number
+        ),
+        None => "Automated fixes for GhostHealth Guard findings.\n".to_string(),
+    };
+
+    body.push_str("\n### Fixed\n\n| Finding | Reported at | Fix |\n|---|---|---|\n");
+    for finding in &plan.fixed {
+        let place = finding.location.as_ref().map_or(String::new(), |l| {
+            let text = format!("{}:{}", l.file, l.start_line);
+            links
+                .url(finding)
+                .map_or(text.clone(), |url| format!("[{}]({})", text, url))
+        });
+        let id = finding.fingerprint.as_deref().map_or(String::new(), |fp| {
+            format!(" (ID `{}`)", lifecycle::short_fingerprint(fp))
+        });
+        body.push_str(&format!(
+            "| **{}** `{}`{} | {} | {} |\n",
+            finding.severity,
+            finding.rule_id,
+            id,
+            place,
+            autofix::fixer_for(&finding.rule_id)
+                .map(|f| f.description())
+                .or(finding.fix.as_ref().map(|f| f.description.as_str()))
+                .unwrap_or_default()
+        ));
+    }
+
+    if !plan.skipped.is_empty() {
+        body.push_str("\n### Not fixed automatically\n\n");
+        for (finding, reason) in &plan.skipped {
+            let place = finding
+                .location
+                .as_ref()
+                .map_or(String::new(), |l| format!(" at {}", l));
+            body.push_str(&format!(
+                "- `{}`{}: {}\n",
+                finding.rule_id,
+                place,
+                markdown::escape(reason)
+            ));
+        }
+    }
+
+    body.push_str(
+        "\n### Verification\n\nEach patched file was re-parsed and re-scanned: the fixed findings are no longer reported and nothing new is. \
+Build and test before merging; secrets moved out of the code must still be rotated.\n",
+    );
+    body
 }
 
+/// Fixes what `autofix` can of `findings` on top of `base_branch` and opens
+/// a PR into it. Returns the PR's URL, or `None` if nothing could be fixed.
 pub async fn open_remediation_pr(
     client: &Octocrab,
     owner: &str,
     repo: &str,
     base_branch: &str,
-    fix_content: String,
-) -> anyhow::Result<()> {
-    let branch_name = "ghosthealth-remediation";
-    let target_path = "src/patient.rs";
-
+    findings: &[Finding],
+    source_pr: Option<u64>,
+) -> anyhow::Result<Option<String>> {
     // Get the base reference
     let base_ref = client
         .repos(owner, repo)
@@ -41,70 +115,111 @@ pub async fn open_remediation_pr(
         }
     };
 
-    // Create branch if it doesn't exist
-    if let Err(err) = client
-        .repos(owner, repo)
-        .create_ref(&Reference::Branch(branch_name.to_string()), &base_sha)
-        .await
-    {
-        let err_string = err.to_string();
-        if !err_string.contains("Reference already exists") {
-            return Err(err.into());
-        }
+    // Fetch every file a fixer applies to, as of the base commit
+    let paths: BTreeSet<&str> = findings
+        .iter()
+        .filter(|f| autofix::fixer_for(&f.rule_id).is_some() || f.fix.is_some())
+        .filter_map(|f| f.location.as_ref().map(|l| l.file.as_str()))
+        .collect();
+    let files = github::fetch_files(client, owner, repo, paths, &base_sha).await;
+
+    let plan = autofix::plan(&files, findings);
+    if plan.is_empty() {
+        return Ok(None);
     }
 
-    // Try to update existing file or create new one
-    let existing_file = client
-        .repos(owner, repo)
-        .get_content()
-        .path(target_path)
-        .r#ref(branch_name)
-        .send()
-        .await;
-
-    match existing_file {
-        Ok(page) => {
-            let file = page.items.first().ok_or_else(|| {
-                anyhow::anyhow!("Expected existing file metadata for {}", target_path)
-            })?;
-
-            client
-                .repos(owner, repo)
-                .update_file(
-                    target_path,
-                    "Auto-remediation: remove PHI logging",
-                    &fix_content,
-                    file.sha.clone(),
-                )
-                .branch(branch_name)
-                .send()
-                .await?;
-        }
-        Err(_) => {
-            client
-                .repos(owner, repo)
-                .create_file(
-                    target_path,
-                    "Auto-remediation: remove PHI logging",
-                    &fix_content,
-                )
-                .branch(branch_name)
-                .send()
-                .await?;
+    // One commit with every patched file, on a new branch
+    let base_commit: serde_json::Value = client
+        .get(
+            format!("/repos/{}/{}/git/commits/{}", owner, repo, base_sha),
+            None::<&()>,
+        )
+        .await?;
+    let tree: Vec<serde_json::Value> = plan
+        .patches
+        .iter()
+        .map(|p| json!({ "path": p.path, "mode": "100644", "type": "blob", "content": p.patched }))
+        .collect();
+    let tree: serde_json::Value = client
+        .post(
+            format!("/repos/{}/{}/git/trees", owner, repo),
+            Some(&json!({ "base_tree": base_commit["tree"]["sha"], "tree": tree })),
+        )
+        .await?;
+
+    let mut message = format!("Fix {} GhostHealth Guard finding(s)\n\n", plan.fixed.len());
+    for finding in &plan.fixed {
+        if let Some(location) = &finding.location {
+            message.push_str(&format!("- {} at {}\n", finding.rule_id, location));
         }
     }
+    let commit: serde_json::Value = client
+        .post(
+            format!("/repos/{}/{}/git/commits", owner, repo),
+            Some(&json!({ "message": message, "tree": tree["sha"], "parents": [base_sha] })),
+        )
+        .await?;
 
-    // Create the pull request - CORRECT for octocrab 0.38
+    let branch = branch_name(&plan, Utc::now());
     client
+        .post::<_, serde_json::Value>(
+            format!("/repos/{}/{}/git/refs", owner, repo),
+            Some(&json!({ "ref": format!("refs/heads/{}", branch), "sha": commit["sha"] })),
+        )
+        .await?;
+
+    let links = SourceLinks {
+        owner: owner.to_string(),
+        repo: repo.to_string(),
+        head_sha: base_sha,
+    };
+    let pr = client
         .pulls(owner, repo)
         .create(
-            "Compliance Fix: Remove PHI logging", // Title FIRST
-            branch_name,                          // Head branch (your feature branch)
-            base_branch,                          // Base branch (where to merge into)
+            format!("Compliance fixes: {} finding(s)", plan.fixed.len()),
+            &branch,
+            base_branch,
         )
-        .body("Automated remediation for PHI leak")
+        .body(pr_body(&plan, &links, source_pr))
         .send()
         .await?;
 
-    Ok(())
+    Ok(pr.html_url.map(|url| url.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location, Severity};
+
+    #[test]
+    fn names_branches_uniquely_and_links_findings() {
+        let mut finding = Finding::new(Analyzer::Regex, "PHI_LOGGING", Severity::High, "x")
+            .at(Location::line("src/a.rs", 4));
+        finding.fingerprint = Some("0123456789abcdef".into());
+        let plan = FixPlan {
+            fixed: vec![finding.clone()],
+            skipped: vec![(finding, "re-scan still reports GHG-PHI-001".into())],
+            ..Default::default()
+        };
+
+        let now = Utc::now();
+        let branch = branch_name(&plan, now);
+        assert!(branch.starts_with("ghosthealth/autofix-"));
+        assert_ne!(
+            branch,
+            branch_name(&plan, now + chrono::Duration::nanoseconds(1))
+        );
+
+        let links = SourceLinks {
+            owner: "acme".into(),
+            repo: "ehr".into(),
+            head_sha: "abc".into(),
+        };
+        let body = pr_body(&plan, &links, Some(7));
+        assert!(body.contains("#7"));
+        assert!(body.contains("[src/a.rs:4](https://github.com/acme/ehr/blob/abc/src/a.rs#L4)"));
+        assert!(body.contains("ID `0123456789ab`"));
+        assert!(body.contains("### Not fixed automatically"));
+    }
 }
@@ -0,0 +1,260 @@
+//! Stable rule IDs, CWE references and remediation guidance per finding category.
+//!
+//! IDs are never reused or renumbered: exporters, suppressions and dashboards
+//! key on them. Retire a rule by leaving its entry in place.
+
+pub struct Rule {
+    pub id: &'static str,
+    pub category: &'static str,
+    pub title: &'static str,
+    pub cwe: Option<u32>,
+    pub remediation: &'static str,
+}
+
+impl Rule {
+    /// Anchor for this rule in `docs/rules.md`.
+    pub fn help_uri(&self) -> String {
+        help_uri(self.id)
+    }
+}
+
+pub fn help_uri(id: &str) -> String {
+    format!(
+        "{}/blob/main/docs/rules.md#{}",
+        env!("CARGO_PKG_REPOSITORY"),
+        id.to_ascii_lowercase()
+    )
+}
+
+const fn rule(
+    id: &'static str,
+    category: &'static str,
+    title: &'static str,
+    cwe: Option<u32>,
+    remediation: &'static str,
+) -> Rule {
+    Rule {
+        id,
+        category,
+        title,
+        cwe,
+        remediation,
+    }
+}
+
+pub const RULES: &[Rule] = &[
+    // PHI handling
+    rule(
+        "GHG-PHI-001",
+        "PHI_LOGGING",
+        "PHI written to logs",
+        Some(532),
+        "Log an opaque identifier instead of the PHI value, or drop the field from the log statement.",
+    ),
+    rule(
+        "GHG-PHI-002",
+        "PHI_EXPOSURE",
+        "PHI exposed outside its boundary",
+        Some(359),
+        "Return only the minimum necessary fields and mask identifiers before they leave the service.",
+    ),
+    rule(
+        "GHG-PHI-003",
+        "DEBUG_TRAIT_LEAK",
+        "PHI reachable through a Debug implementation",
+        Some(532),
+        "Implement Debug by hand and redact PHI fields, or wrap them in a type whose Debug prints a placeholder.",
+    ),
+    rule(
+        "GHG-PHI-004",
+        "SENSITIVE_FUNCTION",
+        "Function handles PHI",
+        Some(359),
+        "Confirm access checks and audit logging cover this function, and keep PHI out of its error paths.",
+    ),
+    rule(
+        "GHG-PHI-005",
+        "SEMGREP",
+        "Semgrep PHI policy match",
+        None,
+        "See the matching Semgrep rule in semgrep/phi_rules.yml for the specific fix.",
+    ),
+    rule(
+        "GHG-PHI-006",
+        "PHI_LITERAL",
+        "PHI value in source",
+        Some(359),
+        "Replace the value with synthetic test data; if it was already committed, purge it from history.",
+    ),
+    // Security controls
+    rule(
+        "GHG-SEC-001",
+        "HARDCODED_SECRET",
+        "Hardcoded credential",
+        Some(798),
+        "Load the secret from the environment or a secrets manager and rotate the exposed value.",
+    ),
+    rule(
+        "GHG-SEC-002",
+        "UNENCRYPTED_TRANSIT",
+        "PHI sent without TLS",
+        Some(319),
+        "Use an https:// endpoint and keep certificate verification enabled.",
+    ),
+    rule(
+        "GHG-SEC-003",
+        "INSECURE_STORAGE",
+        "PHI stored unencrypted",
+        Some(312),
+        "Encrypt the data at rest with AES-256-GCM via the fips module before persisting it.",
+    ),
+    rule(
+        "GHG-SEC-004",
+        "MISSING_ACCESS_CONTROL",
+        "PHI reachable without an access check",
+        Some(862),
+        "Check the caller's authorization for this record before reading or returning it.",
+    ),
+    rule(
+        "GHG-SEC-005",
+        "MISSING_AUDIT_LOG",
+        "PHI access is not audited",
+        Some(778),
+        "Record who accessed which record and when through the audit log.",
+    ),
+    rule(
+        "GHG-SEC-006",
+        "PROMPT_INJECTION",
+        "Text addressed to the AI reviewer",
+        Some(1427),
+        "Remove instructions aimed at automated reviewers from code and comments.",
+    ),
+    // Memory safety
+    rule(
+        "GHG-MEM-001",
+        "UNSAFE_BLOCK",
+        "unsafe block",
+        Some(119),
+        "Replace with a safe API, or document the invariants in a // SAFETY: comment.",
+    ),
+    rule(
+        "GHG-MEM-002",
+        "UNSAFE_FUNCTION",
+        "unsafe fn",
+        Some(119),
+        "Expose a safe wrapper and document the caller's obligations under # Safety.",
+    ),
+    rule(
+        "GHG-MEM-003",
+        "UNSAFE_IMPL",
+        "unsafe impl",
+        Some(119),
+        "Justify why the trait's safety contract holds, or avoid the unsafe impl.",
+    ),
+    rule(
+        "GHG-MEM-004",
+        "UNSAFE_TRAIT",
+        "unsafe trait",
+        Some(119),
+        "Document the contract implementors must uphold.",
+    ),
+    rule(
+        "GHG-MEM-005",
+        "FFI_FUNCTION",
+        "extern \"C\" function",
+        Some(119),
+        "Validate every pointer and length crossing the FFI boundary.",
+    ),
+    rule(
+        "GHG-MEM-006",
+        "EXTERN_BLOCK",
+        "extern block",
+        Some(119),
+        "Wrap foreign functions in safe Rust functions that check their inputs.",
+    ),
+    rule(
+        "GHG-MEM-007",
+        "NO_MANGLE",
+        "#[no_mangle] export",
+        None,
+        "Make sure the exported symbol is intended to be part of the public ABI.",
+    ),
+    rule(
+        "GHG-MEM-008",
+        "RAW_POINTER_DEREF",
+        "Raw pointer dereference",
+        Some(822),
+        "Use references or slices; if a raw pointer is unavoidable, check it is non-null and aligned.",
+    ),
+    rule(
+        "GHG-MEM-009",
+        "TRANSMUTE",
+        "mem::transmute",
+        Some(843),
+        "Use from_ne_bytes, TryFrom or pointer casts with explicit checks instead of transmute.",
+    ),
+    rule(
+        "GHG-MEM-010",
+        "UNSAFE_NOT_FORBIDDEN",
+        "Crate does not forbid unsafe code",
+        None,
+        "Add #![forbid(unsafe_code)] or set unsafe_code = \"forbid\" under [lints.rust].",
+    ),
+    rule(
+        "GHG-MEM-011",
+        "UNSAFE_INCREASE",
+        "Unsafe surface grew",
+        None,
+        "Get a second reviewer to sign off on each new unsafe site.",
+    ),
+    rule(
+        "GHG-MEM-012",
+        "UNSAFE_DEREF",
+        "Dereference in unsafe code",
+        None,
+        "Check the dereferenced value is a valid reference; prefer safe accessors over raw reads.",
+    ),
+    // Review pipeline
+    rule(
+        "GHG-AI-001",
+        "SAFETY_FILTER",
+        "AI review blocked by provider safety filter",
+        None,
+        "Review the change manually; the AI provider declined to analyse it.",
+    ),
+];
+
+pub fn for_category(category: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|r| r.category == category)
+}
+
+pub fn by_id(id: &str) -> Option<&'static Rule> {
+    RULES.iter().find(|r| r.id == id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn ids_and_categories_are_unique() {
+        let ids: HashSet<_> = RULES.iter().map(|r| r.id).collect();
+        let categories: HashSet<_> = RULES.iter().map(|r| r.category).collect();
+        assert_eq!(ids.len(), RULES.len());
+        assert_eq!(categories.len(), RULES.len());
+        assert!(by_id("GHG-SEC-001").is_some_and(|r| r.category == "HARDCODED_SECRET"));
+    }
+
+    #[test]
+    fn every_rule_is_documented() {
+        let docs = include_str!("../docs/rules.md");
+        for rule in RULES {
+            assert!(
+                docs.contains(&format!("### {}\n", rule.id)),
+                "{} missing from docs/rules.md",
+                rule.id
+            );
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! SARIF 2.1.0 export, as consumed by GitHub code scanning and most IDEs.
+
+use crate::models::{AuditResult, Finding, Severity};
+use crate::rules;
+use base64::Engine;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+
+pub const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+pub const TOOL_NAME: &str = "GhostHealth Guard";
+
+/// Key under `partialFingerprints`; bump the suffix if `lifecycle::fingerprint` changes.
+const FINGERPRINT_KEY: &str = "ghosthealthFingerprint/v1";
+
+pub fn to_sarif(result: &AuditResult) -> Value {
+    // One rule entry per rule ID, rated by its most severe finding in this run.
+    let mut rule_severity: BTreeMap<&str, (&Finding, Severity)> = BTreeMap::new();
+    for issue in &result.issues {
+        let entry = rule_severity
+            .entry(issue.rule_id.as_str())
+            .or_insert((issue, issue.severity));
+        entry.1 = entry.1.max(issue.severity);
+    }
+    let index: BTreeMap<&str, usize> = rule_severity
+        .keys()
+        .enumerate()
+        .map(|(i, id)| (*id, i))
+        .collect();
+    let rules: Vec<Value> = rule_severity
+        .values()
+        .map(|(issue, severity)| rule(issue, *severity))
+        .collect();
+
+    let results: Vec<Value> = result
+        .issues
+        .iter()
+        .map(|issue| sarif_result(issue, index.get(issue.rule_id.as_str()).copied()))
+        .collect();
+
+    json!({
+        "$schema": SARIF_SCHEMA,
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": TOOL_NAME,
+                    "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                    "semanticVersion": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "automationDetails": { "id": "ghosthealth-guard/" },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+            "properties": {
+                "status": result.status.as_str(),
+                "riskScore": result.risk_score,
+                "findingSchemaVersion": result.schema_version,
+            },
+        }]
+    })
+}
+
+/// `level` for a severity; GitHub shows `error` as a failing alert.
+pub fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical | Severity::High => "error",
+        Severity::Medium => "warning",
+        Severity::Low | Severity::Info => "note",
+    }
+}
+
+/// GitHub's `security-severity` scale: 9+ critical, 7+ high, 4+ medium.
+fn security_severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Critical => "9.5",
+        Severity::High => "8.0",
+        Severity::Medium => "5.5",
+        Severity::Low => "3.0",
+        Severity::Info => "0.0",
+    }
+}
+
+fn rule(issue: &Finding, severity: Severity) -> Value {
+    let catalog = rules::by_id(&issue.rule_id);
+    let mut tags = vec!["security".to_string(), "compliance".to_string()];
+    tags.extend(issue.cwe.map(|cwe| format!("external/cwe/cwe-{}", cwe)));
+
+    json!({
+        "id": issue.rule_id,
+        "name": pascal_case(&issue.category),
+        "shortDescription": { "text": catalog.map_or(issue.category.as_str(), |r| r.title) },
+        "help": {
+            "text": issue.remediation.clone().unwrap_or_default(),
+        },
+        "helpUri": rules::help_uri(&issue.rule_id),
+        "defaultConfiguration": { "level": level(severity) },
+        "properties": {
+            "tags": tags,
+            "security-severity": security_severity(severity),
+        },
+    })
+}
+
+fn sarif_result(issue: &Finding, rule_index: Option<usize>) -> Value {
+    let mut value = json!({
+        "ruleId": issue.rule_id,
+        "level": level(issue.severity),
+        "message": { "text": issue.message },
+        "properties": {
+            "analyzer": issue.analyzer.as_str(),
+            "confidence": issue.confidence.as_str(),
+            "severity": issue.severity.as_str(),
+        },
+    });
+
+    if let Some(index) = rule_index {
+        value["ruleIndex"] = json!(index);
+    }
+    if let Some(location) = &issue.location {
+        let mut region = json!({ "startLine": location.start_line.max(1) });
+        if let Some(end) = location.end_line {
+            region["endLine"] = json!(end);
+        }
+        if let Some(column) = location.start_column {
+            region["startColumn"] = json!(column);
+        }
+        if let Some(column) = location.end_column {
+            region["endColumn"] = json!(column);
+        }
+        if let Some(snippet) = &issue.snippet {
+            region["snippet"] = json!({ "text": snippet });
+        }
+        value["locations"] = json!([{
+            "physicalLocation": {
+                "artifactLocation": { "uri": location.file, "uriBaseId": "%SRCROOT%" },
+                "region": region,
+            }
+        }]);
+    }
+    if let Some(fingerprint) = &issue.fingerprint {
+        value["partialFingerprints"] = json!({ FINGERPRINT_KEY: fingerprint });
+    }
+    if let Some(cwe) = issue.cwe {
+        value["properties"]["cwe"] = json!(format!("CWE-{}", cwe));
+    }
+    if !issue.controls.is_empty() {
+        value["properties"]["controls"] = json!(issue.controls);
+    }
+    value
+}
+
+fn pascal_case(category: &str) -> String {
+    category
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => {
+                    first.to_ascii_uppercase().to_string() + &chars.as_str().to_ascii_lowercase()
+                }
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+/// Body for `POST /repos/{owner}/{repo}/code-scanning/sarifs`: the SARIF is
+/// gzipped, then base64-encoded.
+pub fn encode_for_upload(sarif: &Value) -> anyhow::Result<String> {
+    let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+    serde_json::to_writer(&mut gzip, sarif)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(gzip.finish()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Location};
+
+    #[test]
+    fn exports_rules_locations_and_fingerprints() {
+        let mut issue = Finding::new(
+            Analyzer::Regex,
+            "PHI_LOGGING",
+            Severity::High,
+            "PHI field logged",
+        )
+        .at(Location::line("src/a.rs", 4))
+        .with_snippet("println!(\"{}\", <SSN>);");
+        issue.fingerprint = Some("abc".into());
+        let result = AuditResult {
+            issues: vec![
+                issue,
+                Finding::new(Analyzer::Ai, "SAFETY_FILTER", Severity::Info, "blocked"),
+            ],
+            ..Default::default()
+        };
+
+        let sarif = to_sarif(&result);
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][0]["id"], "GHG-AI-001");
+        assert_eq!(
+            run["tool"]["driver"]["rules"][1]["properties"]["security-severity"],
+            "8.0"
+        );
+        assert_eq!(run["results"][0]["ruleIndex"], 1);
+        assert_eq!(run["results"][0]["level"], "error");
+        assert_eq!(
+            run["results"][0]["locations"][0]["physicalLocation"]["region"]["startLine"],
+            4
+        );
+        assert_eq!(
+            run["results"][0]["partialFingerprints"][FINGERPRINT_KEY],
+            "abc"
+        );
+        assert!(run["results"][1].get("locations").is_none());
+    }
+
+    #[test]
+    fn uploads_gzipped_base64_sarif() {
+        use std::io::Read;
+        let sarif = to_sarif(&AuditResult::default());
+        let encoded = encode_for_upload(&sarif).unwrap_or_default();
+        let gz = base64::engine::general_purpose::STANDARD
+            .decode(encoded)
+            .unwrap_or_default();
+        assert_eq!(&gz[..2], &[0x1f, 0x8b]);
+        let mut json = String::new();
+        let read = flate2::read::GzDecoder::new(gz.as_slice()).read_to_string(&mut json);
+        assert!(read.is_ok());
+        assert_eq!(serde_json::from_str::<Value>(&json).ok(), Some(sarif));
+    }
+}
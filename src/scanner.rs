@@ -1,23 +1,39 @@
-use crate::models::Issue;
+use crate::diff::{self, LineKind};
+use crate::models::{Analyzer, Finding, Location, Severity};
+use crate::redact;
 use regex::Regex;
+use std::io::Write;
+use std::path::Path;
 use std::process::Command;
 use std::sync::OnceLock;
 
+/// Bump whenever a deterministic rule is added or changed; cached AI verdicts
+/// recorded under an older rule pack are discarded.
+pub const RULE_PACK_VERSION: &str = "2026.10.5";
+
 static PHI_RE: OnceLock<Option<Regex>> = OnceLock::new();
 static LOGGING_RE: OnceLock<Option<Regex>> = OnceLock::new();
-static UNSAFE_RE: OnceLock<Option<Regex>> = OnceLock::new();
 static HARDCODED_RE: OnceLock<Option<Regex>> = OnceLock::new();
+static SSN_RE: OnceLock<Option<Regex>> = OnceLock::new();
 
-pub fn deterministic_scan(diff: &str) -> Vec<Issue> {
-    let mut issues = vec![];
-
-    let phi_pattern = match PHI_RE
+fn phi_re() -> Option<&'static Regex> {
+    PHI_RE
         .get_or_init(|| {
             Regex::new(r"(?i)(ssn|patient_id|patient|heart_rate|dob|diagnosis|medical_record|name)")
                 .ok()
         })
         .as_ref()
-    {
+}
+
+/// Whether `text` names a PHI field, by the same pattern the scan uses.
+pub fn mentions_phi(text: &str) -> bool {
+    phi_re().is_some_and(|re| re.is_match(text))
+}
+
+pub fn deterministic_scan(diff: &str) -> Vec<Finding> {
+    let mut issues = vec![];
+
+    let phi_pattern = match phi_re() {
         Some(re) => re,
         None => return issues,
     };
@@ -28,73 +44,116 @@ pub fn deterministic_scan(diff: &str) -> Vec<Issue> {
         Some(re) => re,
         None => return issues,
     };
-    let unsafe_pattern = match UNSAFE_RE
-        .get_or_init(|| Regex::new(r"\bunsafe\s*\{").ok())
+    let hardcoded_pattern = match HARDCODED_RE
+        .get_or_init(|| Regex::new(r#"(?i)(password|secret|api_key|token)\s*=\s*"[^"]+""#).ok())
         .as_ref()
     {
         Some(re) => re,
         None => return issues,
     };
-    let hardcoded_pattern = match HARDCODED_RE
-        .get_or_init(|| Regex::new(r#"(?i)(password|secret|api_key|token)\s*=\s*"[^"]+""#).ok())
+
+    let ssn_pattern = match SSN_RE
+        .get_or_init(|| Regex::new(r"\b(\d{3})-(\d{2})-(\d{4})\b").ok())
         .as_ref()
     {
         Some(re) => re,
         None => return issues,
     };
 
-    for (i, line) in diff.lines().enumerate() {
-        // PHI being logged
-        if phi_pattern.is_match(line) && logging_pattern.is_match(line) {
-            issues.push(Issue {
-                category: "PHI_LOGGING".into(),
-                severity: "HIGH".into(),
-                message: format!("PHI field logged at line {} — HIPAA violation", i + 1),
-            });
-        }
+    for file in diff::parse(diff) {
+        let path = if file.path.is_empty() {
+            "diff"
+        } else {
+            &file.path
+        };
+        for line in file.hunks.iter().flat_map(|h| h.lines.iter()) {
+            // Removed code can't leak anything new.
+            if line.kind == LineKind::Removed {
+                continue;
+            }
+            let text = line.content.as_str();
+            let location = Location::line(path, line.new_line.unwrap_or_default());
+            let finding = |category: &str, severity: Severity, what: &str| {
+                Finding::new(
+                    Analyzer::Regex,
+                    category,
+                    severity,
+                    format!("{} at {}", what, location),
+                )
+                .at(location.clone())
+                .with_snippet(redact::redact(text.trim()).text)
+            };
 
-        // Unsafe blocks
-        if unsafe_pattern.is_match(line) {
-            issues.push(Issue {
-                category: "UNSAFE_BLOCK".into(),
-                severity: "MEDIUM".into(),
-                message: format!("Unsafe block detected at line {}", i + 1),
-            });
-        }
+            // PHI being logged
+            if phi_pattern.is_match(text) && logging_pattern.is_match(text) {
+                issues.push(finding(
+                    "PHI_LOGGING",
+                    Severity::High,
+                    "PHI field logged — HIPAA violation",
+                ));
+            }
 
-        // Hardcoded secrets
-        if hardcoded_pattern.is_match(line) {
-            issues.push(Issue {
-                category: "HARDCODED_SECRET".into(),
-                severity: "CRITICAL".into(),
-                message: format!("Hardcoded secret detected at line {}", i + 1),
-            });
+            // Hardcoded secrets
+            if hardcoded_pattern.is_match(text) {
+                issues.push(finding(
+                    "HARDCODED_SECRET",
+                    Severity::Critical,
+                    "Hardcoded secret detected",
+                ));
+            }
+
+            // Real-looking SSNs; numbers the SSA never issues (area 000, 666
+            // or 9xx, group 00, serial 0000) are left for test data.
+            let issued = |c: &regex::Captures| {
+                !matches!(&c[1], "000" | "666")
+                    && !c[1].starts_with('9')
+                    && &c[2] != "00"
+                    && &c[3] != "0000"
+            };
+            if ssn_pattern.captures_iter(text).any(|c| issued(&c)) {
+                issues.push(finding(
+                    "PHI_LITERAL",
+                    Severity::High,
+                    "Social Security number in source",
+                ));
+            }
         }
     }
 
     issues
 }
 
-#[allow(dead_code)]
-pub fn run_semgrep() -> Vec<Issue> {
+/// The bundled Semgrep rules, so scans work outside this repository.
+pub const SEMGREP_RULES: &str = include_str!("../semgrep/phi_rules.yml");
+
+/// Runs Semgrep with `SEMGREP_RULES` over `root`. Paths in the findings are
+/// relative to `root`. Empty if Semgrep is not installed or fails.
+pub fn run_semgrep(root: &Path) -> Vec<Finding> {
+    // A new private file, not a fixed name in the shared temp directory.
+    let Ok(mut rules) = tempfile::Builder::new()
+        .prefix("ghosthealth-semgrep-")
+        .suffix(".yml")
+        .tempfile()
+    else {
+        return vec![];
+    };
+    if rules.write_all(SEMGREP_RULES.as_bytes()).is_err() {
+        return vec![];
+    }
     let output = Command::new("semgrep")
-        .args(["--config", "semgrep/phi_rules.yml", "--json", "."])
+        .arg("--config")
+        .arg(rules.path())
+        .args(["--json", "--quiet", "."])
+        .current_dir(root)
         .output();
 
     match output {
-        Ok(out) if out.status.success() => {
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            if stdout.contains("\"results\"") && !stdout.contains("\"results\": []") {
-                return vec![Issue {
-                    category: "SEMGREP".into(),
-                    severity: "HIGH".into(),
-                    message: "Semgrep PHI rule violation detected".into(),
-                }];
-            }
-            vec![]
-        }
-        Ok(_) => {
-            // semgrep ran but found nothing or failed silently
+        Ok(out) if out.status.success() => parse_semgrep(&String::from_utf8_lossy(&out.stdout)),
+        Ok(out) => {
+            tracing::warn!(
+                "semgrep failed: {}",
+                String::from_utf8_lossy(&out.stderr).trim()
+            );
             vec![]
         }
         Err(_) => {
@@ -103,3 +162,43 @@ pub fn run_semgrep() -> Vec<Issue> {
         }
     }
 }
+
+/// Located findings from Semgrep's `--json` output.
+pub fn parse_semgrep(json: &str) -> Vec<Finding> {
+    let Ok(output) = serde_json::from_str::<serde_json::Value>(json) else {
+        return vec![];
+    };
+    let Some(results) = output["results"].as_array() else {
+        return vec![];
+    };
+    results
+        .iter()
+        .filter_map(|r| {
+            let path = r["path"].as_str()?;
+            let path = path.strip_prefix("./").unwrap_or(path);
+            let line = |at: &str| r[at]["line"].as_u64().map(|l| l as u32);
+            let severity = match r["extra"]["severity"].as_str() {
+                Some("ERROR") => Severity::High,
+                Some("WARNING") => Severity::Medium,
+                _ => Severity::Low,
+            };
+            let message = format!(
+                "{} ({})",
+                r["extra"]["message"]
+                    .as_str()
+                    .unwrap_or("Semgrep PHI rule violation"),
+                r["check_id"].as_str().unwrap_or("semgrep")
+            );
+            let location = Location {
+                end_line: line("end"),
+                ..Location::line(path, line("start")?)
+            };
+            let mut finding =
+                Finding::new(Analyzer::Semgrep, "SEMGREP", severity, message).at(location);
+            if let Some(code) = r["extra"]["lines"].as_str() {
+                finding = finding.with_snippet(redact::redact(code.trim()).text);
+            }
+            Some(finding)
+        })
+        .collect()
+}
@@ -0,0 +1,183 @@
+//! The one place a risk score is computed. Deterministic and AI findings go
+//! through the same weights, so the number on a PR can be explained line by line.
+
+use crate::models::Finding;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScoringConfig {
+    /// Points for one finding of each severity.
+    pub severity_weights: BTreeMap<String, f64>,
+    /// Multiplier per finding confidence (`high`, `medium`, `low`).
+    pub confidence: BTreeMap<String, f64>,
+    /// Maximum points a single category can contribute.
+    pub category_caps: BTreeMap<String, f64>,
+    pub default_cap: f64,
+    /// Each further hit in a category counts `decay` times the previous one.
+    pub decay: f64,
+    /// Scores above this are a VIOLATION.
+    pub violation_threshold: u8,
+}
+
+impl Default for ScoringConfig {
+    fn default() -> Self {
+        let map = |pairs: &[(&str, f64)]| {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), *v))
+                .collect::<BTreeMap<_, _>>()
+        };
+        Self {
+            severity_weights: map(&[
+                ("CRITICAL", 40.0),
+                ("HIGH", 25.0),
+                ("MEDIUM", 10.0),
+                ("LOW", 3.0),
+                ("INFO", 0.0),
+            ]),
+            confidence: map(&[("high", 1.0), ("medium", 0.8), ("low", 0.5)]),
+            category_caps: map(&[
+                ("HARDCODED_SECRET", 80.0),
+                ("PHI_LOGGING", 70.0),
+                ("PHI_EXPOSURE", 70.0),
+                ("UNSAFE_BLOCK", 30.0),
+                ("UNSAFE_NOT_FORBIDDEN", 5.0),
+            ]),
+            default_cap: 50.0,
+            decay: 0.5,
+            violation_threshold: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CategoryScore {
+    pub category: String,
+    pub hits: usize,
+    /// Points before the category cap.
+    pub raw: f64,
+    pub points: f64,
+    pub capped: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub total: u8,
+    /// Largest contribution first.
+    pub categories: Vec<CategoryScore>,
+}
+
+impl ScoreBreakdown {
+    /// Markdown table for the PR review.
+    pub fn render_markdown(&self) -> String {
+        if self.categories.is_empty() {
+            return "No findings contributed to the risk score.".to_string();
+        }
+        let mut out = String::from("| Category | Hits | Points |\n|---|---:|---:|\n");
+        for c in &self.categories {
+            let cap = if c.capped {
+                format!(" (capped from {:.0})", c.raw)
+            } else {
+                String::new()
+            };
+            out.push_str(&format!(
+                "| {} | {} | {:.0}{} |\n",
+                c.category, c.hits, c.points, cap
+            ));
+        }
+        out.push_str(&format!("| **Total** | | **{}/100** |", self.total));
+        out
+    }
+}
+
+pub fn score(config: &ScoringConfig, issues: &[Finding]) -> ScoreBreakdown {
+    let mut by_category: BTreeMap<&str, Vec<f64>> = BTreeMap::new();
+    for issue in issues {
+        let weight = config
+            .severity_weights
+            .get(issue.severity.as_str())
+            .copied()
+            .unwrap_or(0.0);
+        let confidence = config
+            .confidence
+            .get(issue.confidence.as_str())
+            .copied()
+            .unwrap_or(1.0);
+        by_category
+            .entry(issue.category.as_str())
+            .or_default()
+            .push(weight * confidence);
+    }
+
+    let mut categories: Vec<CategoryScore> = by_category
+        .into_iter()
+        .map(|(category, mut hits)| {
+            // Strongest hit counts fully, repeats count less and less.
+            hits.sort_by(|a, b| b.total_cmp(a));
+            let raw: f64 = hits
+                .iter()
+                .enumerate()
+                .map(|(i, points)| points * config.decay.powi(i as i32))
+                .sum();
+            let cap = config
+                .category_caps
+                .get(category)
+                .copied()
+                .unwrap_or(config.default_cap);
+            CategoryScore {
+                category: category.to_string(),
+                hits: hits.len(),
+                raw,
+                points: raw.min(cap),
+                capped: raw > cap,
+            }
+        })
+        .collect();
+    categories.sort_by(|a, b| b.points.total_cmp(&a.points));
+
+    let total = categories.iter().map(|c| c.points).sum::<f64>();
+    ScoreBreakdown {
+        total: total.round().clamp(0.0, 100.0) as u8,
+        categories,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Analyzer, Confidence, Severity};
+
+    fn issue(category: &str, severity: Severity) -> Finding {
+        Finding::new(Analyzer::Regex, category, severity, "x")
+    }
+
+    #[test]
+    fn repeated_hits_have_diminishing_returns() {
+        let config = ScoringConfig::default();
+        let one = score(&config, &[issue("PHI_LOGGING", Severity::High)]);
+        let three = score(&config, &vec![issue("PHI_LOGGING", Severity::High); 3]);
+        assert_eq!(one.total, 25);
+        assert_eq!(three.total, 44); // 25 + 12.5 + 6.25
+    }
+
+    #[test]
+    fn caps_and_confidence_apply_per_category() {
+        let config = ScoringConfig::default();
+        let ai = issue("PHI_EXPOSURE", Severity::High).with_confidence(Confidence::Medium);
+        let breakdown = score(
+            &config,
+            &[
+                ai,
+                issue("UNSAFE_NOT_FORBIDDEN", Severity::Low),
+                issue("UNSAFE_NOT_FORBIDDEN", Severity::Medium),
+            ],
+        );
+
+        assert_eq!(breakdown.categories[0].points, 20.0);
+        assert!(breakdown.categories[1].capped);
+        assert_eq!(breakdown.total, 25);
+        assert!(breakdown.render_markdown().contains("capped from 12"));
+    }
+}
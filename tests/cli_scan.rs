@@ -0,0 +1,112 @@
+//! Runs the `ghosthealth` binary against scratch trees and diffs.
+
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+fn ghosthealth(dir: &Path, args: &[&str], stdin: &str) -> anyhow::Result<Output> {
+    use std::io::Write;
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ghosthealth"))
+        .args(args)
+        .current_dir(dir)
+        .env_remove("LLM_PROVIDER")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    if let Some(mut input) = child.stdin.take() {
+        input.write_all(stdin.as_bytes())?;
+    }
+    Ok(child.wait_with_output()?)
+}
+
+#[test]
+fn scans_a_tree_and_exits_on_threshold() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir_all(dir.path().join("src"))?;
+    std::fs::create_dir_all(dir.path().join("target"))?;
+    std::fs::write(
+        dir.path().join("src/main.rs"),
+        "fn main() {\n    println!(\"{}\", patient_ssn);\n}\nunsafe fn raw() {}\n",
+    )?;
+    std::fs::write(dir.path().join("target/gen.rs"), "let password = \"x\";\n")?;
+
+    let out = ghosthealth(dir.path(), &["scan", "--no-semgrep"], "")?;
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(out.status.code(), Some(1));
+    assert!(stdout.contains("GHG-PHI-001  src/main.rs:2"));
+    assert!(stdout.contains("GHG-MEM-002"));
+    assert!(!stdout.contains("target/gen.rs"));
+
+    let out = ghosthealth(
+        dir.path(),
+        &[
+            "scan",
+            "--no-semgrep",
+            "--fail-on",
+            "critical",
+            "-f",
+            "sarif",
+        ],
+        "",
+    )?;
+    assert_eq!(out.status.code(), Some(0));
+    let sarif: serde_json::Value = serde_json::from_slice(&out.stdout)?;
+    assert_eq!(sarif["version"], "2.1.0");
+    Ok(())
+}
+
+#[test]
+fn scans_only_added_lines_of_a_diff() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    std::fs::create_dir_all(dir.path().join("src"))?;
+    std::fs::write(
+        dir.path().join("src/lib.rs"),
+        "unsafe fn old() {}\npub fn new() {}\n",
+    )?;
+    let diff = "diff --git a/src/lib.rs b/src/lib.rs\n--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,1 +1,2 @@\n unsafe fn old() {}\n+pub fn new() {}\n";
+
+    let out = ghosthealth(dir.path(), &["scan", "--no-semgrep", "--diff", "-"], diff)?;
+    assert_eq!(out.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&out.stdout).starts_with("0 finding(s)"));
+
+    let out = ghosthealth(dir.path(), &["scan", "--git", "not-a-range"], "")?;
+    assert_eq!(out.status.code(), Some(2));
+    Ok(())
+}
+
+fn git(dir: &Path, args: &[&str]) -> anyhow::Result<()> {
+    let status = Command::new("git")
+        .args(["-c", "user.name=t", "-c", "user.email=t@example.com"])
+        .args(args)
+        .current_dir(dir)
+        .status()?;
+    anyhow::ensure!(status.success(), "git {:?} failed", args);
+    Ok(())
+}
+
+#[test]
+fn pre_commit_hook_scans_the_index() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    git(dir.path(), &["init", "-q"])?;
+    std::fs::write(
+        dir.path().join("seed.rs"),
+        "pub const SSN: &str = \"123-45-6789\";\n",
+    )?;
+    git(dir.path(), &["add", "seed.rs"])?;
+    // The working tree no longer has the SSN, but the index does.
+    std::fs::write(dir.path().join("seed.rs"), "pub const SSN: &str = \"\";\n")?;
+
+    let out = ghosthealth(dir.path(), &["hook", "pre-commit"], "")?;
+    assert_eq!(out.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&out.stderr).contains("GHG-PHI-006  seed.rs:1"));
+
+    git(dir.path(), &["add", "seed.rs"])?;
+    let out = ghosthealth(dir.path(), &["hook", "pre-commit"], "")?;
+    assert_eq!(out.status.code(), Some(0));
+
+    let out = ghosthealth(dir.path(), &["install-hooks"], "")?;
+    assert_eq!(out.status.code(), Some(0));
+    let script = std::fs::read_to_string(dir.path().join(".git/hooks/pre-commit"))?;
+    assert!(script.contains("hook pre-commit"));
+    Ok(())
+}
@@ -0,0 +1,88 @@
+//! Offline reviewer tooling; only built with `--features dev-tools`.
+
+use ghosthealth_guard::audit::{llm_review, ReviewOptions};
+use ghosthealth_guard::cassette::CassetteReviewer;
+use ghosthealth_guard::llm::{LlmRequest, LlmReviewer, OpenAiReviewer};
+use ghosthealth_guard::llm_stub::{StubReply, StubServer};
+use ghosthealth_guard::models::{AiOutcome, Status};
+
+fn local_reviewer(url: String) -> OpenAiReviewer {
+    OpenAiReviewer {
+        base_url: url,
+        model: "stub".into(),
+        api_key: None,
+        label: "local",
+    }
+}
+
+#[tokio::test]
+async fn stub_server_drives_http_retry_and_block_paths() -> anyhow::Result<()> {
+    let stub = StubServer::start(
+        "127.0.0.1:0",
+        vec![
+            StubReply::Error {
+                status: 429,
+                message: "slow down".into(),
+            },
+            StubReply::Text {
+                content: "Looks fine!".into(),
+            },
+            StubReply::Verdict {
+                status: "CLEAN".into(),
+                risk_score: 0,
+                issues: vec![],
+            },
+            StubReply::Blocked,
+        ],
+    )
+    .await?;
+    let reviewer = local_reviewer(stub.url());
+
+    // 429 is retried by the client, prose is repaired by the pipeline.
+    let result = llm_review(&reviewer, &ReviewOptions::default(), "+ let x = 1;").await?;
+    assert_eq!(stub.requests(), 3);
+    assert_eq!(result.status, Status::Clean);
+    assert_eq!(result.ai_outcome, Some(AiOutcome::Repaired { attempts: 1 }));
+
+    let result = llm_review(&reviewer, &ReviewOptions::default(), "+ let y = 2;").await?;
+    assert_eq!(result.status, Status::Blocked);
+    Ok(())
+}
+
+#[tokio::test]
+async fn cassette_replays_recorded_answers_offline() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let path = dir.path().join("review.json");
+    let diff = "+ let email = \"jane@clinic.org\";\n+ info!(\"{}\", email);\n";
+
+    let stub = StubServer::start(
+        "127.0.0.1:0",
+        vec![StubReply::Text {
+            content: r#"{"status":"VIOLATION","risk_score":75,"issues":[{"category":"PHI_LOGGING","severity":"HIGH","message":"logs jane@clinic.org","file":"src/a.rs","line":2,"quote":"info!(\"{}\", email);"}]}"#.into(),
+        }],
+    )
+    .await?;
+    let recorder = CassetteReviewer::record(Box::new(local_reviewer(stub.url())), &path);
+    let recorded = llm_review(&recorder, &ReviewOptions::default(), diff).await?;
+    drop(stub);
+
+    let fixture = std::fs::read_to_string(&path)?;
+    assert!(!fixture.contains("jane@clinic.org"));
+
+    // A new nonce is generated per request; replay must still match.
+    let player = CassetteReviewer::replay(&path)?;
+    let replayed = llm_review(&player, &ReviewOptions::default(), diff).await?;
+    assert_eq!(replayed.status, recorded.status);
+    assert_eq!(replayed.risk_score, 75);
+    assert_eq!(player.model(), "local/stub");
+
+    // Unrecorded requests fail instead of reaching the network.
+    assert!(player
+        .complete(&LlmRequest {
+            prompt: "something else".into(),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+    Ok(())
+}
@@ -0,0 +1,373 @@
+use async_trait::async_trait;
+use ghosthealth_guard::audit::{llm_review, FailurePolicy, ReviewOptions};
+use ghosthealth_guard::cache::MemoryReviewCache;
+use ghosthealth_guard::llm::{
+    tenant_key_env, AzureOpenAiReviewer, GeminiReviewer, LlmConfig, LlmProvider, LlmRequest,
+    LlmResponse, LlmReviewer, OpenAiReviewer,
+};
+use ghosthealth_guard::models::{AiOutcome, Analyzer, Finding, Location, Severity, Status};
+use ghosthealth_guard::{ai_fix, github};
+use secrecy::SecretString;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+
+const VIOLATION: &str = r#"{"status":"VIOLATION","risk_score":70,"issues":[{"category":"PHI_LOGGING","severity":"HIGH","message":"ssn logged","file":"src/a.rs","line":1,"quote":"println!(\"{}\", ssn);"}]}"#;
+
+#[tokio::test]
+async fn openai_backend_uses_configured_base_url() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/v1/chat/completions")
+        .match_header("authorization", "Bearer sk-test")
+        .with_body(
+            json!({ "choices": [{ "message": { "content": VIOLATION }, "finish_reason": "stop" }] })
+                .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let reviewer = OpenAiReviewer {
+        base_url: server.url(),
+        model: "gpt-4o".into(),
+        api_key: Some(SecretString::new("sk-test".into())),
+        label: "openai",
+    };
+    let result = llm_review(
+        &reviewer,
+        &ReviewOptions::default(),
+        "+ println!(\"{}\", ssn);",
+    )
+    .await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok_and(|r| r.status == Status::Violation && r.risk_score == 70));
+}
+
+#[tokio::test]
+async fn azure_backend_targets_deployment() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/openai/deployments/phi-review/chat/completions")
+        .match_query(mockito::Matcher::UrlEncoded(
+            "api-version".into(),
+            "2024-06-01".into(),
+        ))
+        .match_header("api-key", "azure-key")
+        .with_body(
+            json!({ "choices": [{ "message": { "content": VIOLATION }, "finish_reason": "stop" }] })
+                .to_string(),
+        )
+        .create_async()
+        .await;
+
+    let reviewer = AzureOpenAiReviewer {
+        endpoint: server.url(),
+        deployment: "phi-review".into(),
+        api_version: "2024-06-01".into(),
+        api_key: SecretString::new("azure-key".into()),
+    };
+    let result = llm_review(&reviewer, &ReviewOptions::default(), "+ let x = 1;").await;
+
+    mock.assert_async().await;
+    assert!(result.is_ok_and(|r| r.status == Status::Violation));
+}
+
+#[tokio::test]
+async fn gemini_safety_block_is_reported_as_blocked() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("POST", "/v1beta/models/gemini-1.5-flash:generateContent")
+        .with_body(json!({ "candidates": [{ "finishReason": "SAFETY" }] }).to_string())
+        .create_async()
+        .await;
+
+    let reviewer = GeminiReviewer {
+        base_url: server.url(),
+        model: "gemini-1.5-flash".into(),
+        api_key: SecretString::new("g-key".into()),
+    };
+    let result = llm_review(
+        &reviewer,
+        &ReviewOptions::default(),
+        "+ let ssn = \"123-45-6789\";",
+    )
+    .await;
+
+    assert!(result.is_ok_and(|r| r.status == Status::Blocked));
+}
+
+#[test]
+fn local_provider_needs_no_api_key() {
+    let config = LlmConfig {
+        provider: "ollama".parse().unwrap_or(LlmProvider::OpenAi),
+        model: Some("llama3.1".into()),
+        base_url: Some("http://127.0.0.1:11434".into()),
+        api_key: None,
+        deployment: None,
+        api_version: None,
+    };
+
+    assert!(config
+        .build()
+        .is_ok_and(|reviewer| reviewer.model() == "local/llama3.1"));
+}
+
+#[test]
+fn missing_key_is_an_error_not_a_mock() {
+    let config = LlmConfig {
+        provider: LlmProvider::Gemini,
+        model: None,
+        base_url: None,
+        api_key: None,
+        deployment: None,
+        api_version: None,
+    };
+    assert!(config
+        .build()
+        .is_err_and(|e| e.to_string().contains("GEMINI_API_KEY not set")));
+}
+
+#[test]
+fn tenant_key_env_is_restricted_to_the_tenant_prefix() {
+    assert!(tenant_key_env("GHOSTHEALTH_LLM_KEY_ACME").is_ok());
+    for name in [
+        "DATABASE_URL",
+        "GITHUB_PRIVATE_KEY",
+        "OPENAI_API_KEY",
+        "GHOSTHEALTH_LLM_KEY_",
+        "GHOSTHEALTH_LLM_KEY_acme",
+    ] {
+        assert!(tenant_key_env(name).is_err(), "{} accepted", name);
+    }
+}
+
+/// Replays canned answers in order and records every prompt it was sent.
+struct ScriptedReviewer {
+    answers: Mutex<Vec<&'static str>>,
+    prompts: Mutex<Vec<String>>,
+}
+
+impl ScriptedReviewer {
+    fn new(mut answers: Vec<&'static str>) -> Self {
+        answers.reverse();
+        Self {
+            answers: Mutex::new(answers),
+            prompts: Mutex::new(vec![]),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmReviewer for ScriptedReviewer {
+    fn model(&self) -> String {
+        "scripted".into()
+    }
+
+    async fn complete(&self, request: &LlmRequest) -> anyhow::Result<LlmResponse> {
+        if let Ok(mut prompts) = self.prompts.lock() {
+            prompts.push(request.prompt.clone());
+        }
+        let answer = self
+            .answers
+            .lock()
+            .ok()
+            .and_then(|mut a| a.pop())
+            .unwrap_or("still not json");
+        Ok(LlmResponse::Text(answer.to_string()))
+    }
+}
+
+#[tokio::test]
+async fn invalid_answer_is_repaired_with_validation_errors() {
+    let reviewer = ScriptedReviewer::new(vec![
+        r#"{"status":"VIOLATION","risk_score":70,"issues":[{"category":"VIBES","severity":"HIGH","message":"x","file":"src/a.rs","line":1,"quote":"x"}]}"#,
+        VIOLATION,
+    ]);
+    let result = llm_review(&reviewer, &ReviewOptions::default(), "+ diff").await;
+
+    assert!(result.is_ok_and(|r| r.ai_outcome == Some(AiOutcome::Repaired { attempts: 1 })));
+    let prompts = reviewer
+        .prompts
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_default();
+    assert!(prompts.len() == 2 && prompts[1].contains("issues[0].category"));
+}
+
+#[tokio::test]
+async fn unparseable_answer_fails_closed_by_default() {
+    let reviewer = ScriptedReviewer::new(vec!["Looks fine!", "Really, it's fine."]);
+    let result = llm_review(&reviewer, &ReviewOptions::default(), "+ diff").await;
+
+    assert!(result.is_ok_and(|r| r.status == Status::NeedsReview
+        && matches!(r.ai_outcome, Some(AiOutcome::FailedClosed { .. }))));
+}
+
+#[tokio::test]
+async fn fail_open_and_retry_policies() {
+    let open = ReviewOptions {
+        failure_policy: FailurePolicy::FailOpen,
+        ..Default::default()
+    };
+    let result = llm_review(&ScriptedReviewer::new(vec![]), &open, "+ diff").await;
+    assert!(result
+        .is_ok_and(|r| r.status == Status::Clean
+            && matches!(r.ai_outcome, Some(AiOutcome::FailedOpen { .. }))));
+
+    let retry = ReviewOptions {
+        failure_policy: FailurePolicy::Retry { max_repairs: 3 },
+        ..Default::default()
+    };
+    let reviewer = ScriptedReviewer::new(vec!["no", "nope", "nah", VIOLATION]);
+    let result = llm_review(&reviewer, &retry, "+ diff").await;
+    assert!(result.is_ok_and(|r| r.ai_outcome == Some(AiOutcome::Repaired { attempts: 3 })));
+}
+
+#[tokio::test]
+async fn phi_is_redacted_before_egress() {
+    let reviewer = ScriptedReviewer::new(vec![
+        r#"{"status":"VIOLATION","risk_score":80,"issues":[{"category":"PHI_EXPOSURE","severity":"HIGH","message":"SSN <SSN_1> hardcoded","file":"src/a.rs","line":1,"quote":"let ssn = \"<SSN_1>\";"}]}"#,
+    ]);
+    let diff = "+ let ssn = \"666-44-1111\";\n+ println!(\"{}\", ssn);\n";
+    let result = llm_review(&reviewer, &ReviewOptions::default(), diff).await;
+
+    let prompts = reviewer
+        .prompts
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_default();
+    assert!(prompts.iter().all(|p| !p.contains("666-44-1111")));
+    assert!(result.is_ok_and(|r| r.redactions.get("SSN") == Some(&1)
+        && r.issues[0].message.contains("<SSN_1> at diff line 1")
+        && !serde_json::to_string(&r)
+            .unwrap_or_default()
+            .contains("666-44-1111")));
+}
+
+#[tokio::test]
+async fn large_diffs_are_chunked_and_failed_chunks_reported() {
+    let mut diff = String::new();
+    for file in ["src/a.rs", "src/b.rs"] {
+        diff.push_str(&format!(
+            "diff --git a/{f} b/{f}\n--- a/{f}\n+++ b/{f}\n@@ -1,0 +1,40 @@\n",
+            f = file
+        ));
+        for i in 0..40 {
+            diff.push_str(&format!("+let field_{} = load_record({});\n", i, i));
+        }
+    }
+
+    // Single-flight so the scripted answers line up with chunk order.
+    let options = ReviewOptions {
+        chunk_tokens: Some(600),
+        concurrency: 1,
+        ..Default::default()
+    };
+    // src/b.rs answers with prose, then fails its repair re-prompt.
+    let reviewer = ScriptedReviewer::new(vec![VIOLATION, "nope", "still nope"]);
+    let result = llm_review(&reviewer, &options, &diff).await;
+
+    let calls = reviewer.prompts.lock().map(|p| p.len()).unwrap_or(0);
+    assert_eq!(calls, 3);
+    assert!(result.is_ok_and(|r| r.status == Status::Violation
+        && r.issues.len() == 1
+        && r.failed_chunks == vec!["src/b.rs".to_string()]
+        && matches!(r.ai_outcome, Some(AiOutcome::FailedClosed { .. }))));
+}
+
+#[tokio::test]
+async fn cached_verdicts_skip_the_model_and_are_marked() {
+    let options = ReviewOptions {
+        cache: Some(Arc::new(MemoryReviewCache::default())),
+        ..Default::default()
+    };
+    let first = "diff --git a/src/a.rs b/src/a.rs\nindex 1111111..2222222 100644\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -0,0 +1,1 @@\n+println!(\"{}\", ssn);\n";
+    // Same change cherry-picked elsewhere: different blob hashes and line numbers.
+    let picked = "diff --git a/src/a.rs b/src/a.rs\nindex 3333333..4444444 100644\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -42,0 +48,1 @@\n+println!(\"{}\", ssn);\n";
+
+    let reviewer = ScriptedReviewer::new(vec![VIOLATION]);
+    let fresh = llm_review(&reviewer, &options, first).await;
+    assert!(fresh.is_ok_and(|r| r.issues.iter().all(|i| !i.cached)));
+
+    let replay = llm_review(&reviewer, &options, picked).await;
+    let calls = reviewer.prompts.lock().map(|p| p.len()).unwrap_or(0);
+    assert_eq!(calls, 1);
+    // The cached citation is rebased onto this diff's hunk, so it grounds
+    // in place rather than being relocated.
+    assert!(replay.is_ok_and(|r| r.status == Status::Violation
+        && r.issues.len() == 1
+        && r.issues[0].cached
+        && r.issues[0].location.as_ref().map(|l| l.start_line) == Some(48)
+        && r.grounding
+            .is_some_and(|g| g.grounded == 1 && g.relocated == 0)));
+}
+
+#[tokio::test]
+async fn hallucinated_citations_are_dropped() {
+    let reviewer = ScriptedReviewer::new(vec![
+        r#"{"status":"VIOLATION","risk_score":90,"issues":[
+            {"category":"PHI_LOGGING","severity":"HIGH","message":"ssn logged","file":"src/a.rs","line":1,"quote":"println!(\"{}\", ssn);"},
+            {"category":"HARDCODED_SECRET","severity":"CRITICAL","message":"key in code","file":"src/config.rs","line":7,"quote":"const KEY: &str = \"abc\";"}
+        ]}"#,
+    ]);
+    let diff = "diff --git a/src/a.rs b/src/a.rs\n--- a/src/a.rs\n+++ b/src/a.rs\n@@ -0,0 +1,1 @@\n+println!(\"{}\", ssn);\n";
+    let result = llm_review(&reviewer, &ReviewOptions::default(), diff).await;
+
+    assert!(result.is_ok_and(|r| r.issues.len() == 1
+        && r.issues[0].category == "PHI_LOGGING"
+        && r.grounding
+            .is_some_and(|g| g.dropped == 1 && g.rate() == 0.5)));
+}
+
+#[tokio::test]
+async fn ai_only_fixes_are_confirmed_by_re_reviewing_the_patch() {
+    let source = "fn store(cache: &mut Cache, record: &Record) {\n    cache.insert(record.id, record.ssn.clone());\n}\n";
+    let finding = Finding::new(
+        Analyzer::Ai,
+        "INSECURE_STORAGE",
+        Severity::High,
+        "SSN cached in clear text",
+    )
+    .at(Location::line("src/store.rs", 2));
+    let fix = r#"{"replacement": "    cache.insert(record.id, encrypt(&record.ssn));", "description": "Encrypt the SSN before caching it"}"#;
+
+    let reviewer = ScriptedReviewer::new(vec![
+        fix,
+        r#"{"status":"CLEAN","risk_score":0,"issues":[]}"#,
+    ]);
+    let accepted = ai_fix::propose(&reviewer, source, &finding).await;
+    assert!(accepted.is_ok_and(|f| f
+        .description
+        .ends_with("(AI-proposed, verified by AI re-review)")));
+    let prompts = reviewer
+        .prompts
+        .lock()
+        .map(|p| p.clone())
+        .unwrap_or_default();
+    assert!(
+        prompts.len() == 2
+            && prompts[1].contains("+    cache.insert(record.id, encrypt(&record.ssn));")
+    );
+
+    let reviewer = ScriptedReviewer::new(vec![
+        fix,
+        r#"{"status":"VIOLATION","risk_score":60,"issues":[{"category":"INSECURE_STORAGE","severity":"HIGH","message":"still cached","file":"src/store.rs","line":2,"quote":"cache.insert(record.id, encrypt(&record.ssn));"}]}"#,
+    ]);
+    let rejected = ai_fix::propose(&reviewer, source, &finding).await;
+    assert!(rejected.is_err_and(|e| e.contains("still reports INSECURE_STORAGE")));
+
+    // No valid re-review answer, no fix.
+    let reviewer = ScriptedReviewer::new(vec![fix]);
+    let rejected = ai_fix::propose(&reviewer, source, &finding).await;
+    assert!(rejected.is_err_and(|e| e.contains("no valid answer")));
+}
+
+#[tokio::test]
+async fn violation_without_grounded_findings_does_not_block() {
+    let diff = "diff --git a/src/b.rs b/src/b.rs\n--- a/src/b.rs\n+++ b/src/b.rs\n@@ -1,0 +1,1 @@\n+let total = count + 1;\n";
+    let reviewer = ScriptedReviewer::new(vec![VIOLATION]);
+    let result = github::process_diff(&reviewer, &ReviewOptions::default(), diff).await;
+    assert!(result.is_ok_and(|r| r.status == Status::Clean
+        && r.issues.is_empty()
+        && r.grounding.is_some_and(|g| g.dropped == 1)));
+}
@@ -0,0 +1,110 @@
+//! Golden-file tests for the report writers. Run with `UPDATE_GOLDEN=1` to
+//! rewrite the files under `tests/fixtures/reports/` after an intended change.
+
+use ghosthealth_guard::controls;
+use ghosthealth_guard::export::{self, Format};
+use ghosthealth_guard::lifecycle;
+use ghosthealth_guard::models::{
+    Analyzer, AuditResult, Confidence, Finding, Location, Severity, Status,
+};
+use std::path::PathBuf;
+
+fn sample() -> AuditResult {
+    let mut issues = vec![
+        Finding::new(
+            Analyzer::Regex,
+            "PHI_LOGGING",
+            Severity::High,
+            "PHI field logged — HIPAA violation at src/patient.rs:4",
+        )
+        .at(Location::line("src/patient.rs", 4))
+        .with_snippet("println!(\"{}\", <SSN_1>);"),
+        Finding::new(
+            Analyzer::Ast,
+            "UNSAFE_BLOCK",
+            Severity::Medium,
+            "unsafe block at src/ffi.rs:12",
+        )
+        .at(Location {
+            file: "src/ffi.rs".into(),
+            start_line: 12,
+            end_line: Some(14),
+            start_column: Some(5),
+            end_column: Some(6),
+        }),
+        Finding::new(
+            Analyzer::Ai,
+            "MISSING_AUDIT_LOG",
+            Severity::Medium,
+            "Reads <record>, \"chart\" & notes without an audit entry",
+        )
+        .at(Location::line("src/patient.rs", 20))
+        .with_snippet("let chart = repo.load(id)?;")
+        .with_confidence(Confidence::Medium),
+        Finding::new(
+            Analyzer::Ast,
+            "UNSAFE_NOT_FORBIDDEN",
+            Severity::Low,
+            "=crate root does not forbid unsafe code",
+        ),
+    ];
+    controls::annotate(&mut issues);
+    lifecycle::assign_fingerprints(&mut issues);
+
+    AuditResult {
+        status: Status::Violation,
+        risk_score: 42,
+        issues,
+        ..Default::default()
+    }
+}
+
+fn check(format: Format, file: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/reports")
+        .join(file);
+    let rendered = export::render(&sample(), format).unwrap_or_default();
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        assert!(std::fs::write(&path, &rendered).is_ok());
+        return;
+    }
+    let golden = std::fs::read_to_string(&path).unwrap_or_default();
+    assert_eq!(rendered, golden, "{} does not match {}", format, file);
+}
+
+#[test]
+fn json_matches_golden() {
+    check(Format::Json, "report.json");
+}
+
+#[test]
+fn sarif_matches_golden() {
+    check(Format::Sarif, "report.sarif");
+}
+
+#[test]
+fn junit_matches_golden() {
+    check(Format::Junit, "report.junit.xml");
+}
+
+#[test]
+fn gitlab_matches_golden() {
+    check(Format::Gitlab, "report.gitlab.json");
+}
+
+#[test]
+fn checkstyle_matches_golden() {
+    check(Format::Checkstyle, "report.checkstyle.xml");
+}
+
+#[test]
+fn csv_matches_golden() {
+    check(Format::Csv, "report.csv");
+}
+
+#[test]
+fn clean_scan_is_one_passing_testcase() {
+    let junit = export::render(&AuditResult::default(), Format::Junit).unwrap_or_default();
+    assert!(junit.contains("tests=\"1\" failures=\"0\""));
+}